@@ -0,0 +1,3 @@
+fn main() -> std::process::ExitCode {
+    xrest_lib::cli::main()
+}
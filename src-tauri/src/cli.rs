@@ -0,0 +1,564 @@
+use crate::domains::auth::profile::AuthProfile;
+use crate::domains::data_file::load_data_file;
+use crate::domains::load_test::{self, LoadMode, LoadTestOptions, LoadTestReport};
+use crate::domains::runner::{self, RunOptions, RunRequestResult, RunSummary, WorkflowOptions};
+use crate::domains::service::service::ServiceDomain;
+use crate::io::{HttpClient, PooledHttpClient, RealFileSystem, RealHttpClient, SecretStore};
+use crate::services::RequestService;
+use crate::types::Service;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Prefix of environment variables holding secrets, e.g. `XREST_SECRET_API_KEY` for `api-key`
+pub const SECRET_ENV_PREFIX: &str = "XREST_SECRET_";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "xrest-cli",
+    version,
+    about = "Run xrest services without the app"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the endpoints of a service directory against an environment
+    Run(RunArgs),
+    /// Run a workflow of a service directory against an environment
+    Workflow(WorkflowArgs),
+    /// Load test one endpoint of a service directory
+    Load(LoadArgs),
+}
+
+/// Arguments shared by every command
+#[derive(Debug, clap::Args)]
+pub struct ServiceArgs {
+    /// Directory holding service.yaml
+    pub service_dir: PathBuf,
+    /// Environment to run against; defaults to the service's selected environment
+    #[arg(short, long)]
+    pub env: Option<String>,
+    /// Override a variable, as NAME=VALUE
+    #[arg(long = "var", value_parser = parse_var)]
+    pub vars: Vec<(String, String)>,
+    /// YAML or JSON map of secret keys to values
+    #[arg(long)]
+    pub secrets_file: Option<PathBuf>,
+    /// The app's settings.yaml, for the auth profiles services refer to
+    #[arg(long)]
+    pub settings: Option<PathBuf>,
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+    /// Write the report to a file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub service: ServiceArgs,
+    /// Endpoint id to run, repeatable; runs every endpoint when omitted
+    #[arg(long = "endpoint")]
+    pub endpoints: Vec<String>,
+    /// Send requests concurrently
+    #[arg(long)]
+    pub parallel: bool,
+    /// CSV or JSON data file; the endpoints run once per row
+    #[arg(long)]
+    pub data: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct WorkflowArgs {
+    #[command(flatten)]
+    pub service: ServiceArgs,
+    /// Id of the workflow, the name of its file in `workflows/`
+    #[arg(short, long)]
+    pub workflow: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct LoadArgs {
+    #[command(flatten)]
+    pub service: ServiceArgs,
+    /// Id of the endpoint to load test
+    #[arg(long)]
+    pub endpoint: String,
+    /// Total number of requests
+    #[arg(short = 'n', long, default_value_t = 100, conflicts_with = "rps")]
+    pub requests: usize,
+    /// Requests in flight at a time
+    #[arg(short, long, default_value_t = 10, conflicts_with = "rps")]
+    pub concurrency: usize,
+    /// Requests started per second, instead of a number of requests
+    #[arg(long)]
+    pub rps: Option<u32>,
+    /// Seconds to keep up the rate
+    #[arg(long, requires = "rps")]
+    pub duration: Option<u64>,
+    /// Allow environments marked unsafe
+    #[arg(long)]
+    pub allow_unsafe: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Junit,
+}
+
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("Expected NAME=VALUE, got '{}'", s))
+}
+
+/// Secrets from the environment, falling back to a secrets file
+pub struct CliSecrets {
+    env: HashMap<String, String>,
+    file: HashMap<String, String>,
+}
+
+impl CliSecrets {
+    pub fn new(env: HashMap<String, String>, file: HashMap<String, String>) -> Self {
+        Self { env, file }
+    }
+
+    pub fn load(secrets_file: Option<&PathBuf>) -> Result<Self, String> {
+        let file = match secrets_file {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                serde_yaml::from_str(&content)
+                    .map_err(|e| format!("Invalid secrets file {}: {}", path.display(), e))?
+            }
+            None => HashMap::new(),
+        };
+        Ok(Self::new(std::env::vars().collect(), file))
+    }
+}
+
+/// `api-key` is read from `XREST_SECRET_API_KEY`
+pub fn secret_env_var(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}{}", SECRET_ENV_PREFIX, name)
+}
+
+impl SecretStore for CliSecrets {
+    fn get_secret(&self, key: &str) -> Result<String, String> {
+        let var = secret_env_var(key);
+        self.env
+            .get(&var)
+            .or_else(|| self.file.get(key))
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Secret '{}' not set; export {} or add it to the secrets file",
+                    key, var
+                )
+            })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfilesFile {
+    #[serde(default)]
+    auth_profiles: Vec<AuthProfile>,
+}
+
+fn load_profiles(settings: Option<&PathBuf>) -> Result<Vec<AuthProfile>, String> {
+    let Some(path) = settings else {
+        return Ok(vec![]);
+    };
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let file: ProfilesFile = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Invalid settings file {}: {}", path.display(), e))?;
+    Ok(file.auth_profiles)
+}
+
+/// Entry point of the `xrest-cli` binary
+pub fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let passed = match cli.command {
+        Command::Run(args) => runtime.block_on(run(&args)).map(|s| s.failed == 0),
+        Command::Workflow(args) => runtime.block_on(run_workflow(&args)).map(|s| s.failed == 0),
+        Command::Load(args) => runtime
+            .block_on(load_test(&args))
+            .map(|r| load_test_passed(&r)),
+    };
+    match passed {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// What every command needs to send requests of a service
+struct Context {
+    service: Service,
+    profiles: Vec<AuthProfile>,
+    secrets: CliSecrets,
+}
+
+impl Context {
+    fn load(args: &ServiceArgs) -> Result<Self, String> {
+        let service_dir = args.service_dir.to_string_lossy();
+        Ok(Self {
+            service: ServiceDomain::new(&RealFileSystem).load_service(&service_dir)?,
+            profiles: load_profiles(args.settings.as_ref())?,
+            secrets: CliSecrets::load(args.secrets_file.as_ref())?,
+        })
+    }
+
+    fn request_service(&self) -> RequestService<'_> {
+        self.request_service_with(&RealHttpClient)
+    }
+
+    fn request_service_with<'a>(&'a self, http: &'a dyn HttpClient) -> RequestService<'a> {
+        RequestService::new(http, None).with_secrets(&self.secrets)
+    }
+}
+
+/// Runs the service and writes the report
+pub async fn run(args: &RunArgs) -> Result<RunSummary, String> {
+    let context = Context::load(&args.service)?;
+    let data = match &args.data {
+        Some(path) => load_data_file(&RealFileSystem, path)?,
+        None => vec![],
+    };
+    let options = RunOptions {
+        endpoint_ids: args.endpoints.clone(),
+        environment: args.service.env.clone(),
+        parallel: args.parallel,
+        variables: args.service.vars.iter().cloned().collect(),
+        data,
+    };
+    let summary = runner::run(
+        &context.request_service(),
+        &context.service,
+        &context.profiles,
+        &options,
+        &|_| {},
+    )
+    .await?;
+    write_report(&args.service, &context.service.name, &summary)?;
+    Ok(summary)
+}
+
+/// Runs a workflow of the service and writes the report
+pub async fn run_workflow(args: &WorkflowArgs) -> Result<RunSummary, String> {
+    let context = Context::load(&args.service)?;
+    let service_dir = args.service.service_dir.to_string_lossy();
+    let workflow = ServiceDomain::new(&RealFileSystem)
+        .load_workflows(&service_dir)?
+        .into_iter()
+        .find(|w| w.id == args.workflow)
+        .ok_or_else(|| format!("Workflow '{}' not found in {}", args.workflow, service_dir))?;
+    let options = WorkflowOptions {
+        environment: args.service.env.clone(),
+        variables: args.service.vars.iter().cloned().collect(),
+    };
+    let summary = runner::run_workflow(
+        &context.request_service(),
+        &context.service,
+        &context.profiles,
+        &workflow,
+        &options,
+        &|_| {},
+    )
+    .await?;
+    let title = format!("{} / {}", context.service.name, workflow.name);
+    write_report(&args.service, &title, &summary)?;
+    Ok(summary)
+}
+
+/// Load tests one endpoint and writes the report
+pub async fn load_test(args: &LoadArgs) -> Result<LoadTestReport, String> {
+    if args.service.format == OutputFormat::Junit {
+        return Err("JUnit reports are not available for load tests".to_string());
+    }
+    let context = Context::load(&args.service)?;
+    let mode = match args.rps {
+        Some(rps) => LoadMode::Rate {
+            rps,
+            duration_secs: args.duration.unwrap_or(10),
+        },
+        None => LoadMode::Count {
+            requests: args.requests,
+            concurrency: args.concurrency,
+        },
+    };
+    let options = LoadTestOptions {
+        endpoint_id: args.endpoint.clone(),
+        environment: args.service.env.clone(),
+        variables: args.service.vars.iter().cloned().collect(),
+        mode,
+        allow_unsafe: args.allow_unsafe,
+    };
+    let http = PooledHttpClient::new();
+    let report = load_test::run(
+        &context.request_service_with(&http),
+        &context.service,
+        &context.profiles,
+        &options,
+        &|_| {},
+    )
+    .await?;
+    let output = match args.service.format {
+        OutputFormat::Json => serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?,
+        _ => load_text_report(&context.service.name, &report),
+    };
+    write_output(&args.service, output)?;
+    Ok(report)
+}
+
+/// A load test fails on errors, dropped requests and server errors
+pub fn load_test_passed(report: &LoadTestReport) -> bool {
+    report.errors == 0
+        && report.dropped == 0
+        && report.status_codes.keys().all(|status| *status < 500)
+}
+
+pub fn load_text_report(service_name: &str, report: &LoadTestReport) -> String {
+    let latency = &report.latency;
+    let mut out = format!(
+        "{} / {} ({})\n",
+        service_name,
+        report.endpoint_id,
+        report.environment.as_deref().unwrap_or("no environment")
+    );
+    out.push_str(&format!(
+        "  {} requests in {} ms, {:.1} req/s, {} errors\n",
+        report.total, report.duration_ms, report.rps, report.errors
+    ));
+    if report.dropped > 0 {
+        out.push_str(&format!(
+            "  {} requests dropped, too many were in flight\n",
+            report.dropped
+        ));
+    }
+    out.push_str(&format!(
+        "  latency ms: min {} / p50 {} / p90 {} / p99 {} / max {}\n",
+        latency.min, latency.p50, latency.p90, latency.p99, latency.max
+    ));
+    for (status, count) in &report.status_codes {
+        out.push_str(&format!("  {}: {}\n", status, count));
+    }
+    for (message, count) in &report.error_messages {
+        out.push_str(&format!("  error x{}: {}\n", count, message));
+    }
+    out
+}
+
+fn write_report(args: &ServiceArgs, title: &str, summary: &RunSummary) -> Result<(), String> {
+    let report = match args.format {
+        OutputFormat::Text => text_report(title, summary),
+        OutputFormat::Json => {
+            // Bodies and headers of every request don't belong in CI logs
+            let mut summary = summary.clone();
+            for result in &mut summary.results {
+                result.exchange = None;
+            }
+            serde_json::to_string_pretty(&summary).map_err(|e| e.to_string())?
+        }
+        OutputFormat::Junit => junit_report(title, summary),
+    };
+    write_output(args, report)
+}
+
+fn write_output(args: &ServiceArgs, report: String) -> Result<(), String> {
+    match &args.output {
+        Some(path) => std::fs::write(path, report)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        None => {
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// Why a request failed, one line per reason
+fn failure_reasons(result: &RunRequestResult) -> Vec<String> {
+    let mut reasons: Vec<String> = result.error.iter().cloned().collect();
+    reasons.extend(result.script_errors.iter().cloned());
+    reasons.extend(
+        result
+            .assertions
+            .iter()
+            .filter(|a| !a.passed)
+            .map(|a| a.message.clone()),
+    );
+    if reasons.is_empty() && !result.passed {
+        if let Some(status) = result.status {
+            reasons.push(format!("Status is {}", status));
+        }
+    }
+    reasons
+}
+
+/// `Row 2 (tenant=acme, user=bob)` for the second data row
+fn row_label(summary: &RunSummary, iteration: usize) -> String {
+    let mut values: Vec<String> = summary
+        .iterations
+        .get(iteration)
+        .map(|i| i.row.iter().map(|(k, v)| format!("{}={}", k, v)).collect())
+        .unwrap_or_default();
+    values.sort();
+    format!("Row {} ({})", iteration + 1, values.join(", "))
+}
+
+pub fn text_report(service_name: &str, summary: &RunSummary) -> String {
+    let mut out = format!(
+        "{} ({})\n",
+        service_name,
+        summary.environment.as_deref().unwrap_or("no environment")
+    );
+    let mut iteration = None;
+    for result in &summary.results {
+        if result.iteration != iteration {
+            iteration = result.iteration;
+            if let Some(index) = iteration {
+                out.push_str(&format!("  {}\n", row_label(summary, index)));
+            }
+        }
+        let status = result
+            .status
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "---".to_string());
+        let outcome = if result.skipped {
+            "SKIP"
+        } else if result.passed {
+            "PASS"
+        } else {
+            "FAIL"
+        };
+        out.push_str(&format!(
+            "  {} {:<7} {} {} ({} ms)\n",
+            outcome,
+            result.method,
+            display_name(result),
+            status,
+            result.time_elapsed
+        ));
+        for reason in failure_reasons(result) {
+            out.push_str(&format!("       {}\n", reason));
+        }
+    }
+    let failed_rows: Vec<String> = summary
+        .iterations
+        .iter()
+        .filter(|i| i.failed > 0)
+        .map(|i| (i.index + 1).to_string())
+        .collect();
+    if !failed_rows.is_empty() {
+        out.push_str(&format!("Failed rows: {}\n", failed_rows.join(", ")));
+    }
+    let skipped = if summary.skipped > 0 {
+        format!("{} skipped, ", summary.skipped)
+    } else {
+        String::new()
+    };
+    out.push_str(&format!(
+        "{} passed, {} failed, {}{} total in {} ms\n",
+        summary.passed, summary.failed, skipped, summary.total, summary.time_elapsed
+    ));
+    out
+}
+
+/// Endpoint name, prefixed with the step id in workflow runs
+fn display_name(result: &RunRequestResult) -> String {
+    match &result.step_id {
+        Some(step) => format!("{}: {}", step, result.name),
+        None => result.name.clone(),
+    }
+}
+
+pub fn junit_report(service_name: &str, summary: &RunSummary) -> String {
+    let seconds = |ms: u64| format!("{:.3}", ms as f64 / 1000.0);
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuites tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+        summary.total,
+        summary.failed,
+        seconds(summary.time_elapsed)
+    ));
+    out.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+        xml_escape(service_name),
+        summary.total,
+        summary.failed,
+        summary.skipped,
+        seconds(summary.time_elapsed)
+    ));
+    for result in &summary.results {
+        let mut name = format!("{} {}", result.method, display_name(result));
+        if let Some(index) = result.iteration {
+            name.push_str(&format!(" [row {}]", index + 1));
+        }
+        out.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\" time=\"{}\"",
+            xml_escape(service_name),
+            xml_escape(&name),
+            seconds(result.time_elapsed)
+        ));
+        if result.skipped {
+            out.push_str(">\n      <skipped/>\n    </testcase>\n");
+            continue;
+        }
+        if result.passed {
+            out.push_str("/>\n");
+            continue;
+        }
+        let reasons = failure_reasons(result);
+        let mut details = reasons.join("\n");
+        if let Some(index) = result.iteration {
+            details = format!("{}\n{}", row_label(summary, index), details);
+        }
+        out.push_str(">\n");
+        out.push_str(&format!(
+            "      <failure message=\"{}\">{}</failure>\n",
+            xml_escape(reasons.first().map(String::as_str).unwrap_or("Failed")),
+            xml_escape(&details)
+        ));
+        out.push_str("    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -1,8 +1,17 @@
+use crate::domains::har::{HarEntrySummary, HarExportOptions, HarImportOptions};
+use crate::domains::import::ImportResult;
+use crate::domains::load_test::{LoadTestOptions, LoadTestProgress, LoadTestReport};
+use crate::domains::mock_server::{MockRequestLog, MockServerInfo, MockServerOptions};
+use crate::domains::monitor::{Monitor, MonitorResult};
+use crate::domains::proxy::{ProxyInfo, ProxyMode, ProxyOptions};
+use crate::domains::runner::{RunOptions, RunProgress, RunSummary, WorkflowOptions};
+use crate::domains::service::workflow::Workflow;
 use crate::io::{RealFileSystem, RealHttpClient};
 use crate::services::{ConfigService, RequestService};
 use crate::types::{
-    AuthConfig, AuthType, Endpoint, EndpointMetadata, EnvironmentConfig, HistoryEntry, NameValue,
-    PreflightConfig, QResponse, RequestTab, Service, UserSettings,
+    AuthConfig, AuthType, Endpoint, EndpointMetadata, EnvironmentConfig, ExampleResponse,
+    HistoryEntry, NameValue, PreflightConfig, PreflightMode, QResponse, RequestTab, Scripts,
+    Service, UserSettings,
 };
 use openapiv3::OpenAPI;
 
@@ -103,26 +112,24 @@ pub fn save_tab_state(app: AppHandle, state: crate::types::TabState) -> Result<(
 #[tauri::command]
 pub async fn send_request(app: AppHandle, mut tab: RequestTab) -> Result<QResponse, String> {
     if let Some(sid) = &tab.service_id {
-        let app_handle = app.clone();
-        let sid_clone = sid.clone();
-
-        let service_config = tokio::task::spawn_blocking(move || {
-            let config = ConfigService::new(&RealFileSystem);
-            let settings = config.load_settings(&app_handle).ok()?;
-            let stub = settings.services.iter().find(|s| s.id == sid_clone)?;
-            config.load_service(&stub.directory).ok()
-        })
-        .await
-        .map_err(|e| e.to_string())?;
+        let service_config = load_service_config(&app, sid).await?;
 
         if let Some(service) = service_config {
-            if tab.auth.r#type == "none" {
-                tab.auth = service.auth;
-            }
-            if !tab.preflight.enabled {
-                tab.preflight = service.preflight;
+            if tab.auth_profile_id.is_none() {
+                tab.auth_profile_id = crate::domains::auth::profile::referenced_profile_id(
+                    &service,
+                    tab.endpoint_id.as_deref(),
+                );
             }
+            let profile = match &tab.auth_profile_id {
+                Some(profile_id) => Some(load_auth_profile(&app, profile_id).await?),
+                None => None,
+            };
+            service.apply_defaults(&mut tab, profile.as_ref());
         }
+    } else if let Some(profile_id) = &tab.auth_profile_id {
+        let profile = load_auth_profile(&app, profile_id).await?;
+        tab.inherit_auth(profile.auth, profile.preflight);
     }
 
     let cache_path = crate::domains::auth::get_token_cache_path(&app).ok();
@@ -151,6 +158,7 @@ pub async fn send_request(app: AppHandle, mut tab: RequestTab) -> Result<QRespon
         time_elapsed: response.time_elapsed,
         size: response.size,
         created_at: chrono::Utc::now().to_rfc3339(),
+        assertions: response.assertions.clone(),
     };
 
     let app_handle = app.clone();
@@ -163,6 +171,38 @@ pub async fn send_request(app: AppHandle, mut tab: RequestTab) -> Result<QRespon
     Ok(response)
 }
 
+/// Loads a service by id from the registered services, off the async runtime
+async fn load_service_config(app: &AppHandle, service_id: &str) -> Result<Option<Service>, String> {
+    let app_handle = app.clone();
+    let sid = service_id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let config = ConfigService::new(&RealFileSystem);
+        let settings = config.load_settings(&app_handle).ok()?;
+        let stub = settings.services.iter().find(|s| s.id == sid)?;
+        config.load_service(&stub.directory).ok()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+async fn load_auth_profile(
+    app: &AppHandle,
+    profile_id: &str,
+) -> Result<crate::domains::auth::profile::AuthProfile, String> {
+    let app_handle = app.clone();
+    let id = profile_id.to_string();
+
+    tokio::task::spawn_blocking(move || {
+        let settings = ConfigService::new(&RealFileSystem).load_settings(&app_handle)?;
+        crate::domains::auth::profile::find_profile(&settings.auth_profiles, &id)
+            .cloned()
+            .ok_or_else(|| format!("Auth profile '{}' not found", id))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 pub fn close_splashscreen(app: AppHandle) {
     use tauri::Manager;
@@ -285,6 +325,8 @@ pub async fn import_swagger(
             EnvironmentConfig {
                 name: "DEV".to_string(),
                 is_unsafe: false,
+                auth: None,
+                preflight: None,
                 variables: vec![NameValue {
                     name: "BASE_URL".to_string(),
                     value: base_url.clone(),
@@ -295,6 +337,8 @@ pub async fn import_swagger(
             EnvironmentConfig {
                 name: "STAGE".to_string(),
                 is_unsafe: false,
+                auth: None,
+                preflight: None,
                 variables: vec![NameValue {
                     name: "BASE_URL".to_string(),
                     value: base_url.clone(),
@@ -305,6 +349,8 @@ pub async fn import_swagger(
             EnvironmentConfig {
                 name: "PROD".to_string(),
                 is_unsafe: true,
+                auth: None,
+                preflight: None,
                 variables: vec![NameValue {
                     name: "BASE_URL".to_string(),
                     value: base_url,
@@ -315,16 +361,7 @@ pub async fn import_swagger(
         ],
         is_authenticated: false,
         auth_type: Some(AuthType::None),
-        auth: AuthConfig {
-            r#type: "none".to_string(),
-            active: true,
-            basic_user: "".to_string(),
-            basic_pass: "".to_string(),
-            bearer_token: "".to_string(),
-            api_key_name: "".to_string(),
-            api_key_value: "".to_string(),
-            api_key_location: "header".to_string(),
-        },
+        auth: AuthConfig::default(),
         preflight: PreflightConfig {
             enabled: false,
             method: "POST".to_string(),
@@ -339,11 +376,17 @@ pub async fn import_swagger(
             cache_duration_unit: "seconds".to_string(),
             token_key: "".to_string(),
             token_header: None,
+            secret_token: false,
+            refresh_before_expiry: None,
+            steps: vec![],
+            mode: PreflightMode::Token,
         },
         endpoints,
         directory: directory.clone(),
         selected_environment: Some("DEV".to_string()),
         git_url: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
     };
 
     let config_service = ConfigService::new(&RealFileSystem);
@@ -382,7 +425,7 @@ pub async fn import_curl(
         service_id.clone(),
         &curl_command,
         service.is_authenticated,
-        service.auth_type.as_ref().map(|at| at.to_string()),
+        service.auth_type.clone(),
     )?;
 
     let endpoint_name = endpoint.name.clone();
@@ -392,11 +435,174 @@ pub async fn import_curl(
     Ok(service)
 }
 
+/// Imports a Postman v2.1 collection export with any number of environment exports
+#[tauri::command]
+pub async fn import_postman(
+    app: AppHandle,
+    directory: String,
+    collection_file: String,
+    environment_files: Vec<String>,
+    name: Option<String>,
+) -> Result<ImportResult, String> {
+    let config_service = ConfigService::new(&RealFileSystem);
+    let read = |file: &String| {
+        config_service
+            .fs
+            .read_to_string(std::path::Path::new(file))
+            .map_err(|e| format!("Failed to read {}: {}", file, e))
+    };
+    let collection = read(&collection_file)?;
+    let environments = environment_files.iter().map(read).collect::<Result<Vec<_>, _>>()?;
+
+    let mut result = crate::domains::import::postman::import_collection(
+        &collection,
+        &environments,
+        &crate::domains::import::new_service_id(),
+        &directory,
+    )?;
+    if let Some(name) = name.filter(|n| !n.trim().is_empty()) {
+        result.service.name = name;
+    }
+    save_imported_service(&app, &mut result.service, "Postman")?;
+    Ok(result)
+}
+
+/// Imports an Insomnia v4 export, JSON or YAML
+#[tauri::command]
+pub async fn import_insomnia(
+    app: AppHandle,
+    directory: String,
+    file: String,
+    name: Option<String>,
+) -> Result<ImportResult, String> {
+    let content = ConfigService::new(&RealFileSystem)
+        .fs
+        .read_to_string(std::path::Path::new(&file))
+        .map_err(|e| format!("Failed to read {}: {}", file, e))?;
+    let mut result = crate::domains::import::insomnia::import_export(
+        &content,
+        &crate::domains::import::new_service_id(),
+        &directory,
+    )?;
+    if let Some(name) = name.filter(|n| !n.trim().is_empty()) {
+        result.service.name = name;
+    }
+    save_imported_service(&app, &mut result.service, "Insomnia")?;
+    Ok(result)
+}
+
+/// Imports the Bruno collection in `collection_dir`
+#[tauri::command]
+pub async fn import_bruno(
+    app: AppHandle,
+    directory: String,
+    collection_dir: String,
+    name: Option<String>,
+) -> Result<ImportResult, String> {
+    let mut result = crate::domains::import::bruno::import_collection(
+        &RealFileSystem,
+        std::path::Path::new(&collection_dir),
+        &crate::domains::import::new_service_id(),
+        &directory,
+    )?;
+    if let Some(name) = name.filter(|n| !n.trim().is_empty()) {
+        result.service.name = name;
+    }
+    save_imported_service(&app, &mut result.service, "Bruno")?;
+    Ok(result)
+}
+
+/// Lists the entries of a HAR file so the user can choose which to import
+#[tauri::command]
+pub fn get_har_entries(file: String) -> Result<Vec<HarEntrySummary>, String> {
+    let har = crate::domains::har::load_har(&RealFileSystem, std::path::Path::new(&file))?;
+    Ok(crate::domains::har::entry_summaries(&har))
+}
+
+/// Adds the chosen entries of a HAR file as endpoints of the service, skipping requests it
+/// already has. Their origin becomes the service's `BASE_URL`.
+#[tauri::command]
+pub async fn import_har(
+    app: AppHandle,
+    service_id: String,
+    file: String,
+    entries: Vec<usize>,
+    options: HarImportOptions,
+) -> Result<Service, String> {
+    let mut service = load_service_config(&app, &service_id)
+        .await?
+        .ok_or_else(|| format!("Service '{}' not found", service_id))?;
+    tokio::task::spawn_blocking(move || {
+        let har = crate::domains::har::load_har(&RealFileSystem, std::path::Path::new(&file))?;
+        let imported =
+            crate::domains::har::endpoints_from_har(&har, &entries, &options, &service.id)?;
+        crate::domains::har::add_to_service(&mut service, imported);
+        ConfigService::new(&RealFileSystem)
+            .save_service(&mut service, Some("Import endpoints from HAR".to_string()))?;
+        Ok(service)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Writes the given history entries to `path` as a HAR file, with credentials redacted
+/// unless `options` keeps them
+#[tauri::command]
+pub async fn export_history_har(
+    app: AppHandle,
+    ids: Vec<String>,
+    path: String,
+    options: Option<HarExportOptions>,
+) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || {
+        let entries = crate::history::get_history_entries(&app, &ids)?;
+        let har = crate::domains::har::history_to_har(&entries, &options.unwrap_or_default());
+        crate::domains::har::save_har(&RealFileSystem, std::path::Path::new(&path), &har)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Writes the requests of a run to `path` as a HAR file, with credentials redacted unless
+/// `options` keeps them
+#[tauri::command]
+pub fn export_run_har(
+    path: String,
+    summary: RunSummary,
+    options: Option<HarExportOptions>,
+) -> Result<(), String> {
+    let har = crate::domains::har::run_to_har(
+        &summary,
+        &chrono::Utc::now().to_rfc3339(),
+        &options.unwrap_or_default(),
+    );
+    crate::domains::har::save_har(&RealFileSystem, std::path::Path::new(&path), &har)
+}
+
+/// Saves a newly imported service and adds it to the user's services
+fn save_imported_service(
+    app: &AppHandle,
+    service: &mut Service,
+    source: &str,
+) -> Result<(), String> {
+    let config_service = ConfigService::new(&RealFileSystem);
+    let message = format!("Import service from {}: {}", source, service.name);
+    config_service.save_service(service, Some(message))?;
+
+    let mut settings = config_service.load_settings(app)?;
+    settings.services.push(crate::types::ServiceStub {
+        id: service.id.clone(),
+        name: service.name.clone(),
+        directory: service.directory.clone(),
+    });
+    config_service.save_settings(app, &settings)
+}
+
 pub fn curl_to_endpoint(
     service_id: String,
     curl_command: &str,
     authenticated: bool,
-    auth_type: Option<String>,
+    auth_type: Option<AuthType>,
 ) -> Result<Endpoint, String> {
     let parsed = ParsedRequest::load(curl_command, serde_json::Value::Null)
         .map_err(|e| format!("Failed to parse cURL: {}", e))?;
@@ -435,7 +641,7 @@ pub fn curl_to_endpoint(
         method: parsed.method.to_string(),
         url: url_str,
         authenticated,
-        auth_type: auth_type.unwrap_or_else(|| "none".to_string()),
+        auth_type: auth_type.unwrap_or_default(),
         metadata: EndpointMetadata {
             version: "1.0".to_string(),
             last_updated: SystemTime::now()
@@ -460,9 +666,17 @@ pub fn curl_to_endpoint(
             cache_duration_unit: "seconds".to_string(),
             token_key: "access_token".to_string(),
             token_header: Some("Authorization".to_string()),
+            secret_token: false,
+            refresh_before_expiry: None,
+            steps: vec![],
+            mode: PreflightMode::Token,
         },
         last_version: 0,
         versions: vec![],
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+        assertions: vec![],
+        examples: vec![],
     })
 }
 
@@ -471,6 +685,92 @@ pub fn clear_history(app: AppHandle) -> Result<(), String> {
     crate::history::clear_history(&app)
 }
 
+/// Example responses of an OpenAPI 3 operation, one per status and example
+fn openapi_examples(responses: &openapiv3::Responses) -> Vec<ExampleResponse> {
+    let mut examples = Vec::new();
+    for (code, response) in &responses.responses {
+        let (openapiv3::StatusCode::Code(status), Some(response)) = (code, response.as_item())
+        else {
+            continue;
+        };
+        for (content_type, media) in &response.content {
+            let mut values = Vec::new();
+            if let Some(value) = &media.example {
+                values.push((status.to_string(), value));
+            }
+            for (name, example) in &media.examples {
+                if let Some(value) = example.as_item().and_then(|e| e.value.as_ref()) {
+                    values.push((format!("{} {}", status, name), value));
+                }
+            }
+            if values.is_empty() {
+                let schema_example = media
+                    .schema
+                    .as_ref()
+                    .and_then(|s| s.as_item())
+                    .and_then(|s| s.schema_data.example.as_ref());
+                if let Some(value) = schema_example {
+                    values.push((status.to_string(), value));
+                }
+            }
+            for (name, value) in values {
+                examples.push(example_response(name, *status, content_type, value));
+            }
+        }
+    }
+    examples
+}
+
+/// Example responses of a Swagger 2 operation, from `examples` or the schema `example`
+fn swagger_examples(op_value: &serde_json::Value) -> Vec<ExampleResponse> {
+    let mut examples = Vec::new();
+    let Some(responses) = op_value.get("responses").and_then(|v| v.as_object()) else {
+        return examples;
+    };
+    for (code, response) in responses {
+        let Ok(status) = code.parse::<u16>() else {
+            continue;
+        };
+        let by_type = response.get("examples").and_then(|v| v.as_object());
+        if let Some(by_type) = by_type.filter(|e| !e.is_empty()) {
+            for (content_type, value) in by_type {
+                examples.push(example_response(code.clone(), status, content_type, value));
+            }
+        } else if let Some(value) = response.get("schema").and_then(|s| s.get("example")) {
+            examples.push(example_response(
+                code.clone(),
+                status,
+                "application/json",
+                value,
+            ));
+        }
+    }
+    examples
+}
+
+fn example_response(
+    name: String,
+    status: u16,
+    content_type: &str,
+    value: &serde_json::Value,
+) -> ExampleResponse {
+    let body = match value {
+        serde_json::Value::String(s) => s.clone(),
+        other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    };
+    ExampleResponse {
+        name,
+        status,
+        headers: vec![NameValue {
+            name: "Content-Type".to_string(),
+            value: content_type.to_string(),
+            enabled: true,
+            secret_key: None,
+        }],
+        body,
+    }
+}
+
 pub fn parse_spec_content(
     content: &str,
     service_id: &str,
@@ -540,6 +840,8 @@ pub fn parse_spec_content(
                             }
                         }
 
+                        let examples = openapi_examples(&op.responses);
+
                         endpoints.push(Endpoint {
                             id: endpoint_id,
                             service_id: service_id.to_string(),
@@ -547,7 +849,7 @@ pub fn parse_spec_content(
                             method: method.to_string(),
                             url: path.clone(),
                             authenticated: false,
-                            auth_type: "none".to_string(),
+                            auth_type: AuthType::None,
                             metadata: EndpointMetadata {
                                 version: "1.0".to_string(),
                                 last_updated: SystemTime::now()
@@ -572,9 +874,17 @@ pub fn parse_spec_content(
                                 cache_duration_unit: "seconds".to_string(),
                                 token_key: "access_token".to_string(),
                                 token_header: Some("Authorization".to_string()),
+                                secret_token: false,
+                                refresh_before_expiry: None,
+                                steps: vec![],
+                                mode: PreflightMode::Token,
                             },
                             last_version: 0,
                             versions: vec![],
+                            auth_profile_id: None,
+                            scripts: Scripts::default(),
+                            assertions: vec![],
+                            examples,
                         });
                     }
                 }
@@ -642,6 +952,8 @@ pub fn parse_spec_content(
                             }
                         }
 
+                        let examples = swagger_examples(op_value);
+
                         endpoints.push(Endpoint {
                             id: endpoint_id,
                             service_id: service_id.to_string(),
@@ -649,7 +961,7 @@ pub fn parse_spec_content(
                             method: method_upper,
                             url: path.clone(),
                             authenticated: false,
-                            auth_type: "none".to_string(),
+                            auth_type: AuthType::None,
                             metadata: EndpointMetadata {
                                 version: "1.0".to_string(),
                                 last_updated: SystemTime::now()
@@ -674,9 +986,17 @@ pub fn parse_spec_content(
                                 cache_duration_unit: "seconds".to_string(),
                                 token_key: "access_token".to_string(),
                                 token_header: Some("Authorization".to_string()),
+                                secret_token: false,
+                                refresh_before_expiry: None,
+                                steps: vec![],
+                                mode: PreflightMode::Token,
                             },
                             last_version: 0,
                             versions: vec![],
+                            auth_profile_id: None,
+                            scripts: Scripts::default(),
+                            assertions: vec![],
+                            examples,
                         });
                     }
                 }
@@ -691,6 +1011,7 @@ pub fn parse_spec_content(
 pub async fn test_preflight_config(
     app: AppHandle,
     service_id: String,
+    environment: Option<String>,
     config: PreflightConfig,
     variables: std::collections::HashMap<String, String>,
 ) -> Result<crate::types::PreflightTestResult, String> {
@@ -702,6 +1023,7 @@ pub async fn test_preflight_config(
     Ok(crate::domains::auth::preflight::test_preflight(
         &RealHttpClient,
         &service_id,
+        environment.as_deref().unwrap_or(""),
         &config,
         &variables,
         cache_path.as_ref(),
@@ -709,6 +1031,368 @@ pub async fn test_preflight_config(
     .await)
 }
 
+#[tauri::command]
+pub fn list_cached_tokens() -> Vec<crate::domains::auth::cache::CachedTokenInfo> {
+    crate::domains::auth::cache::list_cached_tokens()
+}
+
+/// Evicts one cached token by key, or the whole cache when no key is given
+#[tauri::command]
+pub fn evict_cached_tokens(app: AppHandle, key: Option<String>) -> Result<(), String> {
+    crate::domains::auth::cache::clear_token_cache(key);
+    let cache_path = crate::domains::auth::get_token_cache_path(&app)?;
+    crate::domains::auth::cache::save_cache_to_file(&cache_path)
+}
+
+/// Drops the cached token of a service and runs the service preflight again
+#[tauri::command]
+pub async fn refresh_service_token(
+    app: AppHandle,
+    service_id: String,
+    environment: Option<String>,
+    variables: std::collections::HashMap<String, String>,
+) -> Result<crate::types::PreflightTestResult, String> {
+    let service = load_service_config(&app, &service_id)
+        .await?
+        .ok_or_else(|| format!("Service '{}' not found", service_id))?;
+    let environment = environment.or_else(|| service.selected_environment.clone());
+    let cache_path = crate::domains::auth::get_token_cache_path(&app).ok();
+
+    // Services using a shared profile refresh the profile's token
+    let profile = match &service.auth_profile_id {
+        Some(profile_id) => Some(load_auth_profile(&app, profile_id).await?),
+        None => None,
+    };
+    let (_, preflight) = service.effective_auth(environment.as_deref(), profile.as_ref());
+    let scope = match &profile {
+        Some(p) => crate::domains::auth::profile::cache_scope(&p.id),
+        None => service_id,
+    };
+
+    Ok(crate::domains::auth::preflight::refresh_service_token(
+        &RealHttpClient,
+        &scope,
+        environment.as_deref().unwrap_or(""),
+        &preflight,
+        &variables,
+        cache_path.as_ref(),
+    )
+    .await)
+}
+
+/// Progress of a service run, sent as `runner-progress` events
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunnerEvent {
+    service_id: String,
+    #[serde(flatten)]
+    progress: RunProgress,
+}
+
+/// Runs endpoints of a service against an environment, streaming progress to the UI
+#[tauri::command]
+pub async fn run_service(
+    app: AppHandle,
+    service_id: String,
+    options: RunOptions,
+) -> Result<RunSummary, String> {
+    use tauri::Emitter;
+
+    let service = load_service_config(&app, &service_id)
+        .await?
+        .ok_or_else(|| format!("Service '{}' not found", service_id))?;
+    let app_handle = app.clone();
+    let settings = tokio::task::spawn_blocking(move || {
+        ConfigService::new(&RealFileSystem).load_settings(&app_handle)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let cache_path = crate::domains::auth::get_token_cache_path(&app).ok();
+    let request_service = RequestService::new(&RealHttpClient, cache_path);
+
+    crate::domains::runner::run(
+        &request_service,
+        &service,
+        &settings.auth_profiles,
+        &options,
+        &|progress| {
+            let _ = app.emit(
+                "runner-progress",
+                RunnerEvent {
+                    service_id: service_id.clone(),
+                    progress,
+                },
+            );
+        },
+    )
+    .await
+}
+
+#[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LoadTestEvent {
+    service_id: String,
+    #[serde(flatten)]
+    progress: LoadTestProgress,
+}
+
+/// Load tests one endpoint of a service, streaming progress to the UI
+#[tauri::command]
+pub async fn run_load_test(
+    app: AppHandle,
+    service_id: String,
+    options: LoadTestOptions,
+) -> Result<LoadTestReport, String> {
+    use tauri::Emitter;
+
+    let service = load_service_config(&app, &service_id)
+        .await?
+        .ok_or_else(|| format!("Service '{}' not found", service_id))?;
+    let app_handle = app.clone();
+    let settings = tokio::task::spawn_blocking(move || {
+        ConfigService::new(&RealFileSystem).load_settings(&app_handle)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let cache_path = crate::domains::auth::get_token_cache_path(&app).ok();
+    let http = crate::io::PooledHttpClient::new();
+    let request_service = RequestService::new(&http, cache_path);
+
+    crate::domains::load_test::run(
+        &request_service,
+        &service,
+        &settings.auth_profiles,
+        &options,
+        &|progress| {
+            let _ = app.emit(
+                "load-test-progress",
+                LoadTestEvent {
+                    service_id: service_id.clone(),
+                    progress,
+                },
+            );
+        },
+    )
+    .await
+}
+
+#[tauri::command]
+pub fn save_load_test_report(path: String, report: LoadTestReport) -> Result<(), String> {
+    crate::domains::load_test::save_report(&RealFileSystem, std::path::Path::new(&path), &report)
+}
+
+/// Serves the example responses of a service on localhost, sending each request it
+/// receives as a `mock-server-request` event
+#[tauri::command]
+pub async fn start_mock_server(
+    app: AppHandle,
+    service_id: String,
+    options: MockServerOptions,
+) -> Result<MockServerInfo, String> {
+    use tauri::Emitter;
+
+    let service = load_service_config(&app, &service_id)
+        .await?
+        .ok_or_else(|| format!("Service '{}' not found", service_id))?;
+    crate::domains::mock_server::start(
+        &service,
+        options,
+        std::sync::Arc::new(move |entry| {
+            let _ = app.emit("mock-server-request", entry);
+        }),
+    )
+    .await
+}
+
+#[tauri::command]
+pub fn stop_mock_server(service_id: String) -> Result<(), String> {
+    crate::domains::mock_server::stop(&service_id)
+}
+
+#[tauri::command]
+pub fn get_mock_servers() -> Vec<MockServerInfo> {
+    crate::domains::mock_server::running_servers()
+}
+
+#[tauri::command]
+pub fn get_mock_server_log(service_id: String) -> Result<Vec<MockRequestLog>, String> {
+    crate::domains::mock_server::request_log(&service_id)
+}
+
+/// Starts a local proxy. Recorded exchanges are saved to history and sent as
+/// `proxy-exchange` events; replay answers from the history entries in `replay_ids`.
+#[tauri::command]
+pub async fn start_proxy(
+    app: AppHandle,
+    options: ProxyOptions,
+    replay_ids: Option<Vec<String>>,
+) -> Result<ProxyInfo, String> {
+    use tauri::Emitter;
+
+    let recording = match replay_ids {
+        Some(ids) if options.mode == ProxyMode::Replay => {
+            let app_handle = app.clone();
+            tokio::task::spawn_blocking(move || {
+                crate::history::get_history_entries(&app_handle, &ids)
+            })
+            .await
+            .map_err(|e| e.to_string())??
+        }
+        _ => Vec::new(),
+    };
+    crate::domains::proxy::start(
+        options,
+        recording,
+        std::sync::Arc::new(move |entry| {
+            let _ = app.emit("proxy-exchange", entry.clone());
+            let app_handle = app.clone();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = crate::history::save_history(&app_handle, entry) {
+                    eprintln!("Failed to save history: {}", e);
+                }
+            });
+        }),
+    )
+    .await
+}
+
+#[tauri::command]
+pub fn stop_proxy(id: String) -> Result<(), String> {
+    crate::domains::proxy::stop(&id)
+}
+
+#[tauri::command]
+pub fn get_proxies() -> Vec<ProxyInfo> {
+    crate::domains::proxy::running_proxies()
+}
+
+#[tauri::command]
+pub fn get_proxy_exchanges(id: String) -> Result<Vec<HistoryEntry>, String> {
+    crate::domains::proxy::exchanges(&id)
+}
+
+/// Turns recorded history entries into endpoints of a service. Requests that only differ
+/// in ids become one endpoint; endpoints the service already has are skipped. A service
+/// without `BASE_URL` takes the upstream.
+#[tauri::command]
+pub async fn add_recorded_endpoints(
+    app: AppHandle,
+    service_id: String,
+    history_ids: Vec<String>,
+    upstream: Option<String>,
+) -> Result<Service, String> {
+    let base = upstream
+        .map(|u| Url::parse(&u).map_err(|e| format!("Invalid upstream '{}': {}", u, e)))
+        .transpose()?;
+    let mut service = load_service_config(&app, &service_id)
+        .await?
+        .ok_or_else(|| format!("Service '{}' not found", service_id))?;
+    tokio::task::spawn_blocking(move || {
+        let entries = crate::history::get_history_entries(&app, &history_ids)?;
+        crate::domains::proxy::add_to_service(&mut service, &entries, base.as_ref());
+        ConfigService::new(&RealFileSystem)
+            .save_service(&mut service, Some("Add recorded endpoints".to_string()))?;
+        Ok(service)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Monitors are picked up by the background scheduler on its next tick
+#[tauri::command]
+pub fn get_monitors(app: AppHandle) -> Result<Vec<Monitor>, String> {
+    let path = crate::domains::monitor::get_monitors_path(&app)?;
+    crate::domains::monitor::load_monitors(&RealFileSystem, &path)
+}
+
+#[tauri::command]
+pub fn save_monitors(app: AppHandle, monitors: Vec<Monitor>) -> Result<(), String> {
+    let path = crate::domains::monitor::get_monitors_path(&app)?;
+    crate::domains::monitor::save_monitors(&RealFileSystem, &path, &monitors)
+}
+
+#[tauri::command]
+pub fn get_monitor_results(
+    app: AppHandle,
+    monitor_id: Option<String>,
+    limit: usize,
+) -> Result<Vec<MonitorResult>, String> {
+    crate::domains::monitor::get_results(&app, monitor_id.as_deref(), limit)
+}
+
+#[tauri::command]
+pub fn clear_monitor_results(app: AppHandle, monitor_id: String) -> Result<(), String> {
+    crate::domains::monitor::clear_results(&app, &monitor_id)
+}
+
+#[tauri::command]
+pub fn get_workflows(directory: String) -> Result<Vec<Workflow>, String> {
+    ConfigService::new(&RealFileSystem).load_workflows(&directory)
+}
+
+#[tauri::command]
+pub fn save_workflow(directory: String, workflow: Workflow) -> Result<Vec<Workflow>, String> {
+    let config_service = ConfigService::new(&RealFileSystem);
+    config_service.save_workflow(&directory, &workflow)?;
+    config_service.load_workflows(&directory)
+}
+
+/// Runs a workflow of a service, streaming progress to the UI like `run_service`
+#[tauri::command]
+pub async fn run_workflow(
+    app: AppHandle,
+    service_id: String,
+    workflow_id: String,
+    options: WorkflowOptions,
+) -> Result<RunSummary, String> {
+    use tauri::Emitter;
+
+    let service = load_service_config(&app, &service_id)
+        .await?
+        .ok_or_else(|| format!("Service '{}' not found", service_id))?;
+    let app_handle = app.clone();
+    let directory = service.directory.clone();
+    let (settings, workflows) = tokio::task::spawn_blocking(move || {
+        let config_service = ConfigService::new(&RealFileSystem);
+        Ok::<_, String>((
+            config_service.load_settings(&app_handle)?,
+            config_service.load_workflows(&directory)?,
+        ))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    let workflow = workflows
+        .iter()
+        .find(|w| w.id == workflow_id)
+        .ok_or_else(|| format!("Workflow '{}' not found", workflow_id))?;
+    let cache_path = crate::domains::auth::get_token_cache_path(&app).ok();
+    let request_service = RequestService::new(&RealHttpClient, cache_path);
+
+    crate::domains::runner::run_workflow(
+        &request_service,
+        &service,
+        &settings.auth_profiles,
+        workflow,
+        &options,
+        &|progress| {
+            let _ = app.emit(
+                "runner-progress",
+                RunnerEvent {
+                    service_id: service_id.clone(),
+                    progress,
+                },
+            );
+        },
+    )
+    .await
+}
+
+/// Reads the rows of a CSV or JSON data file for a data-driven run
+#[tauri::command]
+pub fn load_data_file(path: String) -> Result<Vec<crate::domains::data_file::DataRow>, String> {
+    crate::domains::data_file::load_data_file(&RealFileSystem, std::path::Path::new(&path))
+}
+
 #[tauri::command]
 pub async fn get_secrets(app: tauri::AppHandle) -> Result<Vec<String>, String> {
     println!("DEBUG: get_secrets called");
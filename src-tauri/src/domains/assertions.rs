@@ -0,0 +1,226 @@
+use crate::types::QResponse;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+/// A check run against every response of an endpoint
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Assertion {
+    Status {
+        equals: u16,
+    },
+    HeaderEquals {
+        name: String,
+        value: String,
+    },
+    /// `pattern` is a regular expression that must match somewhere in the value
+    HeaderMatches {
+        name: String,
+        pattern: String,
+    },
+    JsonPathEquals {
+        path: String,
+        value: Value,
+    },
+    JsonPathExists {
+        path: String,
+    },
+    JsonPathType {
+        path: String,
+        json_type: JsonType,
+    },
+    BodyContains {
+        text: String,
+    },
+    BodyMatches {
+        pattern: String,
+    },
+    ResponseTimeBelow {
+        ms: u64,
+    },
+    JsonSchema {
+        schema: Value,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Array,
+    Object,
+    Null,
+}
+
+impl JsonType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::String(_) => JsonType::String,
+            Value::Number(n) if n.is_i64() || n.is_u64() => JsonType::Integer,
+            Value::Number(_) => JsonType::Number,
+            Value::Bool(_) => JsonType::Boolean,
+            Value::Array(_) => JsonType::Array,
+            Value::Object(_) => JsonType::Object,
+            Value::Null => JsonType::Null,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            JsonType::String => "string",
+            JsonType::Number => "number",
+            JsonType::Integer => "integer",
+            JsonType::Boolean => "boolean",
+            JsonType::Array => "array",
+            JsonType::Object => "object",
+            JsonType::Null => "null",
+        }
+    }
+
+    /// Integers are numbers too
+    fn accepts(self, value: &Value) -> bool {
+        let actual = Self::of(value);
+        actual == self || (self == JsonType::Number && actual == JsonType::Integer)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AssertionResult {
+    pub assertion: Assertion,
+    pub passed: bool,
+    /// What was found, or why the assertion could not be evaluated
+    pub message: String,
+}
+
+/// Evaluates every assertion against the response
+pub fn evaluate(assertions: &[Assertion], response: &QResponse) -> Vec<AssertionResult> {
+    let json = serde_json::from_str::<Value>(&response.body).map_err(|e| e.to_string());
+    assertions
+        .iter()
+        .map(|assertion| {
+            let (passed, message) = match check(assertion, response, &json) {
+                Ok(outcome) => outcome,
+                Err(e) => (false, e),
+            };
+            AssertionResult {
+                assertion: assertion.clone(),
+                passed,
+                message,
+            }
+        })
+        .collect()
+}
+
+fn check(
+    assertion: &Assertion,
+    response: &QResponse,
+    json: &Result<Value, String>,
+) -> Result<(bool, String), String> {
+    let body_json = || {
+        json.as_ref()
+            .map_err(|e| format!("Response body is not valid JSON: {}", e))
+    };
+
+    Ok(match assertion {
+        Assertion::Status { equals } => (
+            response.status == *equals,
+            format!("Status is {}", response.status),
+        ),
+        Assertion::HeaderEquals { name, value } => {
+            let values = header_values(response, name)?;
+            (
+                values.iter().any(|v| v == value),
+                format!("{} is '{}'", name, values.join(", ")),
+            )
+        }
+        Assertion::HeaderMatches { name, pattern } => {
+            let re = regex::Regex::new(pattern)
+                .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+            let values = header_values(response, name)?;
+            (
+                values.iter().any(|v| re.is_match(v)),
+                format!("{} is '{}'", name, values.join(", ")),
+            )
+        }
+        Assertion::JsonPathEquals { path, value } => {
+            let found = query(body_json()?, path)?;
+            match found.first() {
+                Some(actual) => (*actual == value, format!("{} is {}", path, actual)),
+                None => (false, format!("{} not found", path)),
+            }
+        }
+        Assertion::JsonPathExists { path } => {
+            let found = query(body_json()?, path)?;
+            (
+                !found.is_empty(),
+                format!("{} matched {} value(s)", path, found.len()),
+            )
+        }
+        Assertion::JsonPathType { path, json_type } => {
+            let found = query(body_json()?, path)?;
+            match found.first() {
+                Some(actual) => (
+                    json_type.accepts(actual),
+                    format!("{} is {}", path, JsonType::of(actual).name()),
+                ),
+                None => (false, format!("{} not found", path)),
+            }
+        }
+        Assertion::BodyContains { text } => (
+            response.body.contains(text.as_str()),
+            format!("Body is {} bytes", response.body.len()),
+        ),
+        Assertion::BodyMatches { pattern } => {
+            let re = regex::Regex::new(pattern)
+                .map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+            (
+                re.is_match(&response.body),
+                format!("Body is {} bytes", response.body.len()),
+            )
+        }
+        Assertion::ResponseTimeBelow { ms } => (
+            response.time_elapsed < *ms,
+            format!("Response took {} ms", response.time_elapsed),
+        ),
+        Assertion::JsonSchema { schema } => {
+            let validator = jsonschema::validator_for(schema)
+                .map_err(|e| format!("Invalid JSON Schema: {}", e))?;
+            let errors: Vec<String> = validator
+                .iter_errors(body_json()?)
+                .map(|e| format!("{}: {}", e.instance_path, e))
+                .collect();
+            if errors.is_empty() {
+                (true, "Body matches the schema".to_string())
+            } else {
+                (false, errors.join("; "))
+            }
+        }
+    })
+}
+
+fn header_values<'a>(response: &'a QResponse, name: &str) -> Result<Vec<&'a str>, String> {
+    let values: Vec<&str> = response
+        .headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
+        .collect();
+    if values.is_empty() {
+        return Err(format!("Header '{}' not found", name));
+    }
+    Ok(values)
+}
+
+fn query<'a>(json: &'a Value, path: &str) -> Result<Vec<&'a Value>, String> {
+    let path = JsonPath::parse(path).map_err(|e| format!("Invalid JSONPath '{}': {}", path, e))?;
+    Ok(path.query(json).all())
+}
@@ -1,3 +1,7 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose, Engine as _};
+use keyring::Entry;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
@@ -5,11 +9,38 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+use crate::domains::service::endpoint::PreflightMode;
+
+/// Keyring entry holding the key used to encrypt the persisted token cache
+const KEYRING_SERVICE: &str = "xrest-token-cache";
+const KEYRING_USER: &str = "encryption-key";
+/// Prefix of the encrypted cache file format
+const ENCRYPTED_PREFIX: &str = "xrest-enc-v1:";
+const NONCE_LEN: usize = 12;
+
 /// Represents a cached token with expiration information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CachedToken {
     pub token: String,
     pub expires_at: u64, // Unix timestamp in seconds
+    /// Secret tokens live in memory only and are never written to disk
+    #[serde(default)]
+    pub secret: bool,
+    /// Whether the token is a bearer token or a cookie session
+    #[serde(default)]
+    pub mode: PreflightMode,
+}
+
+/// A cached token as shown to the user, with the token value masked
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedTokenInfo {
+    pub key: String,
+    pub service_id: Option<String>,
+    pub environment: Option<String>,
+    pub expires_at: u64,
+    pub masked_token: String,
+    pub secret: bool,
 }
 
 /// Global token cache: key -> CachedToken
@@ -17,6 +48,24 @@ pub type TokenCacheInner = HashMap<String, CachedToken>;
 pub static TOKEN_CACHE: Lazy<Arc<Mutex<TokenCacheInner>>> =
     Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
+type ChangeListener = Box<dyn Fn() + Send + Sync>;
+static CHANGE_LISTENER: Lazy<Mutex<Option<ChangeListener>>> = Lazy::new(|| Mutex::new(None));
+
+/// Registers a callback invoked whenever tokens are added or removed
+pub fn set_change_listener<F>(listener: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    *CHANGE_LISTENER.lock().unwrap() = Some(Box::new(listener));
+}
+
+/// Must be called without holding the `TOKEN_CACHE` lock, the listener may read the cache
+fn notify_changed() {
+    if let Some(listener) = CHANGE_LISTENER.lock().unwrap().as_ref() {
+        listener();
+    }
+}
+
 /// Get a cached token, returns None if not found or expired
 pub fn get_cached_token(key: &str) -> Option<CachedToken> {
     let cache = TOKEN_CACHE.lock().unwrap();
@@ -24,37 +73,156 @@ pub fn get_cached_token(key: &str) -> Option<CachedToken> {
 }
 
 /// Set a cached token with expiration time
-pub fn set_cached_token(key: String, token: String, expires_at: u64) {
-    let mut cache = TOKEN_CACHE.lock().unwrap();
-    cache.insert(key, CachedToken { token, expires_at });
+pub fn set_cached_token(key: String, token: String, expires_at: u64, secret: bool) {
+    set_cached_session(key, token, expires_at, secret, PreflightMode::Token);
 }
 
-/// Helper to generate a unique cache key based on service ID and preflight details
+/// Set a cached token or cookie session, remembering which of the two it is
+pub fn set_cached_session(
+    key: String,
+    token: String,
+    expires_at: u64,
+    secret: bool,
+    mode: PreflightMode,
+) {
+    TOKEN_CACHE.lock().unwrap().insert(
+        key,
+        CachedToken {
+            token,
+            expires_at,
+            secret,
+            mode,
+        },
+    );
+    notify_changed();
+}
+
+/// Find a valid token of the given mode cached by any preflight of a service in the given
+/// environment. Used when an endpoint has no preflight of its own but its service already
+/// logged in.
+pub fn get_cached_token_for_service(
+    service_id: &str,
+    environment: &str,
+    mode: PreflightMode,
+) -> Option<CachedToken> {
+    let prefix = service_prefix(service_id, environment);
+    let cache = TOKEN_CACHE.lock().unwrap();
+    cache
+        .iter()
+        .filter(|(key, token)| {
+            key.starts_with(&prefix) && token.mode == mode && is_token_valid(token)
+        })
+        .map(|(_, token)| token.clone())
+        .max_by_key(|token| token.expires_at)
+}
+
+/// Helper to generate a unique cache key based on service ID, environment and preflight details
 pub fn generate_key(
     service_id: &str,
+    environment: &str,
     url: &str,
     method: &str,
     body: &str,
     headers: &[(String, String)],
 ) -> String {
-    // If we have a service ID, we want to share the token across all endpoints in that service.
+    // The preflight identity keeps tokens from different IdPs or credentials apart, even
+    // within one service and environment. Keys are persisted, so the hash must not change
+    // between releases.
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for part in [url, method, body] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    for (name, value) in headers {
+        hasher.update(name.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(value.as_bytes());
+        hasher.update([0u8]);
+    }
+    let hash = hex::encode(hasher.finalize());
+
+    // Within a service, the token is shared across all endpoints of the same environment.
     if !service_id.is_empty() {
-        return service_id.to_string();
+        return format!("{}{}", service_prefix(service_id, environment), hash);
     }
 
-    // For the scratchpad (no service ID), we must use the preflight details to avoid collisions
+    // For the scratchpad (no service ID), the preflight details alone avoid collisions
     // between different APIs the user might be testing.
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+    format!("scratchpad:{}", hash)
+}
+
+/// `:` separates the parts of a key, so it is escaped within them
+fn service_prefix(service_id: &str, environment: &str) -> String {
+    let escape = |part: &str| part.replace('%', "%25").replace(':', "%3A");
+    format!("{}:{}:", escape(service_id), escape(environment))
+}
+
+/// Splits a `{service}:{env}:{hash}` key; scratchpad and JWT keys have no service
+fn parse_key(key: &str) -> (Option<String>, Option<String>) {
+    if key.starts_with("scratchpad:") || key.starts_with("jwt:") {
+        return (None, None);
+    }
+    let unescape = |part: &str| part.replace("%3A", ":").replace("%25", "%");
+    match key.split(':').collect::<Vec<_>>()[..] {
+        [service, env, _hash] => (
+            Some(unescape(service)),
+            Some(unescape(env)).filter(|e| !e.is_empty()),
+        ),
+        _ => (None, None),
+    }
+}
 
-    let mut hasher = DefaultHasher::new();
-    url.hash(&mut hasher);
-    method.hash(&mut hasher);
-    body.hash(&mut hasher);
-    headers.hash(&mut hasher);
-    let hash = hasher.finish();
+/// Keeps just enough of a token to tell tokens apart
+pub fn mask_token(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() <= 12 {
+        return "*".repeat(chars.len());
+    }
+    format!(
+        "{}…{}",
+        chars[..4].iter().collect::<String>(),
+        chars[chars.len() - 4..].iter().collect::<String>()
+    )
+}
 
-    format!("scratchpad:{:x}", hash)
+/// Lists all valid cached tokens, soonest expiry first
+pub fn list_cached_tokens() -> Vec<CachedTokenInfo> {
+    let cache = TOKEN_CACHE.lock().unwrap();
+    let mut tokens: Vec<CachedTokenInfo> = cache
+        .iter()
+        .filter(|(_, token)| is_token_valid(token))
+        .map(|(key, token)| {
+            let (service_id, environment) = parse_key(key);
+            CachedTokenInfo {
+                key: key.clone(),
+                service_id,
+                environment,
+                expires_at: token.expires_at,
+                masked_token: mask_token(&token.token),
+                secret: token.secret,
+            }
+        })
+        .collect();
+    tokens.sort_by(|a, b| a.expires_at.cmp(&b.expires_at).then(a.key.cmp(&b.key)));
+    tokens
+}
+
+/// Removes every token of a service, or only those of one environment
+pub fn clear_service_tokens(service_id: &str, environment: Option<&str>) {
+    {
+        let mut cache = TOKEN_CACHE.lock().unwrap();
+        cache.retain(|key, _| {
+            let (service, env) = parse_key(key);
+            let env_matches = match environment {
+                Some(e) => env.as_deref().unwrap_or("") == e,
+                None => true,
+            };
+            !(service.as_deref() == Some(service_id) && env_matches)
+        });
+    }
+    notify_changed();
 }
 
 /// Check if a cached token is still valid (not expired)
@@ -66,15 +234,98 @@ pub fn is_token_valid(cached: &CachedToken) -> bool {
     now < cached.expires_at
 }
 
-/// Persistence: Save the cache to a file
+/// Serializes the non-secret tokens and encrypts them with AES-256-GCM
+pub fn encrypt_cache(cache: &TokenCacheInner, key: &[u8; 32]) -> Result<String, String> {
+    let persisted: TokenCacheInner = cache
+        .iter()
+        .filter(|(_, token)| !token.secret)
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let plaintext = serde_yaml::to_string(&persisted).map_err(|e| e.to_string())?;
+
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+    let nonce_bytes: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce_bytes), plaintext.as_bytes())
+        .map_err(|e| format!("Failed to encrypt token cache: {}", e))?;
+
+    let mut payload = nonce_bytes.to_vec();
+    payload.extend(ciphertext);
+    Ok(format!(
+        "{}{}",
+        ENCRYPTED_PREFIX,
+        general_purpose::STANDARD.encode(payload)
+    ))
+}
+
+pub fn decrypt_cache(content: &str, key: &[u8; 32]) -> Result<TokenCacheInner, String> {
+    let encoded = content
+        .trim()
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .ok_or_else(|| "Token cache is not encrypted".to_string())?;
+    let payload = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| e.to_string())?;
+    if payload.len() < NONCE_LEN {
+        return Err("Token cache is truncated".to_string());
+    }
+
+    let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|e| e.to_string())?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|e| format!("Failed to decrypt token cache: {}", e))?;
+    let plaintext = String::from_utf8(plaintext).map_err(|e| e.to_string())?;
+    serde_yaml::from_str(&plaintext).map_err(|e| e.to_string())
+}
+
+/// Loads the cache encryption key from the OS keyring, creating one on first use
+fn encryption_key() -> Result<[u8; 32], String> {
+    let entry = Entry::new(KEYRING_SERVICE, KEYRING_USER)
+        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
+
+    if let Some(key) = stored_key(entry.get_password())? {
+        return Ok(key);
+    }
+
+    let key: [u8; 32] = rand::random();
+    entry
+        .set_password(&general_purpose::STANDARD.encode(key))
+        .map_err(|e| format!("Failed to store token cache key in keyring: {}", e))?;
+    Ok(key)
+}
+
+/// The key read from the keyring, or None when there is none yet. Any other failure is an
+/// error: replacing a key that exists would make every cached token unreadable.
+pub fn stored_key(stored: Result<String, keyring::Error>) -> Result<Option<[u8; 32]>, String> {
+    let encoded = match stored {
+        Ok(encoded) => encoded,
+        Err(keyring::Error::NoEntry) => return Ok(None),
+        Err(e) => {
+            return Err(format!(
+                "Failed to read token cache key from keyring: {}",
+                e
+            ))
+        }
+    };
+    general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes.as_slice()).ok())
+        .map(Some)
+        .ok_or_else(|| "Token cache key in keyring is malformed".to_string())
+}
+
+/// Persistence: Save the cache to a file, encrypted with the keyring-held key
 pub fn save_cache_to_file(path: &std::path::Path) -> Result<(), String> {
     if let Some(parent) = path.parent() {
         if !parent.exists() {
             std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
         }
     }
+    let key = encryption_key()?;
     let cache = TOKEN_CACHE.lock().unwrap();
-    let content = serde_yaml::to_string(&*cache).map_err(|e| e.to_string())?;
+    let content = encrypt_cache(&cache, &key)?;
     std::fs::write(path, content).map_err(|e| e.to_string())?;
     Ok(())
 }
@@ -85,30 +336,35 @@ pub fn load_cache_from_file(path: &std::path::Path) -> Result<(), String> {
         return Ok(());
     }
     let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
-    let loaded: TokenCacheInner = serde_yaml::from_str(&content).map_err(|e| e.to_string())?;
+    let loaded = decrypt_cache(&content, &encryption_key()?)?;
 
-    let mut cache = TOKEN_CACHE.lock().unwrap();
-    // Clean up expired tokens while loading
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
+    {
+        let mut cache = TOKEN_CACHE.lock().unwrap();
+        // Clean up expired tokens while loading
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
 
-    for (key, token) in loaded {
-        if token.expires_at > now {
-            cache.insert(key, token);
+        for (key, token) in loaded {
+            if token.expires_at > now {
+                cache.insert(key, token);
+            }
         }
     }
+    notify_changed();
     Ok(())
 }
 
 /// Clear all cached tokens, optionally for a specific key
-#[allow(dead_code)]
 pub fn clear_token_cache(key: Option<String>) {
-    let mut cache = TOKEN_CACHE.lock().unwrap();
-    if let Some(k) = key {
-        cache.remove(&k);
-    } else {
-        cache.clear();
+    {
+        let mut cache = TOKEN_CACHE.lock().unwrap();
+        if let Some(k) = key {
+            cache.remove(&k);
+        } else {
+            cache.clear();
+        }
     }
+    notify_changed();
 }
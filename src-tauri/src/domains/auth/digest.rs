@@ -0,0 +1,205 @@
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// A parsed `WWW-Authenticate: Digest ...` challenge
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestChallenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    pub algorithm: String,
+    pub qop: Vec<String>,
+}
+
+/// Client-side values that vary per request; kept separate so responses can be reproduced in tests
+pub struct DigestContext<'a> {
+    pub method: &'a str,
+    pub uri: &'a str,
+    pub cnonce: &'a str,
+    pub nc: u32,
+}
+
+/// Picks the strongest supported Digest challenge from the `WWW-Authenticate` header values
+pub fn select_challenge<'a, I>(header_values: I) -> Option<DigestChallenge>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    header_values
+        .into_iter()
+        .filter_map(parse_challenge)
+        .filter(|c| hash_name(&c.algorithm).is_some())
+        .max_by_key(|c| hash_name(&c.algorithm) == Some("SHA-256"))
+}
+
+pub fn parse_challenge(header: &str) -> Option<DigestChallenge> {
+    let trimmed = header.trim_start();
+    if trimmed.len() < 7 || !trimmed[..7].eq_ignore_ascii_case("digest ") {
+        return None;
+    }
+
+    let mut realm = None;
+    let mut nonce = None;
+    let mut opaque = None;
+    let mut algorithm = "MD5".to_string();
+    let mut qop = Vec::new();
+
+    for (key, value) in parse_params(&trimmed[7..]) {
+        match key.to_lowercase().as_str() {
+            "realm" => realm = Some(value),
+            "nonce" => nonce = Some(value),
+            "opaque" => opaque = Some(value),
+            "algorithm" => algorithm = value,
+            "qop" => {
+                qop = value
+                    .split(',')
+                    .map(|q| q.trim().to_lowercase())
+                    .filter(|q| !q.is_empty())
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+
+    Some(DigestChallenge {
+        realm: realm?,
+        nonce: nonce?,
+        opaque,
+        algorithm,
+        qop,
+    })
+}
+
+/// Builds the `Authorization` header value answering `challenge` (RFC 7616, qop=auth)
+pub fn authorization_header(
+    challenge: &DigestChallenge,
+    username: &str,
+    password: &str,
+    ctx: &DigestContext,
+) -> Result<String, String> {
+    let hash_name = hash_name(&challenge.algorithm).ok_or_else(|| {
+        format!(
+            "Unsupported digest algorithm '{}'; expected MD5 or SHA-256",
+            challenge.algorithm
+        )
+    })?;
+    let h = |data: String| match hash_name {
+        "SHA-256" => hex::encode(Sha256::digest(data.as_bytes())),
+        _ => hex::encode(Md5::digest(data.as_bytes())),
+    };
+
+    let nc = format!("{:08x}", ctx.nc);
+    let mut ha1 = h(format!("{}:{}:{}", username, challenge.realm, password));
+    if challenge.algorithm.to_lowercase().ends_with("-sess") {
+        ha1 = h(format!("{}:{}:{}", ha1, challenge.nonce, ctx.cnonce));
+    }
+    let ha2 = h(format!("{}:{}", ctx.method.to_uppercase(), ctx.uri));
+
+    let use_qop = if challenge.qop.is_empty() {
+        false
+    } else if challenge.qop.iter().any(|q| q == "auth") {
+        true
+    } else {
+        return Err(format!(
+            "Unsupported digest qop '{}'; only 'auth' is supported",
+            challenge.qop.join(",")
+        ));
+    };
+
+    let response = if use_qop {
+        h(format!(
+            "{}:{}:{}:{}:auth:{}",
+            ha1, challenge.nonce, nc, ctx.cnonce, ha2
+        ))
+    } else {
+        // RFC 2069 compatibility for servers that don't offer a qop
+        h(format!("{}:{}:{}", ha1, challenge.nonce, ha2))
+    };
+
+    let mut header = format!(
+        "Digest username={}, realm={}, nonce={}, uri={}, algorithm={}, response=\"{}\"",
+        quoted(username),
+        quoted(&challenge.realm),
+        quoted(&challenge.nonce),
+        quoted(ctx.uri),
+        challenge.algorithm,
+        response
+    );
+    if use_qop {
+        header.push_str(&format!(
+            ", qop=auth, nc={}, cnonce={}",
+            nc,
+            quoted(ctx.cnonce)
+        ));
+    }
+    if let Some(opaque) = &challenge.opaque {
+        header.push_str(&format!(", opaque={}", quoted(opaque)));
+    }
+    Ok(header)
+}
+
+/// A quoted-string with `"` and `\` escaped; the hashes use the unescaped value
+fn quoted(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Generates a random client nonce
+pub fn generate_cnonce() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+fn hash_name(algorithm: &str) -> Option<&'static str> {
+    match algorithm.to_uppercase().as_str() {
+        "MD5" | "MD5-SESS" => Some("MD5"),
+        "SHA-256" | "SHA-256-SESS" => Some("SHA-256"),
+        _ => None,
+    }
+}
+
+/// Splits `key=value, key="quoted, value"` auth-params, unquoting values
+fn parse_params(input: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while matches!(chars.peek(), Some(c) if c.is_whitespace() || *c == ',') {
+            chars.next();
+        }
+        let key: String = chars
+            .by_ref()
+            .take_while(|c| *c != '=')
+            .collect::<String>()
+            .trim()
+            .to_string();
+        if key.is_empty() {
+            break;
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some(escaped) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    '"' => break,
+                    _ => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.peek() {
+                if *c == ',' {
+                    break;
+                }
+                value.push(*c);
+                chars.next();
+            }
+            value = value.trim().to_string();
+        }
+        params.push((key, value));
+    }
+
+    params
+}
@@ -0,0 +1,172 @@
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashMap;
+use url::Url;
+
+use crate::types::Header;
+
+/// A partner-specific HMAC signing recipe
+pub struct HmacRecipe<'a> {
+    /// sha256 (default), sha512 or sha1
+    pub algorithm: &'a str,
+    /// hex (default) or base64
+    pub encoding: &'a str,
+    /// Canonical string template, e.g. `{{method}}\n{{path}}\n{{timestamp}}\n{{bodyHash}}`
+    pub template: &'a str,
+    /// Headers to add; values may use the same placeholders plus `{{signature}}`
+    pub headers: &'a [Header],
+}
+
+/// The final request being signed
+pub struct SignableRequest<'a> {
+    pub method: &'a str,
+    pub url: &'a Url,
+    pub headers: &'a [(String, String)],
+    pub body: &'a str,
+}
+
+/// Placeholders `sign` fills in itself
+const BUILT_IN_PLACEHOLDERS: [&str; 12] = [
+    "method",
+    "path",
+    "query",
+    "host",
+    "url",
+    "body",
+    "bodyHash",
+    "timestamp",
+    "timestampMs",
+    "isoTimestamp",
+    "nonce",
+    "signature",
+];
+
+/// Whether `sign` computes the value of `{{name}}`, so a variable of that name must not
+/// replace it beforehand
+pub fn is_built_in_placeholder(name: &str) -> bool {
+    BUILT_IN_PLACEHOLDERS.contains(&name) || name.starts_with("header.")
+}
+
+#[derive(Clone, Copy)]
+enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Renders the canonical string, signs it and returns the headers to attach.
+///
+/// Placeholders: `{{method}}`, `{{path}}`, `{{query}}`, `{{host}}`, `{{url}}`, `{{body}}`,
+/// `{{bodyHash}}`, `{{timestamp}}`, `{{timestampMs}}`, `{{isoTimestamp}}`, `{{nonce}}` and
+/// `{{header.<Name>}}`. A literal `\n` in the template is treated as a newline.
+pub fn sign(
+    recipe: &HmacRecipe,
+    key: &[u8],
+    request: &SignableRequest,
+    now: DateTime<Utc>,
+    nonce: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let algorithm = match recipe.algorithm.to_lowercase().replace('-', "").as_str() {
+        "" | "sha256" => HashAlgorithm::Sha256,
+        "sha512" => HashAlgorithm::Sha512,
+        "sha1" => HashAlgorithm::Sha1,
+        other => return Err(format!("Unsupported HMAC algorithm '{}'", other)),
+    };
+    let encode = |bytes: &[u8]| -> Result<String, String> {
+        match recipe.encoding.to_lowercase().as_str() {
+            "" | "hex" => Ok(hex::encode(bytes)),
+            "base64" => Ok(general_purpose::STANDARD.encode(bytes)),
+            other => Err(format!("Unsupported signature encoding '{}'", other)),
+        }
+    };
+
+    let mut values: HashMap<String, String> = HashMap::new();
+    values.insert("method".to_string(), request.method.to_uppercase());
+    values.insert("path".to_string(), request.url.path().to_string());
+    values.insert(
+        "query".to_string(),
+        request.url.query().unwrap_or_default().to_string(),
+    );
+    values.insert(
+        "host".to_string(),
+        match request.url.port() {
+            Some(port) => format!("{}:{}", request.url.host_str().unwrap_or_default(), port),
+            None => request.url.host_str().unwrap_or_default().to_string(),
+        },
+    );
+    values.insert("url".to_string(), request.url.to_string());
+    values.insert("body".to_string(), request.body.to_string());
+    values.insert(
+        "bodyHash".to_string(),
+        encode(&digest(algorithm, request.body.as_bytes()))?,
+    );
+    values.insert("timestamp".to_string(), now.timestamp().to_string());
+    values.insert(
+        "timestampMs".to_string(),
+        now.timestamp_millis().to_string(),
+    );
+    values.insert("isoTimestamp".to_string(), now.to_rfc3339());
+    values.insert("nonce".to_string(), nonce.to_string());
+    for (name, value) in request.headers {
+        values.insert(format!("header.{}", name.to_lowercase()), value.clone());
+    }
+
+    let canonical = render(&recipe.template.replace("\\n", "\n"), &values);
+    let signature = encode(&mac(algorithm, key, canonical.as_bytes()))?;
+    values.insert("signature".to_string(), signature);
+
+    Ok(recipe
+        .headers
+        .iter()
+        .filter(|h| h.enabled && !h.name.is_empty())
+        .map(|h| (h.name.clone(), render(&h.value, &values)))
+        .collect())
+}
+
+fn render(template: &str, values: &HashMap<String, String>) -> String {
+    let re = regex::Regex::new(r"\{\{([^}]+)\}\}").expect("Invalid regex");
+    re.replace_all(template, |caps: &regex::Captures| {
+        let name = caps[1].trim();
+        let lookup = match name.strip_prefix("header.") {
+            Some(header) => format!("header.{}", header.to_lowercase()),
+            None => name.to_string(),
+        };
+        values
+            .get(&lookup)
+            .cloned()
+            .unwrap_or_else(|| caps[0].to_string())
+    })
+    .to_string()
+}
+
+fn digest(algorithm: HashAlgorithm, data: &[u8]) -> Vec<u8> {
+    match algorithm {
+        HashAlgorithm::Sha1 => Sha1::digest(data).to_vec(),
+        HashAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+        HashAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
+    }
+}
+
+fn mac(algorithm: HashAlgorithm, key: &[u8], data: &[u8]) -> Vec<u8> {
+    const KEY_ERR: &str = "HMAC accepts keys of any length";
+    match algorithm {
+        HashAlgorithm::Sha1 => {
+            let mut mac = Hmac::<Sha1>::new_from_slice(key).expect(KEY_ERR);
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+        HashAlgorithm::Sha256 => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key).expect(KEY_ERR);
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+        HashAlgorithm::Sha512 => {
+            let mut mac = Hmac::<Sha512>::new_from_slice(key).expect(KEY_ERR);
+            mac.update(data);
+            mac.finalize().into_bytes().to_vec()
+        }
+    }
+}
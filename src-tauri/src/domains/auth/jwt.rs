@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+
+/// Minted tokens are dropped from the cache this many seconds before their `exp`
+pub const EXPIRY_SKEW_SECONDS: u64 = 30;
+
+#[derive(Debug, Clone)]
+pub struct MintedJwt {
+    pub token: String,
+    pub expires_at: Option<u64>,
+}
+
+/// Replaces dynamic values in a claims template:
+/// `{{$timestamp}}` (optionally `{{$timestamp+300}}` / `{{$timestamp-60}}`),
+/// `{{$isoTimestamp}}` and `{{$randomUUID}}`. Unknown placeholders are left untouched.
+pub fn render_dynamic_values(template: &str, now: DateTime<Utc>) -> String {
+    let re = regex::Regex::new(r"\{\{\s*\$(\w+)\s*([+-]\s*\d+)?\s*\}\}").expect("Invalid regex");
+    re.replace_all(template, |caps: &regex::Captures| {
+        let offset = caps
+            .get(2)
+            .and_then(|m| m.as_str().replace(' ', "").parse::<i64>().ok())
+            .unwrap_or(0);
+        match &caps[1] {
+            "timestamp" => (now.timestamp() + offset).to_string(),
+            "isoTimestamp" => (now + chrono::Duration::seconds(offset)).to_rfc3339(),
+            "randomUUID" | "guid" => uuid::Uuid::new_v4().to_string(),
+            _ => caps[0].to_string(),
+        }
+    })
+    .to_string()
+}
+
+/// Signs the claims template with HS256 (shared secret) or RS256 / ES256 (PEM private key)
+pub fn mint(
+    algorithm: &str,
+    key: &str,
+    claims_template: &str,
+    now: DateTime<Utc>,
+) -> Result<MintedJwt, String> {
+    let rendered = render_dynamic_values(claims_template, now);
+    let claims: serde_json::Value = serde_json::from_str(&rendered)
+        .map_err(|e| format!("JWT claims are not valid JSON: {}", e))?;
+    if !claims.is_object() {
+        return Err("JWT claims must be a JSON object".to_string());
+    }
+
+    let (algorithm, encoding_key) = match algorithm.to_uppercase().as_str() {
+        "HS256" => (Algorithm::HS256, EncodingKey::from_secret(key.as_bytes())),
+        "RS256" => (
+            Algorithm::RS256,
+            EncodingKey::from_rsa_pem(key.as_bytes())
+                .map_err(|e| format!("Invalid RSA private key: {}", e))?,
+        ),
+        "ES256" => (
+            Algorithm::ES256,
+            EncodingKey::from_ec_pem(key.as_bytes())
+                .map_err(|e| format!("Invalid EC private key: {}", e))?,
+        ),
+        other => {
+            return Err(format!(
+                "Unsupported JWT algorithm '{}'; expected HS256, RS256 or ES256",
+                other
+            ))
+        }
+    };
+
+    let token = encode(&Header::new(algorithm), &claims, &encoding_key)
+        .map_err(|e| format!("Failed to sign JWT: {}", e))?;
+
+    Ok(MintedJwt {
+        token,
+        expires_at: claims.get("exp").and_then(|v| v.as_u64()),
+    })
+}
+
+/// Returns a cached token for these claims and key, or mints and caches a new one until
+/// shortly before its `exp`. The key is loaded on every call so a rotated key is never
+/// served tokens signed with the old one.
+pub fn get_or_mint<F>(
+    algorithm: &str,
+    key_secret: &str,
+    claims_template: &str,
+    load_key: F,
+) -> Result<String, String>
+where
+    F: FnOnce(&str) -> Result<String, String>,
+{
+    let key = load_key(key_secret)?;
+    let cache_key = cache_key(algorithm, &key, claims_template);
+    if let Some(cached) = super::cache::get_cached_token(&cache_key) {
+        if super::cache::is_token_valid(&cached) {
+            return Ok(cached.token);
+        }
+    }
+
+    let now = Utc::now();
+    let minted = mint(algorithm, &key, claims_template, now)?;
+
+    if let Some(exp) = minted.expires_at {
+        let refresh_at = exp.saturating_sub(EXPIRY_SKEW_SECONDS);
+        if refresh_at > now.timestamp() as u64 {
+            // Minting is cheap, so locally signed tokens are never persisted
+            super::cache::set_cached_token(cache_key, minted.token.clone(), refresh_at, true);
+        }
+    }
+
+    Ok(minted.token)
+}
+
+/// Tokens are keyed by a digest of the signing inputs, before dynamic values are rendered
+fn cache_key(algorithm: &str, key: &str, claims_template: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    for part in [algorithm.to_uppercase().as_str(), key, claims_template] {
+        hasher.update(part.as_bytes());
+        hasher.update([0u8]);
+    }
+    format!("jwt:{}", hex::encode(hasher.finalize()))
+}
@@ -1,22 +1,40 @@
 pub mod cache;
+pub mod digest;
+pub mod hmac_signing;
+pub mod jwt;
 pub mod preflight;
+pub mod profile;
+pub mod sigv4;
 
+use crate::types::Header;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager, Runtime};
 
 pub fn get_token_cache_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
     let path = app.path().app_cache_dir().map_err(|e| e.to_string())?;
-    Ok(path.join("token_cache.yaml"))
+    Ok(path.join("token_cache.enc"))
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-#[serde(rename_all = "lowercase")]
+/// Earlier versions persisted tokens as plaintext YAML; remove that file if it's still around
+pub fn remove_legacy_token_cache<R: Runtime>(app: &AppHandle<R>) {
+    if let Ok(dir) = app.path().app_cache_dir() {
+        let _ = std::fs::remove_file(dir.join("token_cache.yaml"));
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase", try_from = "String")]
 pub enum AuthType {
+    #[default]
     None,
     Basic,
     Bearer,
     ApiKey,
+    AwsSigV4,
+    Digest,
+    Jwt,
+    Hmac,
 }
 
 impl std::fmt::Display for AuthType {
@@ -26,19 +44,278 @@ impl std::fmt::Display for AuthType {
             AuthType::Basic => write!(f, "basic"),
             AuthType::Bearer => write!(f, "bearer"),
             AuthType::ApiKey => write!(f, "apikey"),
+            AuthType::AwsSigV4 => write!(f, "awssigv4"),
+            AuthType::Digest => write!(f, "digest"),
+            AuthType::Jwt => write!(f, "jwt"),
+            AuthType::Hmac => write!(f, "hmac"),
+        }
+    }
+}
+
+/// Accepts any casing so older files keep loading, but rejects unknown types
+impl TryFrom<String> for AuthType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "" | "none" => Ok(AuthType::None),
+            "basic" => Ok(AuthType::Basic),
+            "bearer" => Ok(AuthType::Bearer),
+            "apikey" => Ok(AuthType::ApiKey),
+            "awssigv4" => Ok(AuthType::AwsSigV4),
+            "digest" => Ok(AuthType::Digest),
+            "jwt" => Ok(AuthType::Jwt),
+            "hmac" => Ok(AuthType::Hmac),
+            _ => Err(format!("Unknown auth type '{}'", value)),
         }
     }
 }
 
+/// Auth of a service, endpoint or tab
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-#[serde(rename_all = "camelCase")]
+#[serde(try_from = "LegacyAuthConfig")]
 pub struct AuthConfig {
-    pub r#type: String,
     pub active: bool,
+    #[serde(flatten)]
+    pub method: AuthMethod,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            active: true,
+            method: AuthMethod::None,
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn auth_type(&self) -> AuthType {
+        match self.method {
+            AuthMethod::None => AuthType::None,
+            AuthMethod::Basic(_) => AuthType::Basic,
+            AuthMethod::Bearer { .. } => AuthType::Bearer,
+            AuthMethod::ApiKey(_) => AuthType::ApiKey,
+            AuthMethod::AwsSigV4(_) => AuthType::AwsSigV4,
+            AuthMethod::Digest(_) => AuthType::Digest,
+            AuthMethod::Jwt(_) => AuthType::Jwt,
+            AuthMethod::Hmac(_) => AuthType::Hmac,
+        }
+    }
+
+    /// A type without any settings, as the app opens endpoint tabs
+    pub fn is_blank(&self) -> bool {
+        match &self.method {
+            AuthMethod::None => true,
+            AuthMethod::Basic(credentials) | AuthMethod::Digest(credentials) => {
+                *credentials == BasicCredentials::default()
+            }
+            AuthMethod::Bearer { bearer_token } => bearer_token.is_empty(),
+            AuthMethod::ApiKey(api_key) => {
+                api_key.api_key_name.is_empty() && api_key.api_key_value.is_empty()
+            }
+            AuthMethod::AwsSigV4(aws) => *aws == AwsSigV4Auth::default(),
+            AuthMethod::Jwt(jwt) => *jwt == JwtAuth::default(),
+            AuthMethod::Hmac(hmac) => *hmac == HmacAuth::default(),
+        }
+    }
+}
+
+/// Each auth method with only its own settings. Serialized with the same `type` tag and
+/// field names as the old flat config, so the UI and existing files keep working.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "lowercase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AuthMethod {
+    None,
+    Basic(BasicCredentials),
+    Bearer {
+        bearer_token: String,
+    },
+    ApiKey(ApiKeyAuth),
+    AwsSigV4(AwsSigV4Auth),
+    /// Answers `WWW-Authenticate: Digest` challenges
+    Digest(BasicCredentials),
+    Jwt(JwtAuth),
+    Hmac(HmacAuth),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BasicCredentials {
     pub basic_user: String,
     pub basic_pass: String,
-    pub bearer_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeyLocation {
+    #[default]
+    Header,
+    Query,
+    Cookie,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyAuth {
     pub api_key_name: String,
     pub api_key_value: String,
-    pub api_key_location: String,
+    pub api_key_location: ApiKeyLocation,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AwsSigV4Auth {
+    /// Keyring entry holding the AWS access key id
+    pub aws_access_key_secret: String,
+    /// Keyring entry holding the AWS secret access key
+    pub aws_secret_key_secret: String,
+    /// Keyring entry holding the AWS session token, if temporary credentials are used
+    #[serde(default)]
+    pub aws_session_token_secret: String,
+    pub aws_region: String,
+    pub aws_service: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct JwtAuth {
+    /// HS256, RS256 or ES256
+    pub jwt_algorithm: String,
+    /// Keyring entry holding the HMAC secret or PEM private key used to sign minted JWTs
+    pub jwt_key_secret: String,
+    /// JSON claims template; supports variables and dynamic values like `{{$timestamp+300}}`
+    pub jwt_claims: String,
+    /// Header receiving the raw token; empty or `Authorization` sends it as a bearer token
+    #[serde(default)]
+    pub jwt_header: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HmacAuth {
+    /// sha256, sha512 or sha1
+    pub hmac_algorithm: String,
+    /// hex or base64
+    pub hmac_encoding: String,
+    /// Keyring entry holding the HMAC signing key
+    pub hmac_key_secret: String,
+    /// Canonical string template, e.g. `{{method}}\n{{path}}\n{{timestamp}}\n{{bodyHash}}`
+    pub hmac_template: String,
+    /// Headers carrying the signature, e.g. `X-Signature: {{signature}}`
+    #[serde(default)]
+    pub hmac_headers: Vec<Header>,
+}
+
+/// The flat shape every auth config used to have, with every method's fields side by side.
+/// New files are written in the same shape minus the other methods' fields, so this reads both.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyAuthConfig {
+    #[serde(default)]
+    r#type: String,
+    #[serde(default = "default_active")]
+    active: bool,
+    #[serde(default)]
+    basic_user: String,
+    #[serde(default)]
+    basic_pass: String,
+    #[serde(default)]
+    bearer_token: String,
+    #[serde(default)]
+    api_key_name: String,
+    #[serde(default)]
+    api_key_value: String,
+    #[serde(default)]
+    api_key_location: String,
+    #[serde(default)]
+    aws_access_key_secret: String,
+    #[serde(default)]
+    aws_secret_key_secret: String,
+    #[serde(default)]
+    aws_session_token_secret: String,
+    #[serde(default)]
+    aws_region: String,
+    #[serde(default)]
+    aws_service: String,
+    #[serde(default)]
+    jwt_algorithm: String,
+    #[serde(default)]
+    jwt_key_secret: String,
+    #[serde(default)]
+    jwt_claims: String,
+    #[serde(default)]
+    jwt_header: String,
+    #[serde(default)]
+    hmac_algorithm: String,
+    #[serde(default)]
+    hmac_encoding: String,
+    #[serde(default)]
+    hmac_key_secret: String,
+    #[serde(default)]
+    hmac_template: String,
+    #[serde(default)]
+    hmac_headers: Vec<Header>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl TryFrom<LegacyAuthConfig> for AuthConfig {
+    type Error = String;
+
+    fn try_from(legacy: LegacyAuthConfig) -> Result<Self, Self::Error> {
+        let basic = || BasicCredentials {
+            basic_user: legacy.basic_user.clone(),
+            basic_pass: legacy.basic_pass.clone(),
+        };
+        let method = match AuthType::try_from(legacy.r#type.clone())? {
+            AuthType::None => AuthMethod::None,
+            AuthType::Basic => AuthMethod::Basic(basic()),
+            AuthType::Digest => AuthMethod::Digest(basic()),
+            AuthType::Bearer => AuthMethod::Bearer {
+                bearer_token: legacy.bearer_token,
+            },
+            AuthType::ApiKey => AuthMethod::ApiKey(ApiKeyAuth {
+                api_key_location: match legacy.api_key_location.to_lowercase().as_str() {
+                    "" | "header" => ApiKeyLocation::Header,
+                    "query" => ApiKeyLocation::Query,
+                    "cookie" => ApiKeyLocation::Cookie,
+                    other => return Err(format!("Unknown API key location '{}'", other)),
+                },
+                api_key_name: legacy.api_key_name,
+                api_key_value: legacy.api_key_value,
+            }),
+            AuthType::AwsSigV4 => AuthMethod::AwsSigV4(AwsSigV4Auth {
+                aws_access_key_secret: legacy.aws_access_key_secret,
+                aws_secret_key_secret: legacy.aws_secret_key_secret,
+                aws_session_token_secret: legacy.aws_session_token_secret,
+                aws_region: legacy.aws_region,
+                aws_service: legacy.aws_service,
+            }),
+            AuthType::Jwt => AuthMethod::Jwt(JwtAuth {
+                jwt_algorithm: legacy.jwt_algorithm,
+                jwt_key_secret: legacy.jwt_key_secret,
+                jwt_claims: legacy.jwt_claims,
+                jwt_header: legacy.jwt_header,
+            }),
+            AuthType::Hmac => AuthMethod::Hmac(HmacAuth {
+                hmac_algorithm: legacy.hmac_algorithm,
+                hmac_encoding: legacy.hmac_encoding,
+                hmac_key_secret: legacy.hmac_key_secret,
+                hmac_template: legacy.hmac_template,
+                hmac_headers: legacy.hmac_headers,
+            }),
+        };
+
+        Ok(AuthConfig {
+            active: legacy.active,
+            method,
+        })
+    }
 }
@@ -1,17 +1,82 @@
-use crate::domains::service::endpoint::PreflightConfig;
+use crate::domains::service::endpoint::{PreflightConfig, PreflightExtraction, PreflightMode};
 use crate::io::HttpClient;
-use crate::types::{Header, PreflightTestResult};
+use crate::types::{Header, Param, PreflightStepResult, PreflightTestResult, QResponse};
+use once_cell::sync::Lazy;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// How often the background task looks for tokens that are about to expire
+pub const REFRESH_INTERVAL_SECONDS: u64 = 30;
+
+/// One async lock per cache key, so concurrent callers share a single token fetch
+static IN_FLIGHT: Lazy<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Everything needed to run a preflight again without a request
+#[derive(Clone)]
+struct RefreshJob {
+    service_id: String,
+    environment: String,
+    config: PreflightConfig,
+    variables: HashMap<String, String>,
+}
+
+/// Preflights that opted into proactive refresh, by cache key
+static REFRESH_JOBS: Lazy<Mutex<HashMap<String, RefreshJob>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Holds the lock of a cache key. The key leaves `IN_FLIGHT` with its last holder, also
+/// when a caller gives up while waiting.
+struct InFlight {
+    key: String,
+    guard: Option<tokio::sync::OwnedMutexGuard<()>>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.guard.take();
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        // Only the registry's reference is left, so nobody else holds or waits on the key
+        if in_flight
+            .get(&self.key)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+async fn lock_key(cache_key: &str) -> InFlight {
+    // Declared before the lock is cloned, so it is dropped after it if the wait is cancelled
+    let mut in_flight = InFlight {
+        key: cache_key.to_string(),
+        guard: None,
+    };
+    let lock = IN_FLIGHT
+        .lock()
+        .unwrap()
+        .entry(cache_key.to_string())
+        .or_default()
+        .clone();
+    in_flight.guard = Some(lock.lock_owned().await);
+    in_flight
+}
+
+/// Cache keys with a preflight running or waiting to run
+pub fn in_flight_keys() -> Vec<String> {
+    IN_FLIGHT.lock().unwrap().keys().cloned().collect()
+}
+
 pub async fn execute_preflight(
     http: &dyn HttpClient,
     service_id: &str,
+    environment: &str,
     config: &PreflightConfig,
     variables: &HashMap<String, String>,
     cache_path: Option<&std::path::PathBuf>,
 ) -> Result<String, String> {
-    let result = test_preflight(http, service_id, config, variables, cache_path).await;
+    let result = test_preflight(http, service_id, environment, config, variables, cache_path).await;
     if result.success {
         Ok(result.token.unwrap_or_default())
     } else {
@@ -19,102 +84,282 @@ pub async fn execute_preflight(
     }
 }
 
-pub async fn test_preflight(
+/// Drops the cached tokens of a service environment and runs its preflight again
+pub async fn refresh_service_token(
     http: &dyn HttpClient,
     service_id: &str,
+    environment: &str,
     config: &PreflightConfig,
     variables: &HashMap<String, String>,
     cache_path: Option<&std::path::PathBuf>,
 ) -> PreflightTestResult {
-    let resolved_url = resolve_variables(&config.url, variables);
-    let mut resolved_body = resolve_variables(&config.body, variables);
+    super::cache::clear_service_tokens(service_id, Some(environment));
+    if let Some(path) = cache_path {
+        let _ = super::cache::save_cache_to_file(path);
+    }
+    test_preflight(http, service_id, environment, config, variables, cache_path).await
+}
 
-    if config.body_type == "application/x-www-form-urlencoded" && !config.body_params.is_empty() {
-        let mut params = Vec::new();
-        for p in &config.body_params {
-            let name = resolve_variables(&p.name, variables);
-            let value = resolve_variables(&p.value, variables);
-            if !name.is_empty() {
-                params.push(format!(
-                    "{}={}",
-                    urlencoding::encode(&name),
-                    urlencoding::encode(&value)
-                ));
-            }
+/// Re-runs the preflights whose tokens expire within their `refresh_before_expiry`
+/// threshold. Returns how many tokens were refreshed.
+pub async fn refresh_expiring_tokens(
+    http: &dyn HttpClient,
+    cache_path: Option<&std::path::PathBuf>,
+) -> usize {
+    let jobs: Vec<(String, RefreshJob)> = REFRESH_JOBS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut refreshed = 0;
+    for (key, job) in jobs {
+        // Evicted tokens are not brought back
+        let Some(cached) = super::cache::get_cached_token(&key) else {
+            REFRESH_JOBS.lock().unwrap().remove(&key);
+            continue;
+        };
+        let threshold = job.config.refresh_before_expiry.unwrap_or_default();
+        if cached.expires_at > now + threshold {
+            continue;
         }
-        if !params.is_empty() {
-            resolved_body = params.join("&");
+
+        let result = run_preflight(
+            http,
+            &job.service_id,
+            &job.environment,
+            &job.config,
+            &job.variables,
+            cache_path,
+            true,
+        )
+        .await;
+        if result.success {
+            refreshed += 1;
+        } else {
+            // Don't keep hitting a failing IdP; the next request will log in again
+            REFRESH_JOBS.lock().unwrap().remove(&key);
         }
     }
-    let mut resolved_headers = Vec::new();
-    let mut request_headers_vec = Vec::new();
-    for h in &config.headers {
-        let name = resolve_variables(&h.name, variables);
-        let value = resolve_variables(&h.value, variables);
-        resolved_headers.push((name.clone(), value.clone()));
-        request_headers_vec.push(Header {
-            name,
-            value,
-            enabled: true,
-            secret_key: None,
-        });
+    refreshed
+}
+
+pub async fn test_preflight(
+    http: &dyn HttpClient,
+    service_id: &str,
+    environment: &str,
+    config: &PreflightConfig,
+    variables: &HashMap<String, String>,
+    cache_path: Option<&std::path::PathBuf>,
+) -> PreflightTestResult {
+    run_preflight(
+        http,
+        service_id,
+        environment,
+        config,
+        variables,
+        cache_path,
+        false,
+    )
+    .await
+}
+
+/// Runs the preflight; `force` skips the cached token but still replaces it
+async fn run_preflight(
+    http: &dyn HttpClient,
+    service_id: &str,
+    environment: &str,
+    config: &PreflightConfig,
+    variables: &HashMap<String, String>,
+    cache_path: Option<&std::path::PathBuf>,
+    force: bool,
+) -> PreflightTestResult {
+    let token_request = resolve_request(
+        &config.url,
+        &config.body,
+        &config.body_type,
+        &config.body_params,
+        &config.headers,
+        variables,
+    );
+
+    // Values extracted by earlier steps are still unresolved here, which keeps the key
+    // stable; the steps themselves are part of the preflight identity.
+    let mut identity = String::new();
+    for step in &config.steps {
+        let step_request = resolve_request(
+            &step.url,
+            &step.body,
+            &step.body_type,
+            &step.body_params,
+            &step.headers,
+            variables,
+        );
+        identity.push_str(&format!(
+            "{} {} {}\n",
+            step.method, step_request.url, step_request.body
+        ));
     }
+    identity.push_str(&token_request.body);
 
     let cache_key = super::cache::generate_key(
         service_id,
-        &resolved_url,
+        environment,
+        &token_request.url,
         &config.method,
-        &resolved_body,
-        &resolved_headers,
+        &identity,
+        &token_request.headers,
     );
 
+    // Held until the token is cached, so callers waiting on the same key find it there
+    let _in_flight = if config.cache_token {
+        Some(lock_key(&cache_key).await)
+    } else {
+        None
+    };
+
     // Check cache
-    if config.cache_token {
+    if config.cache_token && !force {
         if let Some(cached) = super::cache::get_cached_token(&cache_key) {
-            if super::cache::is_token_valid(&cached) {
+            // A token cached before the mode changed is replaced by a fresh login
+            if cached.mode == config.mode && super::cache::is_token_valid(&cached) {
                 return PreflightTestResult {
                     success: true,
                     token: Some(cached.token),
                     error: None,
-                    request_url: resolved_url,
+                    request_url: token_request.url,
                     request_method: config.method.clone(),
-                    request_headers: request_headers_vec,
-                    request_body: resolved_body,
+                    request_headers: to_header_list(&token_request.headers),
+                    request_body: token_request.body,
                     response_status: 200,
                     response_body: "Token served from cache".to_string(),
                     response_headers: vec![],
                     time_elapsed: 0,
+                    steps: vec![],
                 };
             }
         }
     }
 
-    if !resolved_body.is_empty()
-        && config.method.to_uppercase() != "GET"
-        && config.method.to_uppercase() != "HEAD"
-    {
-        resolved_headers.push(("Content-Type".to_string(), config.body_type.clone()));
-        request_headers_vec.push(Header {
-            name: "Content-Type".to_string(),
-            value: config.body_type.clone(),
-            enabled: true,
-            secret_key: None,
+    // Earlier steps feed extracted values into the templates of the steps after them
+    let mut variables = variables.clone();
+    let mut steps = Vec::new();
+    for (index, step) in config.steps.iter().enumerate() {
+        let name = if step.name.is_empty() {
+            format!("Step {}", index + 1)
+        } else {
+            step.name.clone()
+        };
+        let request = resolve_request(
+            &step.url,
+            &step.body,
+            &step.body_type,
+            &step.body_params,
+            &step.headers,
+            &variables,
+        );
+        // Cookies are read from the login response itself, not the page it redirects to
+        let follow_redirects = !step
+            .extractions
+            .iter()
+            .any(|e| e.source.eq_ignore_ascii_case("cookie"));
+        let (request, response_result) = send(
+            http,
+            &step.method,
+            &step.body_type,
+            request,
+            follow_redirects,
+        )
+        .await;
+
+        let mut step_result = PreflightStepResult {
+            name: name.clone(),
+            request_url: request.url,
+            request_method: step.method.clone(),
+            request_headers: to_header_list(&request.headers),
+            request_body: request.body,
+            response_status: 0,
+            response_body: "".to_string(),
+            response_headers: vec![],
+            time_elapsed: 0,
+            extracted: HashMap::new(),
+            error: None,
+        };
+
+        let outcome = response_result.and_then(|response| {
+            step_result.response_status = response.status;
+            step_result.response_headers = response.headers.clone();
+            step_result.time_elapsed = response.time_elapsed;
+            let extracted = if is_success(response.status) {
+                step.extractions
+                    .iter()
+                    .map(|e| extract(e, &response).map(|value| (e.variable.clone(), value)))
+                    .collect::<Result<HashMap<_, _>, _>>()
+            } else {
+                Err(format!("status {}", response.status))
+            };
+            step_result.response_body = response.body;
+            extracted
         });
+
+        match outcome {
+            Ok(extracted) => {
+                variables.extend(extracted.clone());
+                step_result.extracted = extracted;
+                steps.push(step_result);
+            }
+            Err(e) => {
+                let error = format!("Preflight step '{}' failed: {}", name, e);
+                step_result.error = Some(error.clone());
+                let failed = step_result.clone();
+                steps.push(step_result);
+                return PreflightTestResult {
+                    success: false,
+                    token: None,
+                    error: Some(error),
+                    request_url: failed.request_url,
+                    request_method: failed.request_method,
+                    request_headers: failed.request_headers,
+                    request_body: failed.request_body,
+                    response_status: failed.response_status,
+                    response_body: failed.response_body,
+                    response_headers: failed.response_headers,
+                    time_elapsed: failed.time_elapsed,
+                    steps,
+                };
+            }
+        }
     }
 
-    let response_result = http
-        .send_request(
-            &config.method,
-            &resolved_url,
-            resolved_headers,
-            if resolved_body.is_empty() {
-                None
-            } else {
-                Some(resolved_body.clone())
-            },
-            vec![],
+    let token_request = if config.steps.is_empty() {
+        token_request
+    } else {
+        resolve_request(
+            &config.url,
+            &config.body,
+            &config.body_type,
+            &config.body_params,
+            &config.headers,
+            &variables,
         )
-        .await;
+    };
+    let follow_redirects = config.mode == PreflightMode::Token;
+    let (token_request, response_result) = send(
+        http,
+        &config.method,
+        &config.body_type,
+        token_request,
+        follow_redirects,
+    )
+    .await;
+    let request_headers_vec = to_header_list(&token_request.headers);
+    let resolved_url = token_request.url;
+    let resolved_body = token_request.body;
 
     match response_result {
         Ok(response) => {
@@ -129,56 +374,50 @@ pub async fn test_preflight(
                 })
                 .collect();
 
-            let response_json_result: Result<serde_json::Value, _> =
-                serde_json::from_str(&response.body);
-
-            let token_result = match response_json_result {
-                Ok(response_json) => {
-                    let token_opt = response_json
-                        .get(&config.token_key)
-                        .and_then(|v| v.as_str())
-                        .map(|t| t.to_string());
-
-                    match token_opt {
-                        Some(token) => Ok((token, response_json)),
-                        None => Err(format!(
-                            "Token key '{}' not found in preflight response",
-                            config.token_key
-                        )),
-                    }
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+
+            let token_result = if !is_success(response.status) {
+                Err(format!(
+                    "Preflight request failed with status {}",
+                    response.status
+                ))
+            } else {
+                match config.mode {
+                    PreflightMode::Cookies => session_cookies(&response.headers, now),
+                    PreflightMode::Token => token_from_json(config, &response.body),
                 }
-                Err(e) => Err(format!("Preflight response is not valid JSON: {}", e)),
             };
 
             match token_result {
-                Ok((token, response_json)) => {
+                Ok((token, derived_duration)) => {
                     // Update cache
                     if config.cache_token {
                         let expires_in_seconds = if config.cache_duration == "derived" {
-                            let duration_value = response_json
-                                .get(&config.cache_duration_key)
-                                .and_then(|v| v.as_u64())
-                                .unwrap_or(3600); // Default if key missing to avoid testing failure? Or fail? Logic in execute was strict
-
-                            match config.cache_duration_unit.as_str() {
-                                "seconds" => duration_value,
-                                "minutes" => duration_value * 60,
-                                "hours" => duration_value * 3600,
-                                "days" => duration_value * 86400,
-                                _ => duration_value,
-                            }
+                            derived_duration.unwrap_or(3600)
                         } else {
                             config.cache_duration.parse::<u64>().unwrap_or(3600)
                         };
 
-                        let now = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs();
-                        super::cache::set_cached_token(
+                        if config.refresh_before_expiry.is_some() {
+                            REFRESH_JOBS.lock().unwrap().insert(
+                                cache_key.clone(),
+                                RefreshJob {
+                                    service_id: service_id.to_string(),
+                                    environment: environment.to_string(),
+                                    config: config.clone(),
+                                    variables: variables.clone(),
+                                },
+                            );
+                        }
+                        super::cache::set_cached_session(
                             cache_key,
                             token.clone(),
                             now + expires_in_seconds,
+                            config.secret_token,
+                            config.mode,
                         );
 
                         if let Some(path) = cache_path {
@@ -198,6 +437,7 @@ pub async fn test_preflight(
                         response_body: response.body,
                         response_headers: response_headers_vec,
                         time_elapsed: response.time_elapsed,
+                        steps,
                     }
                 }
                 Err(e) => PreflightTestResult {
@@ -212,6 +452,7 @@ pub async fn test_preflight(
                     response_body: response.body,
                     response_headers: response_headers_vec,
                     time_elapsed: response.time_elapsed,
+                    steps,
                 },
             }
         }
@@ -227,10 +468,230 @@ pub async fn test_preflight(
             response_body: "".to_string(),
             response_headers: vec![],
             time_elapsed: 0,
+            steps,
         },
     }
 }
 
+/// A preflight request with variables resolved
+struct ResolvedRequest {
+    url: String,
+    body: String,
+    headers: Vec<(String, String)>,
+}
+
+fn resolve_request(
+    url: &str,
+    body: &str,
+    body_type: &str,
+    body_params: &[Param],
+    headers: &[Header],
+    variables: &HashMap<String, String>,
+) -> ResolvedRequest {
+    let resolved_url = resolve_variables(url, variables);
+    let mut resolved_body = resolve_variables(body, variables);
+
+    if body_type == "application/x-www-form-urlencoded" && !body_params.is_empty() {
+        let mut params = Vec::new();
+        for p in body_params {
+            let name = resolve_variables(&p.name, variables);
+            let value = resolve_variables(&p.value, variables);
+            if !name.is_empty() {
+                params.push(format!(
+                    "{}={}",
+                    urlencoding::encode(&name),
+                    urlencoding::encode(&value)
+                ));
+            }
+        }
+        if !params.is_empty() {
+            resolved_body = params.join("&");
+        }
+    }
+    let resolved_headers = headers
+        .iter()
+        .map(|h| {
+            (
+                resolve_variables(&h.name, variables),
+                resolve_variables(&h.value, variables),
+            )
+        })
+        .collect();
+
+    ResolvedRequest {
+        url: resolved_url,
+        body: resolved_body,
+        headers: resolved_headers,
+    }
+}
+
+/// Sends a resolved request; the returned request includes the added Content-Type
+async fn send(
+    http: &dyn HttpClient,
+    method: &str,
+    body_type: &str,
+    mut request: ResolvedRequest,
+    follow_redirects: bool,
+) -> (ResolvedRequest, Result<QResponse, String>) {
+    if !request.body.is_empty() && method.to_uppercase() != "GET" && method.to_uppercase() != "HEAD"
+    {
+        request
+            .headers
+            .push(("Content-Type".to_string(), body_type.to_string()));
+    }
+
+    let body = if request.body.is_empty() {
+        None
+    } else {
+        Some(request.body.clone())
+    };
+    let response = if follow_redirects {
+        http.send_request(method, &request.url, request.headers.clone(), body, vec![])
+            .await
+    } else {
+        http.send_request_without_redirects(
+            method,
+            &request.url,
+            request.headers.clone(),
+            body,
+            vec![],
+        )
+        .await
+    };
+    (request, response)
+}
+
+fn to_header_list(headers: &[(String, String)]) -> Vec<Header> {
+    headers
+        .iter()
+        .map(|(name, value)| Header {
+            name: name.clone(),
+            value: value.clone(),
+            enabled: true,
+            secret_key: None,
+        })
+        .collect()
+}
+
+/// Redirects count as success, session logins typically answer with one
+fn is_success(status: u16) -> bool {
+    (200..400).contains(&status)
+}
+
+/// Reads `tokenKey` from a JSON body, along with the `cacheDurationKey` lifetime in seconds
+fn token_from_json(config: &PreflightConfig, body: &str) -> Result<(String, Option<u64>), String> {
+    let json: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| format!("Preflight response is not valid JSON: {}", e))?;
+    let token = json
+        .get(&config.token_key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| {
+            format!(
+                "Token key '{}' not found in preflight response",
+                config.token_key
+            )
+        })?;
+
+    let duration = json
+        .get(&config.cache_duration_key)
+        .and_then(|v| v.as_u64())
+        .map(|value| match config.cache_duration_unit.as_str() {
+            "minutes" => value * 60,
+            "hours" => value * 3600,
+            "days" => value * 86400,
+            _ => value,
+        });
+    Ok((token.to_string(), duration))
+}
+
+/// Joins the cookies set by a login response into a `Cookie` header value, along with the
+/// shortest `Max-Age` / `Expires` lifetime among them. Cookies being deleted are skipped.
+fn session_cookies(headers: &[Header], now: u64) -> Result<(String, Option<u64>), String> {
+    let mut pairs = Vec::new();
+    let mut shortest: Option<u64> = None;
+
+    for header in headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case("set-cookie"))
+    {
+        let mut parts = header.value.split(';');
+        let Some((name, value)) = parts.next().and_then(|pair| pair.split_once('=')) else {
+            continue;
+        };
+
+        let mut max_age: Option<i64> = None;
+        let mut expires: Option<i64> = None;
+        for attribute in parts {
+            let (key, val) = attribute.split_once('=').unwrap_or((attribute, ""));
+            match key.trim().to_lowercase().as_str() {
+                "max-age" => max_age = val.trim().parse().ok(),
+                "expires" => {
+                    expires = chrono::DateTime::parse_from_rfc2822(val.trim())
+                        .ok()
+                        .map(|t| t.timestamp() - now as i64)
+                }
+                _ => {}
+            }
+        }
+
+        // Max-Age takes precedence over Expires
+        match max_age.or(expires) {
+            Some(lifetime) if lifetime <= 0 => continue,
+            Some(lifetime) => {
+                shortest = Some(shortest.map_or(lifetime as u64, |s| s.min(lifetime as u64)))
+            }
+            None => {}
+        }
+        pairs.push(format!("{}={}", name.trim(), value.trim()));
+    }
+
+    if pairs.is_empty() {
+        return Err("Preflight response did not set any cookies".to_string());
+    }
+    Ok((pairs.join("; "), shortest))
+}
+
+/// Reads a value out of a step response: a dot path into the JSON body
+/// (`data.items.0.id`), a header name, or a cookie name from `Set-Cookie`
+pub fn extract(extraction: &PreflightExtraction, response: &QResponse) -> Result<String, String> {
+    let path = extraction.path.trim();
+    let found = match extraction.source.as_str() {
+        "header" => response
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(path))
+            .map(|h| h.value.clone()),
+        "cookie" => response
+            .headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("set-cookie"))
+            .filter_map(|h| h.value.split(';').next()?.split_once('='))
+            .find(|(name, _)| name.trim() == path)
+            .map(|(_, value)| value.trim().to_string()),
+        _ => {
+            let json: serde_json::Value = serde_json::from_str(&response.body)
+                .map_err(|e| format!("Response is not valid JSON: {}", e))?;
+            path.split('.')
+                .filter(|segment| !segment.is_empty())
+                .try_fold(&json, |value, segment| match value {
+                    serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+                    _ => value.get(segment),
+                })
+                .map(|value| match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+        }
+    };
+
+    found.ok_or_else(|| {
+        format!(
+            "'{}' not found in response {}",
+            extraction.path, extraction.source
+        )
+    })
+}
+
 fn resolve_variables(text: &str, variables: &HashMap<String, String>) -> String {
     let re = regex::Regex::new(r"\{\{([^}]+)\}\}").expect("Invalid regex");
     let mut result = text.to_string();
@@ -0,0 +1,32 @@
+use super::AuthConfig;
+use crate::domains::service::endpoint::PreflightConfig;
+use crate::domains::service::service::Service;
+use serde::{Deserialize, Serialize};
+
+/// A named auth and preflight definition that services and endpoints reference by id.
+/// Everything using the same profile shares one cached token.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthProfile {
+    pub id: String,
+    pub name: String,
+    pub auth: AuthConfig,
+    pub preflight: PreflightConfig,
+}
+
+/// Token cache scope of a profile, used in place of a service id
+pub fn cache_scope(profile_id: &str) -> String {
+    format!("profile:{}", profile_id)
+}
+
+/// The profile referenced by an endpoint, falling back to the one of its service
+pub fn referenced_profile_id(service: &Service, endpoint_id: Option<&str>) -> Option<String> {
+    endpoint_id
+        .and_then(|id| service.endpoints.iter().find(|e| e.id == id))
+        .and_then(|e| e.auth_profile_id.clone())
+        .or_else(|| service.auth_profile_id.clone())
+}
+
+pub fn find_profile<'a>(profiles: &'a [AuthProfile], id: &str) -> Option<&'a AuthProfile> {
+    profiles.iter().find(|p| p.id == id)
+}
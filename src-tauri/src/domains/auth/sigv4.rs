@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+
+/// Credentials and credential scope used to sign a request with AWS Signature Version 4
+#[derive(Debug, Clone)]
+pub struct SigV4Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub session_token: Option<String>,
+    pub region: String,
+    pub service: String,
+}
+
+/// Signs a fully built request, adding `X-Amz-Date`, `X-Amz-Security-Token` (when a session
+/// token is present) and `Authorization` to `headers`.
+///
+/// The extra `query` pairs are merged into the URL with the same encoding used for the
+/// canonical request, so the returned URL must be sent as-is without any further query.
+pub fn sign_request(
+    method: &str,
+    url: &str,
+    query: &[(String, String)],
+    headers: &mut Vec<(String, String)>,
+    body: &[u8],
+    credentials: &SigV4Credentials,
+    now: DateTime<Utc>,
+) -> Result<String, String> {
+    let mut parsed = Url::parse(url).map_err(|e| format!("Invalid URL for SigV4: {}", e))?;
+    let host = match (parsed.host_str(), parsed.port()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        _ => return Err(format!("URL has no host to sign: {}", url)),
+    };
+
+    let mut pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(k, v)| (uri_encode(&k), uri_encode(&v)))
+        .collect();
+    for (k, v) in query {
+        pairs.push((uri_encode(k), uri_encode(v)));
+    }
+    pairs.sort();
+    let canonical_query = pairs
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+    parsed.set_query(if canonical_query.is_empty() {
+        None
+    } else {
+        Some(&canonical_query)
+    });
+
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+
+    headers.retain(|(name, _)| {
+        let name = name.to_lowercase();
+        name != "x-amz-date" && name != "x-amz-security-token" && name != "authorization"
+    });
+    headers.push(("X-Amz-Date".to_string(), amz_date.clone()));
+    if let Some(token) = credentials.session_token.as_ref().filter(|t| !t.is_empty()) {
+        headers.push(("X-Amz-Security-Token".to_string(), token.clone()));
+    }
+
+    // Header names are lowercased and sorted; repeated headers are joined with commas
+    let mut canonical: Vec<(String, String)> = Vec::new();
+    if !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case("host")) {
+        canonical.push(("host".to_string(), host));
+    }
+    for (name, value) in headers.iter() {
+        let name = name.to_lowercase();
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        match canonical.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => {
+                existing.push(',');
+                existing.push_str(&value);
+            }
+            None => canonical.push((name, value)),
+        }
+    }
+    canonical.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let canonical_headers: String = canonical
+        .iter()
+        .map(|(n, v)| format!("{}:{}\n", n, v))
+        .collect();
+    let signed_headers = canonical
+        .iter()
+        .map(|(n, _)| n.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method.to_uppercase(),
+        canonical_uri(parsed.path(), &credentials.service),
+        canonical_query,
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(body))
+    );
+
+    let scope = format!(
+        "{}/{}/{}/aws4_request",
+        date, credentials.region, credentials.service
+    );
+    let string_to_sign = format!(
+        "{}\n{}\n{}\n{}",
+        ALGORITHM,
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let k_date = hmac_sha256(
+        format!("AWS4{}", credentials.secret_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let k_region = hmac_sha256(&k_date, credentials.region.as_bytes());
+    let k_service = hmac_sha256(&k_region, credentials.service.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    headers.push((
+        "Authorization".to_string(),
+        format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM, credentials.access_key, scope, signed_headers, signature
+        ),
+    ));
+
+    Ok(parsed.to_string())
+}
+
+/// The URL path is already percent-encoded once; every service except S3 expects
+/// each segment to be encoded a second time.
+fn canonical_uri(path: &str, service: &str) -> String {
+    if path.is_empty() {
+        return "/".to_string();
+    }
+    if service == "s3" {
+        return path.to_string();
+    }
+    path.split('/')
+        .map(uri_encode)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn uri_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
@@ -0,0 +1,66 @@
+use crate::io::FileSystem;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// One row of a data file: variable name to value
+pub type DataRow = HashMap<String, String>;
+
+/// Reads a `.csv` file with a header row, or a `.json` array of objects
+pub fn load_data_file(fs: &dyn FileSystem, path: &Path) -> Result<Vec<DataRow>, String> {
+    let content = fs.read_to_string(path)?;
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("csv") => parse_csv(&content),
+        Some("json") => parse_json(&content),
+        _ => Err(format!(
+            "Unsupported data file {}: expected .csv or .json",
+            path.display()
+        )),
+    }
+}
+
+pub fn parse_csv(content: &str) -> Result<Vec<DataRow>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::Headers)
+        .from_reader(content.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    reader
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            let record = record.map_err(|e| format!("Row {}: {}", i + 1, e))?;
+            Ok(headers
+                .iter()
+                .zip(record.iter())
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+/// Strings are used as they are, other values as JSON and null as an empty string
+pub fn parse_json(content: &str) -> Result<Vec<DataRow>, String> {
+    let rows: Vec<Value> = serde_json::from_str(content)
+        .map_err(|e| format!("Expected a JSON array of objects: {}", e))?;
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| match row {
+            Value::Object(fields) => Ok(fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Value::String(s) => s,
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    };
+                    (name, value)
+                })
+                .collect()),
+            _ => Err(format!("Row {} is not an object", i + 1)),
+        })
+        .collect()
+}
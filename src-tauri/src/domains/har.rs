@@ -0,0 +1,621 @@
+//! HAR 1.2 files: picking browser requests as endpoints and handing history to others
+
+use crate::domains::import::{
+    form_body, name_value, new_endpoint, service_base_url, set_base_url, set_content_type,
+};
+use crate::domains::runner::{RunExchange, RunSummary};
+use crate::io::FileSystem;
+use crate::types::{Endpoint, ExampleResponse, Header, HistoryEntry, NameValue, Service};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use url::Url;
+
+/// Set by the browser or the connection rather than by the request itself
+const SKIPPED_HEADERS: [&str; 6] = [
+    "host",
+    "connection",
+    "content-length",
+    "accept-encoding",
+    "keep-alive",
+    "transfer-encoding",
+];
+const AUTH_HEADERS: [&str; 3] = ["authorization", "proxy-authorization", "x-api-key"];
+const COOKIE_HEADERS: [&str; 1] = ["cookie"];
+/// Hidden from exports unless credentials are asked for
+const CREDENTIAL_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "cookie",
+    "set-cookie",
+];
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Har {
+    pub log: HarLog,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HarLog {
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub creator: HarCreator,
+    #[serde(default)]
+    pub entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct HarCreator {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    #[serde(default)]
+    pub started_date_time: String,
+    /// Total time in milliseconds
+    #[serde(default)]
+    pub time: f64,
+    pub request: HarRequest,
+    pub response: HarResponse,
+    #[serde(default)]
+    pub cache: serde_json::Value,
+    #[serde(default)]
+    pub timings: HarTimings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<HarPair>,
+    #[serde(default)]
+    pub query_string: Vec<HarPair>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_data: Option<HarPostData>,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HarPair {
+    pub name: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HarPostData {
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default)]
+    pub text: String,
+    /// Form fields, set instead of `text` by some browsers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<HarParam>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HarParam {
+    pub name: String,
+    #[serde(default)]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    /// 0 when no response arrived
+    pub status: u16,
+    #[serde(default)]
+    pub status_text: String,
+    #[serde(default)]
+    pub http_version: String,
+    #[serde(default)]
+    pub cookies: Vec<serde_json::Value>,
+    #[serde(default)]
+    pub headers: Vec<HarPair>,
+    #[serde(default)]
+    pub content: HarContent,
+    #[serde(default, rename = "redirectURL")]
+    pub redirect_url: String,
+    #[serde(default = "unknown_size")]
+    pub headers_size: i64,
+    #[serde(default = "unknown_size")]
+    pub body_size: i64,
+    /// Why the request failed, a custom field as the HAR spec allows
+    #[serde(default, rename = "_error", skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    #[serde(default)]
+    pub size: i64,
+    #[serde(default)]
+    pub mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct HarTimings {
+    #[serde(default)]
+    pub send: f64,
+    #[serde(default)]
+    pub wait: f64,
+    #[serde(default)]
+    pub receive: f64,
+}
+
+fn unknown_size() -> i64 {
+    -1
+}
+
+/// One line of the entry picker
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntrySummary {
+    pub index: usize,
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    pub mime_type: String,
+    pub started_date_time: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HarImportOptions {
+    /// Drop `Cookie` headers
+    #[serde(default)]
+    pub strip_cookies: bool,
+    /// Drop `Authorization`, `Proxy-Authorization` and `X-Api-Key` headers
+    #[serde(default)]
+    pub strip_auth: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HarExportOptions {
+    /// Keep the values of `Authorization`, `Proxy-Authorization`, `X-Api-Key`, `Cookie` and
+    /// `Set-Cookie` headers, which are redacted otherwise
+    #[serde(default)]
+    pub include_credentials: bool,
+}
+
+pub fn parse_har(content: &str) -> Result<Har, String> {
+    serde_json::from_str(content).map_err(|e| format!("Invalid HAR file: {}", e))
+}
+
+pub fn load_har(fs: &dyn FileSystem, path: &Path) -> Result<Har, String> {
+    parse_har(&fs.read_to_string(path)?)
+}
+
+pub fn save_har(fs: &dyn FileSystem, path: &Path, har: &Har) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !fs.exists(parent) {
+            fs.create_dir_all(parent)?;
+        }
+    }
+    let content = serde_json::to_string_pretty(har).map_err(|e| e.to_string())?;
+    fs.write(path, &content)
+}
+
+pub fn entry_summaries(har: &Har) -> Vec<HarEntrySummary> {
+    har.log
+        .entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| HarEntrySummary {
+            index,
+            method: entry.request.method.clone(),
+            url: entry.request.url.clone(),
+            status: entry.response.status,
+            mime_type: entry.response.content.mime_type.clone(),
+            started_date_time: entry.started_date_time.clone(),
+        })
+        .collect()
+}
+
+/// One endpoint per chosen entry, keeping its full URL, headers, query and body. The response
+/// becomes the endpoint's example. [`add_to_service`] moves the origin into `BASE_URL`.
+pub fn endpoints_from_har(
+    har: &Har,
+    indices: &[usize],
+    options: &HarImportOptions,
+    service_id: &str,
+) -> Result<Vec<Endpoint>, String> {
+    indices
+        .iter()
+        .map(|&index| {
+            let entry = har
+                .log
+                .entries
+                .get(index)
+                .ok_or_else(|| format!("HAR file has no entry {}", index))?;
+            Ok(endpoint_from_entry(entry, options, service_id))
+        })
+        .collect()
+}
+
+fn endpoint_from_entry(entry: &HarEntry, options: &HarImportOptions, service_id: &str) -> Endpoint {
+    let request = &entry.request;
+    let (url, query) = match Url::parse(&request.url) {
+        Ok(mut parsed) => {
+            let query: Vec<NameValue> = parsed
+                .query_pairs()
+                .map(|(name, value)| name_value(&name, &value, true))
+                .collect();
+            parsed.set_query(None);
+            parsed.set_fragment(None);
+            (parsed.to_string(), query)
+        }
+        Err(_) => (request.url.clone(), vec![]),
+    };
+    let path = Url::parse(&url)
+        .map(|u| u.path().to_string())
+        .unwrap_or_else(|_| url.clone());
+    let mut endpoint = new_endpoint(
+        service_id,
+        &format!("{} {}", request.method.to_uppercase(), path),
+        &request.method,
+        &url,
+    );
+    endpoint.params = if request.query_string.is_empty() {
+        query
+    } else {
+        request
+            .query_string
+            .iter()
+            .map(|q| name_value(&q.name, &q.value, true))
+            .collect()
+    };
+    endpoint.headers = request
+        .headers
+        .iter()
+        .filter(|h| keep_header(&h.name, options))
+        .map(|h| name_value(&h.name, &h.value, true))
+        .collect();
+    // Stripped credentials are left to the service auth
+    endpoint.authenticated = options.strip_auth
+        && request
+            .headers
+            .iter()
+            .any(|h| AUTH_HEADERS.contains(&h.name.to_ascii_lowercase().as_str()));
+
+    if let Some(post_data) = &request.post_data {
+        endpoint.body = if post_data.text.is_empty() && !post_data.params.is_empty() {
+            let fields: Vec<NameValue> = post_data
+                .params
+                .iter()
+                .map(|p| name_value(&p.name, p.value.as_deref().unwrap_or_default(), true))
+                .collect();
+            form_body(&fields)
+        } else {
+            post_data.text.clone()
+        };
+        if !post_data.mime_type.is_empty() {
+            set_content_type(&mut endpoint.headers, &post_data.mime_type);
+        }
+    }
+
+    if entry.response.status > 0 {
+        let content = &entry.response.content;
+        let content_type = entry
+            .response
+            .headers
+            .iter()
+            .filter(|h| h.name.eq_ignore_ascii_case("content-type"))
+            .map(|h| name_value(&h.name, &h.value, true))
+            .collect();
+        endpoint.examples.push(ExampleResponse {
+            name: entry.response.status.to_string(),
+            status: entry.response.status,
+            headers: content_type,
+            body: content_text(content),
+        });
+    }
+    endpoint
+}
+
+/// Adds endpoints to the service, skipping requests it already has. The URL of each becomes
+/// `BASE_URL` plus a path; a service without `BASE_URL` takes the origin of the first
+/// endpoint, in a new environment when it has none. Other origins keep their full URL.
+pub fn add_to_service(service: &mut Service, endpoints: Vec<Endpoint>) {
+    let mut base_url = service_base_url(service);
+    for mut endpoint in endpoints {
+        if base_url.is_none() {
+            base_url = origin(&endpoint.url);
+            if let Some(base_url) = &base_url {
+                set_base_url(service, base_url);
+            }
+        }
+        if let Some(path) = base_url
+            .as_deref()
+            .and_then(|base_url| endpoint.url.strip_prefix(base_url.trim_end_matches('/')))
+            .filter(|path| path.starts_with('/'))
+        {
+            endpoint.url = path.to_string();
+        }
+        let exists = service
+            .endpoints
+            .iter()
+            .any(|e| e.method.eq_ignore_ascii_case(&endpoint.method) && e.url == endpoint.url);
+        if !exists {
+            service.endpoints.push(endpoint);
+        }
+    }
+}
+
+fn origin(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()
+        .filter(|u| matches!(u.scheme(), "http" | "https"))
+        .map(|u| u.origin().ascii_serialization())
+}
+
+fn keep_header(name: &str, options: &HarImportOptions) -> bool {
+    let name = name.to_ascii_lowercase();
+    let name = name.as_str();
+    // HTTP/2 pseudo headers such as `:authority`
+    if name.starts_with(':') || SKIPPED_HEADERS.contains(&name) {
+        return false;
+    }
+    let stripped = (options.strip_auth && AUTH_HEADERS.contains(&name))
+        || (options.strip_cookies && COOKIE_HEADERS.contains(&name));
+    !stripped
+}
+
+/// Base64 content is decoded when it is text
+fn content_text(content: &HarContent) -> String {
+    let text = content.text.clone().unwrap_or_default();
+    if content.encoding.as_deref() != Some("base64") {
+        return text;
+    }
+    base64::engine::general_purpose::STANDARD
+        .decode(text.trim())
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or(text)
+}
+
+fn new_har(entries: Vec<HarEntry>) -> Har {
+    Har {
+        log: HarLog {
+            version: "1.2".to_string(),
+            creator: HarCreator {
+                name: "xrest".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            },
+            entries,
+        },
+    }
+}
+
+fn pairs(headers: &[Header], options: &HarExportOptions) -> Vec<HarPair> {
+    headers
+        .iter()
+        .filter(|h| h.enabled)
+        .map(|h| {
+            let redacted = !options.include_credentials
+                && CREDENTIAL_HEADERS.contains(&h.name.to_ascii_lowercase().as_str());
+            HarPair {
+                name: h.name.clone(),
+                value: if redacted {
+                    REDACTED.to_string()
+                } else {
+                    h.value.clone()
+                },
+            }
+        })
+        .collect()
+}
+
+fn query_string(url: &str) -> Vec<HarPair> {
+    Url::parse(url)
+        .map(|u| {
+            u.query_pairs()
+                .map(|(name, value)| HarPair {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn content_type(headers: &[Header]) -> String {
+    headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case("content-type"))
+        .map(|h| h.value.clone())
+        .unwrap_or_default()
+}
+
+fn request(
+    method: &str,
+    url: &str,
+    headers: &[Header],
+    body: &str,
+    options: &HarExportOptions,
+) -> HarRequest {
+    HarRequest {
+        method: method.to_string(),
+        url: url.to_string(),
+        http_version: "HTTP/1.1".to_string(),
+        cookies: vec![],
+        headers: pairs(headers, options),
+        query_string: query_string(url),
+        post_data: (!body.is_empty()).then(|| HarPostData {
+            mime_type: content_type(headers),
+            text: body.to_string(),
+            params: vec![],
+        }),
+        headers_size: -1,
+        body_size: body.len() as i64,
+    }
+}
+
+fn timings(time_elapsed: u64) -> HarTimings {
+    HarTimings {
+        send: 0.0,
+        wait: time_elapsed as f64,
+        receive: 0.0,
+    }
+}
+
+fn response(
+    status: u16,
+    status_text: &str,
+    headers: &[Header],
+    body: &str,
+    options: &HarExportOptions,
+) -> HarResponse {
+    HarResponse {
+        status,
+        status_text: status_text.to_string(),
+        http_version: "HTTP/1.1".to_string(),
+        cookies: vec![],
+        headers: pairs(headers, options),
+        content: HarContent {
+            size: body.len() as i64,
+            mime_type: content_type(headers),
+            text: Some(body.to_string()),
+            encoding: None,
+        },
+        redirect_url: String::new(),
+        headers_size: -1,
+        body_size: body.len() as i64,
+        error: None,
+    }
+}
+
+/// HAR of history entries, in the given order
+pub fn history_to_har(entries: &[HistoryEntry], options: &HarExportOptions) -> Har {
+    new_har(
+        entries
+            .iter()
+            .map(|entry| HarEntry {
+                started_date_time: entry.created_at.clone(),
+                time: entry.time_elapsed as f64,
+                request: request(
+                    &entry.method,
+                    &entry.url,
+                    &entry.request_headers,
+                    &entry.request_body,
+                    options,
+                ),
+                response: response(
+                    entry.response_status,
+                    &entry.response_status_text,
+                    &entry.response_headers,
+                    &entry.response_body,
+                    options,
+                ),
+                cache: serde_json::json!({}),
+                timings: timings(entry.time_elapsed),
+                comment: None,
+            })
+            .collect(),
+    )
+}
+
+/// HAR of a run, one entry per request that was sent. The comment names the endpoint and
+/// what failed. Results kept without their exchange, such as those of monitors, only carry
+/// the method, URL, status and timing, dated `started_date_time`.
+pub fn run_to_har(
+    summary: &RunSummary,
+    started_date_time: &str,
+    options: &HarExportOptions,
+) -> Har {
+    new_har(
+        summary
+            .results
+            .iter()
+            .filter(|result| !result.skipped)
+            .map(|result| {
+                let mut failures: Vec<String> = result
+                    .assertions
+                    .iter()
+                    .filter(|a| !a.passed)
+                    .map(|a| a.message.clone())
+                    .collect();
+                failures.extend(result.script_errors.iter().cloned());
+                let comment = if failures.is_empty() {
+                    result.name.clone()
+                } else {
+                    format!("{}: {}", result.name, failures.join("; "))
+                };
+                let exchange = result
+                    .exchange
+                    .as_deref()
+                    .cloned()
+                    .unwrap_or_else(|| RunExchange {
+                        started_at: started_date_time.to_string(),
+                        url: result.url.clone(),
+                        ..RunExchange::default()
+                    });
+                let mut response = match result.status {
+                    Some(status) => response(
+                        status,
+                        &exchange.response_status_text,
+                        &exchange.response_headers,
+                        &exchange.response_body,
+                        options,
+                    ),
+                    None => HarResponse {
+                        status: 0,
+                        status_text: String::new(),
+                        http_version: "HTTP/1.1".to_string(),
+                        cookies: vec![],
+                        headers: vec![],
+                        content: HarContent::default(),
+                        redirect_url: String::new(),
+                        headers_size: -1,
+                        body_size: -1,
+                        error: None,
+                    },
+                };
+                response.error = result.error.clone();
+                HarEntry {
+                    started_date_time: exchange.started_at.clone(),
+                    time: result.time_elapsed as f64,
+                    request: request(
+                        &result.method,
+                        &exchange.url,
+                        &exchange.request_headers,
+                        &exchange.request_body,
+                        options,
+                    ),
+                    response,
+                    cache: serde_json::json!({}),
+                    timings: timings(result.time_elapsed),
+                    comment: Some(comment),
+                }
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,504 @@
+//! Bruno collection import: a folder of `.bru` files with a `bruno.json` at its root
+
+use super::{
+    apply_endpoint_auth, apply_service_auth, environment, form_body, merge_variables,
+    multipart_body, name_value, new_endpoint, new_service, set_content_type, split_query,
+    ImportResult, ImportedAuth, OAuth2Grant, PathVariables, MULTIPART_BOUNDARY,
+};
+use crate::domains::auth::{ApiKeyAuth, ApiKeyLocation, AuthMethod, BasicCredentials};
+use crate::io::FileSystem;
+use crate::types::{Endpoint, NameValue};
+use std::path::Path;
+
+const METHODS: [&str; 9] = [
+    "get", "post", "put", "delete", "patch", "options", "head", "connect", "trace",
+];
+
+/// One `name { ... }` or `name [ ... ]` block of a `.bru` file, with its lines unindented
+#[derive(Debug, Clone, PartialEq)]
+pub struct BruBlock {
+    pub name: String,
+    pub lines: Vec<String>,
+}
+
+impl BruBlock {
+    /// `key: value` entries; a leading `~` marks a disabled entry
+    pub fn pairs(&self) -> Vec<NameValue> {
+        self.lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (enabled, line) = match line.strip_prefix('~') {
+                    Some(rest) => (false, rest),
+                    None => (true, line.as_str()),
+                };
+                let (key, value) = line.split_once(':').unwrap_or((line, ""));
+                name_value(key.trim(), value.trim(), enabled)
+            })
+            .collect()
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.pairs()
+            .into_iter()
+            .find(|p| p.name == key)
+            .map(|p| p.value)
+            .unwrap_or_default()
+    }
+
+    pub fn text(&self) -> String {
+        self.lines.join("\n").trim_end().to_string()
+    }
+
+    /// Entries of a `[ ... ]` list block
+    pub fn items(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| line.trim().trim_end_matches(',').to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
+
+/// Splits a `.bru` file into its blocks
+pub fn parse_bru(content: &str) -> Result<Vec<BruBlock>, String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(BruBlock, &str)> = None;
+    for (number, line) in content.lines().enumerate() {
+        match current.take() {
+            Some((mut block, end)) => {
+                if line.trim_end() == end {
+                    blocks.push(block);
+                } else {
+                    let line = line.strip_prefix("  ").unwrap_or(line.trim_start());
+                    block.lines.push(line.to_string());
+                    current = Some((block, end));
+                }
+            }
+            None if line.trim().is_empty() => {}
+            None => {
+                let line = line.trim_end();
+                let (name, end) = if let Some(name) = line.strip_suffix(" {") {
+                    (name, "}")
+                } else if let Some(name) = line.strip_suffix(" [") {
+                    (name, "]")
+                } else {
+                    return Err(format!(
+                        "Line {}: expected a block, found '{}'",
+                        number + 1,
+                        line
+                    ));
+                };
+                let block = BruBlock {
+                    name: name.trim().to_string(),
+                    lines: vec![],
+                };
+                current = Some((block, end));
+            }
+        }
+    }
+    match current {
+        Some((block, _)) => Err(format!("Block '{}' is not closed", block.name)),
+        None => Ok(blocks),
+    }
+}
+
+fn block<'a>(blocks: &'a [BruBlock], name: &str) -> Option<&'a BruBlock> {
+    blocks.iter().find(|b| b.name == name)
+}
+
+/// Auth named by `mode`, read from its `auth:<mode>` block. `None` means no auth;
+/// `inherit` is resolved by the caller.
+fn imported_auth(
+    blocks: &[BruBlock],
+    mode: &str,
+    owner: &str,
+    unsupported: &mut Vec<String>,
+) -> Option<ImportedAuth> {
+    let settings = block(blocks, &format!("auth:{}", mode));
+    let get = |key: &str| settings.map(|b| b.get(key)).unwrap_or_default();
+    let method = match mode {
+        "" | "none" | "inherit" => return None,
+        "basic" => AuthMethod::Basic(BasicCredentials {
+            basic_user: get("username"),
+            basic_pass: get("password"),
+        }),
+        "digest" => AuthMethod::Digest(BasicCredentials {
+            basic_user: get("username"),
+            basic_pass: get("password"),
+        }),
+        "bearer" => AuthMethod::Bearer {
+            bearer_token: get("token"),
+        },
+        "apikey" => AuthMethod::ApiKey(ApiKeyAuth {
+            api_key_name: get("key"),
+            api_key_value: get("value"),
+            api_key_location: match get("placement").as_str() {
+                "queryparams" => ApiKeyLocation::Query,
+                _ => ApiKeyLocation::Header,
+            },
+        }),
+        "oauth2" => {
+            return Some(ImportedAuth::OAuth2(OAuth2Grant {
+                grant_type: get("grant_type"),
+                access_token_url: get("access_token_url"),
+                client_id: get("client_id"),
+                client_secret: get("client_secret"),
+                scope: get("scope"),
+                username: get("username"),
+                password: get("password"),
+                credentials_in_header: get("credentials_placement") == "basic_auth_header",
+                access_token: String::new(),
+            }))
+        }
+        other => {
+            unsupported.push(format!("{}: {} auth", owner, other));
+            return None;
+        }
+    };
+    Some(ImportedAuth::Method(method))
+}
+
+fn report_scripts(blocks: &[BruBlock], owner: &str, unsupported: &mut Vec<String>) {
+    for block in blocks {
+        let what = match block.name.as_str() {
+            "script:pre-request" => "pre-request script",
+            "script:post-response" => "post-response script",
+            "tests" => "tests",
+            "assert" => "assertions",
+            "vars:post-response" => "post-response variables",
+            _ => continue,
+        };
+        if !block.text().trim().is_empty() {
+            unsupported.push(format!("{}: {}", owner, what));
+        }
+    }
+}
+
+/// Settings a folder passes on to the requests inside it
+#[derive(Clone, Default)]
+struct Inherited {
+    name: String,
+    headers: Vec<NameValue>,
+    /// `None` when the auth of the collection applies
+    auth: Option<Option<ImportedAuth>>,
+}
+
+struct Importer<'a> {
+    fs: &'a dyn FileSystem,
+    service_id: String,
+    service_auth: Option<ImportedAuth>,
+    endpoints: Vec<Endpoint>,
+    path_variables: PathVariables,
+    unsupported: Vec<String>,
+}
+
+/// Builds a service from the Bruno collection in `root`. Collection variables go into every
+/// environment of `environments/`; the collection auth becomes the service auth.
+pub fn import_collection(
+    fs: &dyn FileSystem,
+    root: &Path,
+    service_id: &str,
+    directory: &str,
+) -> Result<ImportResult, String> {
+    let manifest = root.join("bruno.json");
+    if !fs.exists(&manifest) {
+        return Err(format!(
+            "{} is not a Bruno collection (no bruno.json)",
+            root.display()
+        ));
+    }
+    let manifest: serde_json::Value = serde_json::from_str(&fs.read_to_string(&manifest)?)
+        .map_err(|e| format!("Invalid bruno.json: {}", e))?;
+    let name = manifest["name"]
+        .as_str()
+        .map(str::to_string)
+        .unwrap_or_else(|| {
+            root.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+    let mut unsupported = Vec::new();
+    let mut service = new_service(service_id, &name, directory);
+
+    let collection_file = root.join("collection.bru");
+    let collection = if fs.exists(&collection_file) {
+        parse_file(fs, &collection_file)?
+    } else {
+        vec![]
+    };
+    report_scripts(&collection, "Collection", &mut unsupported);
+    let collection_vars = block(&collection, "vars:pre-request")
+        .map(BruBlock::pairs)
+        .unwrap_or_default();
+    let auth_mode = block(&collection, "auth")
+        .map(|b| b.get("mode"))
+        .unwrap_or_default();
+    let service_auth = imported_auth(&collection, &auth_mode, "Collection", &mut unsupported);
+    if let Some(auth) = &service_auth {
+        unsupported.extend(apply_service_auth(&mut service, auth, "Collection"));
+    }
+
+    let environments_dir = root.join("environments");
+    if fs.exists(&environments_dir) {
+        let mut files = fs.read_dir(&environments_dir)?;
+        files.sort();
+        for file in files.iter().filter(|f| is_bru(f)) {
+            let env_name = file_stem(file);
+            let blocks = parse_file(fs, file)?;
+            let mut variables = block(&blocks, "vars")
+                .map(BruBlock::pairs)
+                .unwrap_or_default();
+            // Bruno never writes secret values to disk
+            for secret in block(&blocks, "vars:secret")
+                .map(BruBlock::items)
+                .unwrap_or_default()
+            {
+                let (enabled, secret) = match secret.strip_prefix('~') {
+                    Some(rest) => (false, rest.to_string()),
+                    None => (true, secret),
+                };
+                unsupported.push(format!(
+                    "Environment {}: value of secret '{}'",
+                    env_name, secret
+                ));
+                variables.push(name_value(&secret, "", enabled));
+            }
+            let variables = merge_variables(&collection_vars, variables);
+            service.environments.push(environment(&env_name, variables));
+        }
+    }
+    if service.environments.is_empty() {
+        service
+            .environments
+            .push(environment("Default", collection_vars));
+    }
+    service.selected_environment = service.environments.first().map(|e| e.name.clone());
+
+    let mut importer = Importer {
+        fs,
+        service_id: service_id.to_string(),
+        service_auth,
+        endpoints: vec![],
+        path_variables: PathVariables::default(),
+        unsupported,
+    };
+    let inherited = Inherited {
+        headers: block(&collection, "headers")
+            .map(BruBlock::pairs)
+            .unwrap_or_default(),
+        ..Default::default()
+    };
+    importer.add_folder(root, &inherited, true)?;
+    importer.path_variables.add_to(&mut service.environments);
+    service.endpoints = importer.endpoints;
+    Ok(ImportResult::new(service, importer.unsupported))
+}
+
+fn parse_file(fs: &dyn FileSystem, path: &Path) -> Result<Vec<BruBlock>, String> {
+    parse_bru(&fs.read_to_string(path)?).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn is_bru(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "bru")
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+impl Importer<'_> {
+    /// Folders come first, by name, then the requests in their `seq` order
+    fn add_folder(
+        &mut self,
+        dir: &Path,
+        inherited: &Inherited,
+        is_root: bool,
+    ) -> Result<(), String> {
+        let mut entries = self.fs.read_dir(dir)?;
+        entries.sort();
+        let mut requests = Vec::new();
+        for entry in entries {
+            let file_name = entry
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if is_bru(&entry) {
+                if file_name != "folder.bru" && !(is_root && file_name == "collection.bru") {
+                    let blocks = parse_file(self.fs, &entry)?;
+                    let seq = block(&blocks, "meta")
+                        .and_then(|m| m.get("seq").parse::<f64>().ok())
+                        .unwrap_or(f64::MAX);
+                    requests.push((seq, file_stem(&entry), blocks));
+                }
+            } else if file_name.starts_with('.')
+                || file_name == "node_modules"
+                || (is_root && file_name == "environments")
+            {
+                continue;
+            } else if self.fs.read_dir(&entry).is_ok() {
+                self.add_subfolder(&entry, &file_name, inherited)?;
+            }
+        }
+        requests.sort_by(|a, b| a.0.total_cmp(&b.0));
+        for (_, stem, blocks) in requests {
+            self.add_request(&blocks, &stem, inherited);
+        }
+        Ok(())
+    }
+
+    fn add_subfolder(
+        &mut self,
+        dir: &Path,
+        dir_name: &str,
+        parent: &Inherited,
+    ) -> Result<(), String> {
+        let folder_file = dir.join("folder.bru");
+        let blocks = if self.fs.exists(&folder_file) {
+            parse_file(self.fs, &folder_file)?
+        } else {
+            vec![]
+        };
+        let folder_name = block(&blocks, "meta")
+            .map(|m| m.get("name"))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| dir_name.to_string());
+        let name = qualify(&parent.name, &folder_name);
+        report_scripts(&blocks, &name, &mut self.unsupported);
+        let mut inherited = Inherited {
+            name: name.clone(),
+            headers: parent.headers.clone(),
+            auth: parent.auth.clone(),
+        };
+        if let Some(headers) = block(&blocks, "headers") {
+            inherited.headers.extend(headers.pairs());
+        }
+        let mode = block(&blocks, "auth")
+            .map(|b| b.get("mode"))
+            .unwrap_or_default();
+        if !mode.is_empty() && mode != "inherit" {
+            inherited.auth = Some(imported_auth(&blocks, &mode, &name, &mut self.unsupported));
+        }
+        self.add_folder(dir, &inherited, false)
+    }
+
+    fn add_request(&mut self, blocks: &[BruBlock], stem: &str, inherited: &Inherited) {
+        let meta = block(blocks, "meta");
+        let request_name = meta
+            .map(|m| m.get("name"))
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| stem.to_string());
+        let name = qualify(&inherited.name, &request_name);
+        let kind = meta.map(|m| m.get("type")).unwrap_or_default();
+        if !matches!(kind.as_str(), "" | "http" | "graphql") {
+            self.unsupported.push(format!("{}: {} request", name, kind));
+            return;
+        }
+        let Some(method_block) = blocks.iter().find(|b| METHODS.contains(&b.name.as_str())) else {
+            self.unsupported.push(format!("{}: no HTTP method", name));
+            return;
+        };
+        let (url, _) = split_query(&method_block.get("url"));
+        let path_values = block(blocks, "params:path")
+            .map(BruBlock::pairs)
+            .unwrap_or_default();
+        let url = self
+            .path_variables
+            .rewrite(&name, &url, &path_values, &mut self.unsupported);
+        let mut endpoint = new_endpoint(&self.service_id, &name, &method_block.name, &url);
+        endpoint.params = block(blocks, "params:query")
+            .map(BruBlock::pairs)
+            .unwrap_or_else(|| split_query(&method_block.get("url")).1);
+        endpoint.headers = inherited.headers.clone();
+        if let Some(headers) = block(blocks, "headers") {
+            endpoint.headers.extend(headers.pairs());
+        }
+        self.apply_body(&mut endpoint, blocks, &method_block.get("body"), &name);
+
+        let mode = method_block.get("auth");
+        let auth = if mode.is_empty() || mode == "inherit" {
+            inherited
+                .auth
+                .clone()
+                .unwrap_or_else(|| self.service_auth.clone())
+        } else {
+            imported_auth(blocks, &mode, &name, &mut self.unsupported)
+        };
+        match auth {
+            Some(auth) if Some(&auth) == self.service_auth.as_ref() => {
+                endpoint.authenticated = true;
+                endpoint.auth_type = auth.auth_type();
+            }
+            Some(auth) => self
+                .unsupported
+                .extend(apply_endpoint_auth(&mut endpoint, &auth, &name)),
+            None => {}
+        }
+        report_scripts(blocks, &name, &mut self.unsupported);
+        if block(blocks, "vars:pre-request").is_some_and(|b| !b.pairs().is_empty()) {
+            self.unsupported
+                .push(format!("{}: request variables", name));
+        }
+        self.endpoints.push(endpoint);
+    }
+
+    fn apply_body(&mut self, endpoint: &mut Endpoint, blocks: &[BruBlock], mode: &str, name: &str) {
+        let (block_name, content_type) = match mode {
+            "" | "none" => return,
+            "json" => ("body:json", "application/json"),
+            "text" => ("body:text", "text/plain"),
+            "xml" => ("body:xml", "application/xml"),
+            "sparql" => ("body:sparql", "application/sparql-query"),
+            "formUrlEncoded" => ("body:form-urlencoded", "application/x-www-form-urlencoded"),
+            "multipartForm" => ("body:multipart-form", "multipart/form-data"),
+            "graphql" => ("body:graphql", "application/json"),
+            other => {
+                self.unsupported.push(format!("{}: {} body", name, other));
+                return;
+            }
+        };
+        let Some(body) = block(blocks, block_name) else {
+            return;
+        };
+        match mode {
+            "formUrlEncoded" => endpoint.body = form_body(&body.pairs()),
+            "multipartForm" => {
+                let mut fields = Vec::new();
+                for field in body.pairs() {
+                    if field.value.starts_with("@file(") {
+                        self.unsupported
+                            .push(format!("{}: form-data file field '{}'", name, field.name));
+                    } else {
+                        fields.push(field);
+                    }
+                }
+                endpoint.body = multipart_body(&fields);
+                set_content_type(
+                    &mut endpoint.headers,
+                    &format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY),
+                );
+                return;
+            }
+            "graphql" => {
+                let variables = block(blocks, "body:graphql:vars")
+                    .map(BruBlock::text)
+                    .and_then(|v| serde_json::from_str::<serde_json::Value>(&v).ok())
+                    .unwrap_or_else(|| serde_json::json!({}));
+                endpoint.body =
+                    serde_json::json!({ "query": body.text(), "variables": variables }).to_string();
+            }
+            _ => endpoint.body = body.text(),
+        }
+        set_content_type(&mut endpoint.headers, content_type);
+    }
+}
+
+fn qualify(folder: &str, name: &str) -> String {
+    if folder.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} / {}", folder, name)
+    }
+}
@@ -0,0 +1,395 @@
+//! Insomnia v4 export import, from JSON or YAML
+
+use super::{
+    apply_endpoint_auth, apply_service_auth, environment, form_body, merge_variables,
+    multipart_body, name_value, new_endpoint, new_service, set_content_type, ImportResult,
+    ImportedAuth, OAuth2Grant, MULTIPART_BOUNDARY,
+};
+use crate::domains::auth::{ApiKeyAuth, ApiKeyLocation, AuthMethod, BasicCredentials};
+use crate::types::{Endpoint, NameValue};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize)]
+struct Export {
+    #[serde(default, rename = "__export_format")]
+    export_format: u32,
+    #[serde(default)]
+    resources: Vec<Resource>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Resource {
+    #[serde(rename = "_id")]
+    id: String,
+    #[serde(rename = "_type")]
+    kind: String,
+    #[serde(default)]
+    parent_id: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    method: String,
+    #[serde(default)]
+    url: String,
+    #[serde(default)]
+    headers: Vec<Pair>,
+    #[serde(default)]
+    parameters: Vec<Pair>,
+    #[serde(default)]
+    body: Value,
+    #[serde(default)]
+    authentication: Value,
+    /// Variables of an environment
+    #[serde(default)]
+    data: Value,
+    /// Variables of a folder
+    #[serde(default)]
+    environment: Value,
+    #[serde(default)]
+    pre_request_script: String,
+    #[serde(default)]
+    after_response_script: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pair {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+}
+
+impl Pair {
+    fn to_name_value(&self) -> NameValue {
+        name_value(
+            &template(&self.name),
+            &template(&text(&self.value)),
+            !self.disabled,
+        )
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Insomnia reads variables as `{{ _.name }}`; services use `{{name}}`
+fn template(value: &str) -> String {
+    let re = regex::Regex::new(r"\{\{\s*(?:_\.)?([\w.-]+)\s*\}\}").expect("Invalid regex");
+    re.replace_all(value, "{{$1}}").into_owned()
+}
+
+/// Environment data may nest objects, which Insomnia reads as `_.parent.child`
+fn flatten(prefix: &str, data: &Value, out: &mut Vec<NameValue>) {
+    match data {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let name = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&name, value, out);
+            }
+        }
+        value if !prefix.is_empty() => out.push(name_value(prefix, &text(value), true)),
+        _ => {}
+    }
+}
+
+fn variables(data: &Value) -> Vec<NameValue> {
+    let mut out = Vec::new();
+    flatten("", data, &mut out);
+    out
+}
+
+/// Builds a service from an Insomnia v4 export. The variables of the base environment go into
+/// every sub environment. The auth most requests use becomes the service auth; requests
+/// with other auth carry it in their own headers or query.
+pub fn import_export(
+    content: &str,
+    service_id: &str,
+    directory: &str,
+) -> Result<ImportResult, String> {
+    let export: Export = if content.trim_start().starts_with('{') {
+        serde_json::from_str(content).map_err(|e| format!("Invalid Insomnia export: {}", e))?
+    } else {
+        serde_yaml::from_str(content).map_err(|e| format!("Invalid Insomnia export: {}", e))?
+    };
+    if export.export_format != 4 {
+        return Err(format!(
+            "Unsupported Insomnia export format {}, export the collection as v4 (JSON or YAML)",
+            export.export_format
+        ));
+    }
+    let resources = &export.resources;
+    let by_id: HashMap<&str, &Resource> = resources.iter().map(|r| (r.id.as_str(), r)).collect();
+    let workspace = resources
+        .iter()
+        .find(|r| r.kind == "workspace")
+        .ok_or("Insomnia export has no workspace")?;
+    let mut unsupported = Vec::new();
+    let mut service = new_service(service_id, &workspace.name, directory);
+
+    // The base environment belongs to the workspace, sub environments to the base
+    let environments: Vec<&Resource> = resources
+        .iter()
+        .filter(|r| r.kind == "environment")
+        .collect();
+    let base = environments
+        .iter()
+        .find(|e| e.parent_id.as_deref() == Some(workspace.id.as_str()));
+    let base_vars = base.map(|b| variables(&b.data)).unwrap_or_default();
+    for env in &environments {
+        if base.is_some_and(|b| env.parent_id.as_deref() == Some(b.id.as_str())) {
+            let variables = merge_variables(&base_vars, variables(&env.data));
+            service.environments.push(environment(&env.name, variables));
+        }
+    }
+    if service.environments.is_empty() {
+        let name = base.map(|b| b.name.as_str()).unwrap_or("Default");
+        service.environments.push(environment(name, base_vars));
+    }
+    service.selected_environment = service.environments.first().map(|e| e.name.clone());
+
+    let requests: Vec<&Resource> = resources.iter().filter(|r| r.kind == "request").collect();
+    let auths: Vec<Option<ImportedAuth>> = requests
+        .iter()
+        .map(|r| imported_auth(effective_auth(r, &by_id), &mut Vec::new()))
+        .collect();
+    let service_auth = most_common(&auths);
+    if let Some(auth) = &service_auth {
+        unsupported.extend(apply_service_auth(&mut service, auth, "Workspace"));
+    }
+
+    for resource in resources {
+        let name = qualified_name(resource, &by_id);
+        match resource.kind.as_str() {
+            "request_group" if !variables(&resource.environment).is_empty() => {
+                unsupported.push(format!("{}: folder environment", name))
+            }
+            "grpc_request" | "websocket_request" | "unit_test_suite" => {
+                unsupported.push(format!("{}: {}", name, resource.kind.replace('_', " ")))
+            }
+            _ => {}
+        }
+    }
+
+    for request in requests {
+        let name = qualified_name(request, &by_id);
+        let endpoint = endpoint(
+            request,
+            effective_auth(request, &by_id),
+            &name,
+            service_id,
+            service_auth.as_ref(),
+            &mut unsupported,
+        );
+        service.endpoints.push(endpoint);
+    }
+    Ok(ImportResult::new(service, unsupported))
+}
+
+/// Folder names prefix the request name, as services have no folders
+fn qualified_name(resource: &Resource, by_id: &HashMap<&str, &Resource>) -> String {
+    let mut name = resource.name.clone();
+    let mut parent = resource.parent_id.as_deref().and_then(|id| by_id.get(id));
+    while let Some(folder) = parent.filter(|p| p.kind == "request_group") {
+        name = format!("{} / {}", folder.name, name);
+        parent = folder.parent_id.as_deref().and_then(|id| by_id.get(id));
+    }
+    name
+}
+
+/// A request without auth of its own inherits that of the closest folder setting one
+fn effective_auth<'a>(resource: &'a Resource, by_id: &HashMap<&str, &'a Resource>) -> &'a Value {
+    let mut current = resource;
+    loop {
+        if current.authentication.get("type").is_some() {
+            return &current.authentication;
+        }
+        match current.parent_id.as_deref().and_then(|id| by_id.get(id)) {
+            Some(parent) if parent.kind == "request_group" => current = parent,
+            _ => return &resource.authentication,
+        }
+    }
+}
+
+fn most_common(auths: &[Option<ImportedAuth>]) -> Option<ImportedAuth> {
+    let mut counts: Vec<(&ImportedAuth, usize)> = Vec::new();
+    for auth in auths.iter().flatten() {
+        match counts.iter_mut().find(|(a, _)| *a == auth) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((auth, 1)),
+        }
+    }
+    // The first of equally common auths wins
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(auth, _)| (*auth).clone())
+}
+
+fn imported_auth(auth: &Value, unsupported: &mut Vec<String>) -> Option<ImportedAuth> {
+    if auth["disabled"].as_bool() == Some(true) {
+        return None;
+    }
+    let get = |key: &str| template(auth[key].as_str().unwrap_or_default());
+    let method = match auth["type"].as_str().unwrap_or_default() {
+        "" | "none" => return None,
+        "basic" => AuthMethod::Basic(BasicCredentials {
+            basic_user: get("username"),
+            basic_pass: get("password"),
+        }),
+        "digest" => AuthMethod::Digest(BasicCredentials {
+            basic_user: get("username"),
+            basic_pass: get("password"),
+        }),
+        "bearer" => {
+            let prefix = get("prefix");
+            if !prefix.is_empty() && !prefix.eq_ignore_ascii_case("bearer") {
+                unsupported.push(format!("bearer prefix '{}'", prefix));
+            }
+            AuthMethod::Bearer {
+                bearer_token: get("token"),
+            }
+        }
+        "apikey" => AuthMethod::ApiKey(ApiKeyAuth {
+            api_key_name: get("key"),
+            api_key_value: get("value"),
+            api_key_location: match get("addTo").as_str() {
+                "queryParams" => ApiKeyLocation::Query,
+                "cookie" => ApiKeyLocation::Cookie,
+                _ => ApiKeyLocation::Header,
+            },
+        }),
+        "oauth2" => {
+            return Some(ImportedAuth::OAuth2(OAuth2Grant {
+                grant_type: get("grantType"),
+                access_token_url: get("accessTokenUrl"),
+                client_id: get("clientId"),
+                client_secret: get("clientSecret"),
+                scope: get("scope"),
+                username: get("username"),
+                password: get("password"),
+                // Insomnia sends the client credentials as Basic auth unless told otherwise
+                credentials_in_header: auth["credentialsInBody"].as_bool() != Some(true),
+                access_token: get("accessToken"),
+            }));
+        }
+        other => {
+            unsupported.push(format!("{} auth", other));
+            return None;
+        }
+    };
+    Some(ImportedAuth::Method(method))
+}
+
+fn endpoint(
+    request: &Resource,
+    authentication: &Value,
+    name: &str,
+    service_id: &str,
+    service_auth: Option<&ImportedAuth>,
+    unsupported: &mut Vec<String>,
+) -> Endpoint {
+    let method = if request.method.is_empty() {
+        "GET"
+    } else {
+        &request.method
+    };
+    let mut endpoint = new_endpoint(service_id, name, method, &template(&request.url));
+    endpoint.params = request.parameters.iter().map(Pair::to_name_value).collect();
+    endpoint.headers = request.headers.iter().map(Pair::to_name_value).collect();
+
+    let body = &request.body;
+    let mime_type = body["mimeType"].as_str().unwrap_or_default();
+    let fields: Vec<Pair> = serde_json::from_value(body["params"].clone()).unwrap_or_default();
+    match mime_type {
+        "" => {}
+        "application/x-www-form-urlencoded" => {
+            let fields: Vec<NameValue> = fields.iter().map(Pair::to_name_value).collect();
+            endpoint.body = form_body(&fields);
+            set_content_type(&mut endpoint.headers, mime_type);
+        }
+        "multipart/form-data" => {
+            let mut text_fields = Vec::new();
+            for field in &fields {
+                if field.kind.as_deref() == Some("file") {
+                    unsupported.push(format!("{}: form-data file field '{}'", name, field.name));
+                } else {
+                    text_fields.push(field.to_name_value());
+                }
+            }
+            endpoint.body = multipart_body(&text_fields);
+            set_content_type(
+                &mut endpoint.headers,
+                &format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY),
+            );
+        }
+        // The text of a GraphQL body already is the JSON payload
+        "application/graphql" => {
+            endpoint.body = template(body["text"].as_str().unwrap_or_default());
+            set_content_type(&mut endpoint.headers, "application/json");
+        }
+        _ if body.get("fileName").is_some() => {
+            unsupported.push(format!("{}: file body", name));
+        }
+        _ => {
+            endpoint.body = template(body["text"].as_str().unwrap_or_default());
+            set_content_type(&mut endpoint.headers, mime_type);
+        }
+    }
+
+    let mut auth_notes = Vec::new();
+    match imported_auth(authentication, &mut auth_notes) {
+        Some(auth) if Some(&auth) == service_auth => {
+            endpoint.authenticated = true;
+            endpoint.auth_type = auth.auth_type();
+        }
+        Some(auth) => unsupported.extend(apply_endpoint_auth(&mut endpoint, &auth, name)),
+        None => {}
+    }
+    unsupported.extend(
+        auth_notes
+            .into_iter()
+            .map(|note| format!("{}: {}", name, note)),
+    );
+
+    for (script, label) in [
+        (&request.pre_request_script, "pre-request script"),
+        (&request.after_response_script, "after-response script"),
+    ] {
+        if !script.trim().is_empty() {
+            unsupported.push(format!("{}: {}", name, label));
+        }
+    }
+    // Template tags such as `{% response ... %}` chain requests, which endpoints can't
+    let uses_tags = std::iter::once(&endpoint.url)
+        .chain(std::iter::once(&endpoint.body))
+        .chain(
+            endpoint
+                .headers
+                .iter()
+                .chain(&endpoint.params)
+                .map(|p| &p.value),
+        )
+        .any(|value| value.contains("{%"));
+    if uses_tags {
+        unsupported.push(format!("{}: template tags", name));
+    }
+    endpoint
+}
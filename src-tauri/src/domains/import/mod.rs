@@ -0,0 +1,470 @@
+pub mod bruno;
+pub mod insomnia;
+pub mod postman;
+
+use crate::domains::auth::ApiKeyLocation;
+use crate::domains::service::endpoint::default_preflight_config;
+use crate::types::{
+    AuthConfig, AuthMethod, AuthType, Endpoint, EndpointMetadata, EnvironmentConfig, Header,
+    NameValue, PreflightConfig, Scripts, Service,
+};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What an import created and what it had to leave out
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub endpoints: usize,
+    pub environments: usize,
+    /// One line per setting that has no equivalent, e.g. scripts
+    pub unsupported: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub service: Service,
+    pub summary: ImportSummary,
+}
+
+impl ImportResult {
+    pub fn new(service: Service, unsupported: Vec<String>) -> Self {
+        let summary = ImportSummary {
+            endpoints: service.endpoints.len(),
+            environments: service.environments.len(),
+            unsupported,
+        };
+        Self { service, summary }
+    }
+}
+
+pub fn new_service_id() -> String {
+    format!(
+        "s-{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    )
+}
+
+/// An unauthenticated service without endpoints or environments
+pub fn new_service(id: &str, name: &str, directory: &str) -> Service {
+    Service {
+        id: id.to_string(),
+        name: name.to_string(),
+        environments: vec![],
+        is_authenticated: false,
+        auth_type: Some(AuthType::None),
+        auth: AuthConfig::default(),
+        preflight: default_preflight_config(),
+        endpoints: vec![],
+        directory: directory.to_string(),
+        selected_environment: None,
+        git_url: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+    }
+}
+
+pub fn new_endpoint(service_id: &str, name: &str, method: &str, url: &str) -> Endpoint {
+    Endpoint {
+        id: format!("e-{}", uuid::Uuid::new_v4()),
+        service_id: service_id.to_string(),
+        name: name.to_string(),
+        method: method.to_uppercase(),
+        url: url.to_string(),
+        authenticated: false,
+        auth_type: AuthType::None,
+        metadata: EndpointMetadata {
+            version: "1.0".to_string(),
+            last_updated: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        },
+        params: vec![],
+        headers: vec![],
+        body: String::new(),
+        preflight: default_preflight_config(),
+        last_version: 0,
+        versions: vec![],
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+        assertions: vec![],
+        examples: vec![],
+    }
+}
+
+pub fn name_value(name: &str, value: &str, enabled: bool) -> NameValue {
+    NameValue {
+        name: name.to_string(),
+        value: value.to_string(),
+        enabled,
+        secret_key: None,
+    }
+}
+
+pub fn environment(name: &str, variables: Vec<NameValue>) -> EnvironmentConfig {
+    EnvironmentConfig {
+        name: name.to_string(),
+        is_unsafe: false,
+        variables,
+        auth: None,
+        preflight: None,
+    }
+}
+
+/// `BASE_URL` of the selected environment, else of the first environment that sets it
+pub fn service_base_url(service: &Service) -> Option<String> {
+    let base_url = |env: &EnvironmentConfig| {
+        env.variables
+            .iter()
+            .find(|v| v.name == "BASE_URL" && v.enabled && !v.value.is_empty())
+            .map(|v| v.value.clone())
+    };
+    service
+        .environments
+        .iter()
+        .filter(|env| service.selected_environment.as_deref() == Some(env.name.as_str()))
+        .chain(service.environments.iter())
+        .find_map(base_url)
+}
+
+/// Sets `BASE_URL` in every environment, adding a `Default` one to a service without any
+pub fn set_base_url(service: &mut Service, base_url: &str) {
+    if service.environments.is_empty() {
+        service.environments.push(environment("Default", vec![]));
+        service.selected_environment = Some("Default".to_string());
+    }
+    for env in &mut service.environments {
+        match env.variables.iter_mut().find(|v| v.name == "BASE_URL") {
+            Some(variable) => {
+                variable.value = base_url.to_string();
+                variable.enabled = true;
+            }
+            None => env.variables.push(name_value("BASE_URL", base_url, true)),
+        }
+    }
+}
+
+/// `base` with each of `overrides` replacing the variable of the same name or appended
+pub fn merge_variables(base: &[NameValue], overrides: Vec<NameValue>) -> Vec<NameValue> {
+    let mut variables = base.to_vec();
+    for variable in overrides {
+        match variables.iter_mut().find(|v| v.name == variable.name) {
+            Some(existing) => *existing = variable,
+            None => variables.push(variable),
+        }
+    }
+    variables
+}
+
+/// Values of the `:name` path segments of all requests; the first value given for a name wins
+#[derive(Debug, Default)]
+pub struct PathVariables {
+    variables: Vec<NameValue>,
+}
+
+impl PathVariables {
+    /// Turns `:name` path segments into `{{name}}` placeholders and keeps their values. A
+    /// value differing from the one kept is reported against `owner`.
+    pub fn rewrite(
+        &mut self,
+        owner: &str,
+        url: &str,
+        values: &[NameValue],
+        unsupported: &mut Vec<String>,
+    ) -> String {
+        let (url, names) = path_placeholders(url);
+        for variable in names {
+            let value = values
+                .iter()
+                .find(|v| v.name == variable)
+                .map(|v| v.value.clone())
+                .unwrap_or_default();
+            match self.variables.iter_mut().find(|v| v.name == variable) {
+                None => self.variables.push(name_value(&variable, &value, true)),
+                Some(existing) if existing.value.is_empty() => existing.value = value,
+                Some(existing) if !value.is_empty() && existing.value != value => {
+                    unsupported.push(format!(
+                        "{}: path variable '{}' value '{}', the environments keep '{}'",
+                        owner, variable, value, existing.value
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        url
+    }
+
+    /// Variables the environments already define win over the values kept from requests
+    pub fn add_to(&self, environments: &mut [EnvironmentConfig]) {
+        for env in environments {
+            for variable in &self.variables {
+                if !env.variables.iter().any(|v| v.name == variable.name) {
+                    env.variables.push(variable.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Rewrites `:name` path segments as `{{name}}`, returning the URL and the names found
+fn path_placeholders(url: &str) -> (String, Vec<String>) {
+    let mut names = Vec::new();
+    let segments: Vec<String> = url
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(variable)
+                if !variable.is_empty()
+                    && variable
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+            {
+                names.push(variable.to_string());
+                format!("{{{{{}}}}}", variable)
+            }
+            _ => segment.to_string(),
+        })
+        .collect();
+    (segments.join("/"), names)
+}
+
+/// Adds a `Content-Type` header unless the request already sets one
+pub fn set_content_type(headers: &mut Vec<Header>, content_type: &str) {
+    if !headers
+        .iter()
+        .any(|h| h.name.eq_ignore_ascii_case("content-type"))
+    {
+        headers.push(name_value("Content-Type", content_type, true));
+    }
+}
+
+/// Splits `url` at `?` into the URL and its query parameters, keeping `{{VAR}}` placeholders
+pub fn split_query(url: &str) -> (String, Vec<NameValue>) {
+    match url.split_once('?') {
+        Some((base, query)) => {
+            let params = query
+                .split('&')
+                .filter(|pair| !pair.is_empty())
+                .map(|pair| {
+                    let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                    name_value(name, value, true)
+                })
+                .collect();
+            (base.to_string(), params)
+        }
+        None => (url.to_string(), vec![]),
+    }
+}
+
+/// `application/x-www-form-urlencoded` body of the enabled fields. Values holding
+/// `{{VAR}}` placeholders are left unencoded so they still resolve.
+pub fn form_body(fields: &[NameValue]) -> String {
+    let encode = |s: &str| {
+        if s.contains("{{") {
+            s.to_string()
+        } else {
+            url::form_urlencoded::byte_serialize(s.as_bytes()).collect()
+        }
+    };
+    fields
+        .iter()
+        .filter(|f| f.enabled)
+        .map(|f| format!("{}={}", encode(&f.name), encode(&f.value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub const MULTIPART_BOUNDARY: &str = "----xrest-form-boundary";
+
+/// `multipart/form-data` body of the enabled text fields, delimited by `MULTIPART_BOUNDARY`
+pub fn multipart_body(fields: &[NameValue]) -> String {
+    let mut body = String::new();
+    for field in fields.iter().filter(|f| f.enabled) {
+        body.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            MULTIPART_BOUNDARY, field.name, field.value
+        ));
+    }
+    body.push_str(&format!("--{}--\r\n", MULTIPART_BOUNDARY));
+    body
+}
+
+/// OAuth2 settings of an imported collection or request
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OAuth2Grant {
+    /// As sent to the token endpoint, e.g. `client_credentials` or `password`
+    pub grant_type: String,
+    pub access_token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: String,
+    pub username: String,
+    pub password: String,
+    /// Send the client credentials as basic auth instead of in the body
+    pub credentials_in_header: bool,
+    /// Token the source tool already obtained, if any
+    pub access_token: String,
+}
+
+impl OAuth2Grant {
+    /// Client credentials and password grants become a preflight token request
+    pub fn preflight(&self) -> Option<PreflightConfig> {
+        if !matches!(self.grant_type.as_str(), "client_credentials" | "password")
+            || self.access_token_url.is_empty()
+        {
+            return None;
+        }
+        let mut body_params = vec![name_value("grant_type", &self.grant_type, true)];
+        let mut headers = vec![];
+        if self.credentials_in_header {
+            let credentials = format!("{}:{}", self.client_id, self.client_secret);
+            headers.push(name_value("Authorization", &basic_auth(&credentials), true));
+        } else {
+            body_params.push(name_value("client_id", &self.client_id, true));
+            body_params.push(name_value("client_secret", &self.client_secret, true));
+        }
+        if self.grant_type == "password" {
+            body_params.push(name_value("username", &self.username, true));
+            body_params.push(name_value("password", &self.password, true));
+        }
+        if !self.scope.is_empty() {
+            body_params.push(name_value("scope", &self.scope, true));
+        }
+        Some(PreflightConfig {
+            enabled: true,
+            method: "POST".to_string(),
+            url: self.access_token_url.clone(),
+            body_type: "application/x-www-form-urlencoded".to_string(),
+            body_params,
+            headers,
+            cache_token: true,
+            cache_duration: "derived".to_string(),
+            cache_duration_key: "expires_in".to_string(),
+            token_key: "access_token".to_string(),
+            token_header: Some("Authorization".to_string()),
+            ..default_preflight_config()
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportedAuth {
+    Method(AuthMethod),
+    OAuth2(OAuth2Grant),
+}
+
+impl ImportedAuth {
+    pub fn auth_type(&self) -> AuthType {
+        match self {
+            ImportedAuth::Method(method) => AuthConfig {
+                active: true,
+                method: method.clone(),
+            }
+            .auth_type(),
+            // OAuth2 tokens are sent as bearer tokens
+            ImportedAuth::OAuth2(_) => AuthType::Bearer,
+        }
+    }
+}
+
+/// Makes `auth` the auth of the service. Returns what couldn't be mapped.
+pub fn apply_service_auth(
+    service: &mut Service,
+    auth: &ImportedAuth,
+    owner: &str,
+) -> Option<String> {
+    let mut unsupported = None;
+    let method = match auth {
+        ImportedAuth::Method(method) => method.clone(),
+        ImportedAuth::OAuth2(grant) => match grant.preflight() {
+            Some(preflight) => {
+                service.preflight = preflight;
+                AuthMethod::None
+            }
+            None => {
+                // Interactive grants can't run unattended; keep the token obtained so far
+                unsupported = Some(format!(
+                    "{}: oauth2 grant '{}' (only client credentials and password grants \
+                     run as preflight)",
+                    owner, grant.grant_type
+                ));
+                AuthMethod::Bearer {
+                    bearer_token: grant.access_token.clone(),
+                }
+            }
+        },
+    };
+    service.auth = AuthConfig {
+        active: true,
+        method,
+    };
+    service.is_authenticated = true;
+    service.auth_type = Some(auth.auth_type());
+    unsupported
+}
+
+/// Writes auth that differs from the service's into the endpoint's own headers or query.
+/// Returns what couldn't be mapped.
+pub fn apply_endpoint_auth(
+    endpoint: &mut Endpoint,
+    auth: &ImportedAuth,
+    owner: &str,
+) -> Option<String> {
+    let method = match auth {
+        ImportedAuth::Method(method) => method,
+        ImportedAuth::OAuth2(_) => {
+            return Some(format!(
+                "{}: oauth2 auth differing from the service auth",
+                owner
+            ))
+        }
+    };
+    match method {
+        AuthMethod::None => {}
+        AuthMethod::Basic(credentials) => {
+            let credentials = format!("{}:{}", credentials.basic_user, credentials.basic_pass);
+            if credentials.contains("{{") {
+                return Some(format!(
+                    "{}: basic auth with variables (only the service auth may use them)",
+                    owner
+                ));
+            }
+            endpoint
+                .headers
+                .push(name_value("Authorization", &basic_auth(&credentials), true));
+        }
+        AuthMethod::Bearer { bearer_token } => endpoint.headers.push(name_value(
+            "Authorization",
+            &format!("Bearer {}", bearer_token),
+            true,
+        )),
+        AuthMethod::ApiKey(key) => {
+            let value = name_value(&key.api_key_name, &key.api_key_value, true);
+            match key.api_key_location {
+                ApiKeyLocation::Query => endpoint.params.push(value),
+                _ => endpoint.headers.push(value),
+            }
+        }
+        other => {
+            let auth_type = AuthConfig {
+                active: true,
+                method: other.clone(),
+            }
+            .auth_type();
+            return Some(format!("{}: {} auth", owner, auth_type));
+        }
+    }
+    None
+}
+
+fn basic_auth(credentials: &str) -> String {
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(credentials)
+    )
+}
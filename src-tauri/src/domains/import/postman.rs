@@ -0,0 +1,505 @@
+//! Postman Collection v2.1 and environment import
+
+use super::{
+    apply_endpoint_auth, apply_service_auth, environment, form_body, merge_variables,
+    multipart_body, name_value, new_endpoint, new_service, set_content_type, split_query,
+    ImportResult, ImportedAuth, OAuth2Grant, PathVariables, MULTIPART_BOUNDARY,
+};
+use crate::domains::auth::{ApiKeyAuth, ApiKeyLocation, AuthMethod, BasicCredentials};
+use crate::types::{AuthType, Endpoint, ExampleResponse, NameValue};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+struct Collection {
+    info: Info,
+    #[serde(default)]
+    item: Vec<Item>,
+    #[serde(default)]
+    auth: Option<Auth>,
+    #[serde(default)]
+    variable: Vec<KeyValue>,
+    #[serde(default)]
+    event: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Info {
+    name: String,
+    #[serde(default)]
+    schema: String,
+}
+
+/// A folder when it has `item`, a request otherwise
+#[derive(Debug, Deserialize)]
+struct Item {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    item: Option<Vec<Item>>,
+    #[serde(default)]
+    request: Option<RequestDef>,
+    #[serde(default)]
+    response: Vec<SavedResponse>,
+    #[serde(default)]
+    auth: Option<Auth>,
+    #[serde(default)]
+    event: Vec<Event>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RequestDef {
+    Url(String),
+    Full(Box<Request>),
+}
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    #[serde(default = "default_method")]
+    method: String,
+    #[serde(default)]
+    header: Vec<KeyValue>,
+    #[serde(default)]
+    url: Option<UrlDef>,
+    #[serde(default)]
+    body: Option<Body>,
+    #[serde(default)]
+    auth: Option<Auth>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum UrlDef {
+    Raw(String),
+    Parts {
+        #[serde(default)]
+        raw: String,
+        #[serde(default)]
+        query: Option<Vec<KeyValue>>,
+        /// Values of the `:name` path segments
+        #[serde(default)]
+        variable: Vec<KeyValue>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyValue {
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default)]
+    disabled: bool,
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+}
+
+impl KeyValue {
+    fn to_name_value(&self) -> NameValue {
+        name_value(&self.key, &text(&self.value), !self.disabled)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Body {
+    #[serde(default)]
+    mode: String,
+    #[serde(default)]
+    raw: String,
+    #[serde(default)]
+    urlencoded: Vec<KeyValue>,
+    #[serde(default)]
+    formdata: Vec<KeyValue>,
+    #[serde(default)]
+    options: Value,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct Auth {
+    #[serde(rename = "type")]
+    kind: String,
+    /// Settings of each type, as `[{"key": ..., "value": ...}]` under the type name
+    #[serde(flatten)]
+    settings: serde_json::Map<String, Value>,
+}
+
+impl Auth {
+    fn get(&self, key: &str) -> String {
+        self.settings
+            .get(&self.kind)
+            .and_then(Value::as_array)
+            .and_then(|params| params.iter().find(|p| p["key"] == key))
+            .map(|p| text(&p["value"]))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Event {
+    #[serde(default)]
+    listen: String,
+    #[serde(default)]
+    script: Option<Script>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Script {
+    #[serde(default)]
+    exec: Value,
+}
+
+impl Event {
+    fn has_code(&self) -> bool {
+        match self.script.as_ref().map(|s| &s.exec) {
+            Some(Value::Array(lines)) => lines.iter().any(|l| !text(l).trim().is_empty()),
+            Some(exec) => !text(exec).trim().is_empty(),
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SavedResponse {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    code: Option<u16>,
+    #[serde(default)]
+    header: Option<Vec<KeyValue>>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostmanEnvironment {
+    name: String,
+    #[serde(default)]
+    values: Vec<EnvironmentValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EnvironmentValue {
+    key: String,
+    #[serde(default)]
+    value: Value,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Postman stores most values as strings but accepts numbers and booleans too
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Builds a service from a v2.1 collection and its environment exports. Collection variables
+/// go into every environment; environment values win over them. Without environments the
+/// collection variables make up a single `Default` environment.
+pub fn import_collection(
+    collection: &str,
+    environments: &[String],
+    service_id: &str,
+    directory: &str,
+) -> Result<ImportResult, String> {
+    let collection: Collection = serde_json::from_str(collection)
+        .map_err(|e| format!("Invalid Postman collection: {}", e))?;
+    if !collection.info.schema.is_empty() && !collection.info.schema.contains("v2.1") {
+        return Err(format!(
+            "Unsupported Postman collection schema '{}', export it as v2.1",
+            collection.info.schema
+        ));
+    }
+    let mut unsupported = Vec::new();
+    let mut service = new_service(service_id, &collection.info.name, directory);
+
+    let collection_vars: Vec<NameValue> = collection
+        .variable
+        .iter()
+        .map(KeyValue::to_name_value)
+        .collect();
+    for content in environments {
+        let env: PostmanEnvironment = serde_json::from_str(content)
+            .map_err(|e| format!("Invalid Postman environment: {}", e))?;
+        let values = env
+            .values
+            .iter()
+            .map(|v| name_value(&v.key, &text(&v.value), v.enabled))
+            .collect();
+        let variables = merge_variables(&collection_vars, values);
+        service.environments.push(environment(&env.name, variables));
+    }
+    if service.environments.is_empty() {
+        service
+            .environments
+            .push(environment("Default", collection_vars));
+    }
+    service.selected_environment = service.environments.first().map(|e| e.name.clone());
+
+    report_scripts(&collection.event, "Collection", &mut unsupported);
+    let collection_auth = collection.auth.filter(|a| a.kind != "noauth");
+    if let Some(auth) = &collection_auth {
+        match imported_auth(auth) {
+            Some(imported) => {
+                unsupported.extend(apply_service_auth(&mut service, &imported, "Collection"))
+            }
+            None => unsupported.push(format!("Collection: {} auth", auth.kind)),
+        }
+    }
+
+    let mut importer = Importer {
+        service_id: service_id.to_string(),
+        collection_auth,
+        endpoints: vec![],
+        path_variables: PathVariables::default(),
+        unsupported,
+    };
+    importer.add_items(&collection.item, "", None);
+    service.endpoints = importer.endpoints;
+    importer.path_variables.add_to(&mut service.environments);
+    Ok(ImportResult::new(service, importer.unsupported))
+}
+
+fn report_scripts(events: &[Event], owner: &str, unsupported: &mut Vec<String>) {
+    for event in events.iter().filter(|e| e.has_code()) {
+        unsupported.push(format!("{}: {} script", owner, event.listen));
+    }
+}
+
+fn imported_auth(auth: &Auth) -> Option<ImportedAuth> {
+    let method = match auth.kind.as_str() {
+        "basic" => AuthMethod::Basic(BasicCredentials {
+            basic_user: auth.get("username"),
+            basic_pass: auth.get("password"),
+        }),
+        "bearer" => AuthMethod::Bearer {
+            bearer_token: auth.get("token"),
+        },
+        "apikey" => AuthMethod::ApiKey(ApiKeyAuth {
+            api_key_name: auth.get("key"),
+            api_key_value: auth.get("value"),
+            api_key_location: match auth.get("in").as_str() {
+                "query" => ApiKeyLocation::Query,
+                _ => ApiKeyLocation::Header,
+            },
+        }),
+        "oauth2" => {
+            return Some(ImportedAuth::OAuth2(OAuth2Grant {
+                grant_type: match auth.get("grant_type").as_str() {
+                    "password_credentials" => "password".to_string(),
+                    other => other.to_string(),
+                },
+                access_token_url: auth.get("accessTokenUrl"),
+                client_id: auth.get("clientId"),
+                client_secret: auth.get("clientSecret"),
+                scope: auth.get("scope"),
+                username: auth.get("username"),
+                password: auth.get("password"),
+                credentials_in_header: auth.get("client_authentication") == "header",
+                access_token: auth.get("accessToken"),
+            }))
+        }
+        _ => return None,
+    };
+    Some(ImportedAuth::Method(method))
+}
+
+struct Importer {
+    service_id: String,
+    collection_auth: Option<Auth>,
+    endpoints: Vec<Endpoint>,
+    path_variables: PathVariables,
+    unsupported: Vec<String>,
+}
+
+impl Importer {
+    /// Folders don't exist in services; their names prefix the endpoint names instead
+    fn add_items(&mut self, items: &[Item], folder: &str, folder_auth: Option<&Auth>) {
+        for item in items {
+            let name = if folder.is_empty() {
+                item.name.clone()
+            } else {
+                format!("{} / {}", folder, item.name)
+            };
+            report_scripts(&item.event, &name, &mut self.unsupported);
+            match (&item.item, &item.request) {
+                (Some(children), _) => {
+                    let auth = item.auth.as_ref().or(folder_auth);
+                    self.add_items(children, &name, auth);
+                }
+                (None, Some(request)) => {
+                    let endpoint = self.endpoint(&name, request, item, folder_auth);
+                    self.endpoints.push(endpoint);
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn endpoint(
+        &mut self,
+        name: &str,
+        request: &RequestDef,
+        item: &Item,
+        folder_auth: Option<&Auth>,
+    ) -> Endpoint {
+        let request = match request {
+            RequestDef::Url(url) => {
+                let (url, params) = split_query(url);
+                let url = self.path_variables(name, &url, &[]);
+                let mut endpoint = new_endpoint(&self.service_id, name, "GET", &url);
+                endpoint.params = params;
+                self.apply_auth(&mut endpoint, folder_auth, name);
+                return endpoint;
+            }
+            RequestDef::Full(request) => request,
+        };
+        let (url, params) = match &request.url {
+            Some(UrlDef::Raw(raw)) => {
+                let (url, params) = split_query(raw);
+                (self.path_variables(name, &url, &[]), params)
+            }
+            Some(UrlDef::Parts {
+                raw,
+                query,
+                variable,
+            }) => {
+                let (url, raw_params) = split_query(raw);
+                let url = self.path_variables(name, &url, variable);
+                match query {
+                    Some(query) => (url, query.iter().map(KeyValue::to_name_value).collect()),
+                    None => (url, raw_params),
+                }
+            }
+            None => (String::new(), vec![]),
+        };
+        let mut endpoint = new_endpoint(&self.service_id, name, &request.method, &url);
+        endpoint.params = params;
+        endpoint.headers = request.header.iter().map(KeyValue::to_name_value).collect();
+        if let Some(body) = &request.body {
+            self.apply_body(&mut endpoint, body, name);
+        }
+        let auth = request.auth.as_ref().or(item.auth.as_ref()).or(folder_auth);
+        self.apply_auth(&mut endpoint, auth, name);
+        endpoint.examples = item
+            .response
+            .iter()
+            .map(|r| ExampleResponse {
+                name: r.name.clone(),
+                status: r.code.unwrap_or(200),
+                headers: r
+                    .header
+                    .iter()
+                    .flatten()
+                    .map(KeyValue::to_name_value)
+                    .collect(),
+                body: r.body.clone().unwrap_or_default(),
+            })
+            .collect();
+        endpoint
+    }
+
+    fn path_variables(&mut self, name: &str, url: &str, values: &[KeyValue]) -> String {
+        let values: Vec<NameValue> = values.iter().map(KeyValue::to_name_value).collect();
+        self.path_variables
+            .rewrite(name, url, &values, &mut self.unsupported)
+    }
+
+    fn apply_body(&mut self, endpoint: &mut Endpoint, body: &Body, name: &str) {
+        match body.mode.as_str() {
+            "raw" => {
+                endpoint.body = body.raw.clone();
+                let content_type = match body.options["raw"]["language"].as_str() {
+                    Some("json") => Some("application/json"),
+                    Some("xml") => Some("application/xml"),
+                    Some("html") => Some("text/html"),
+                    Some("javascript") => Some("application/javascript"),
+                    Some("text") => Some("text/plain"),
+                    _ => None,
+                };
+                if let Some(content_type) = content_type.filter(|_| !body.raw.is_empty()) {
+                    set_content_type(&mut endpoint.headers, content_type);
+                }
+            }
+            "urlencoded" => {
+                let fields: Vec<NameValue> = body
+                    .urlencoded
+                    .iter()
+                    .map(KeyValue::to_name_value)
+                    .collect();
+                endpoint.body = form_body(&fields);
+                set_content_type(&mut endpoint.headers, "application/x-www-form-urlencoded");
+            }
+            "formdata" => {
+                let mut fields = Vec::new();
+                for field in &body.formdata {
+                    if field.kind.as_deref() == Some("file") {
+                        self.unsupported
+                            .push(format!("{}: form-data file field '{}'", name, field.key));
+                    } else {
+                        fields.push(field.to_name_value());
+                    }
+                }
+                endpoint.body = multipart_body(&fields);
+                set_content_type(
+                    &mut endpoint.headers,
+                    &format!("multipart/form-data; boundary={}", MULTIPART_BOUNDARY),
+                );
+            }
+            "" | "none" => {}
+            other => self.unsupported.push(format!("{}: {} body", name, other)),
+        }
+    }
+
+    /// Requests using the collection auth inherit it from the service. Other auth is written
+    /// into the request's own headers or query where possible.
+    fn apply_auth(&mut self, endpoint: &mut Endpoint, auth: Option<&Auth>, name: &str) {
+        let auth = match auth {
+            None => {
+                endpoint.authenticated = self.collection_auth.is_some();
+                if let Some(collection_auth) = &self.collection_auth {
+                    endpoint.auth_type = auth_type(collection_auth);
+                }
+                return;
+            }
+            Some(auth) if auth.kind == "noauth" => return,
+            Some(auth) => auth,
+        };
+        if self
+            .collection_auth
+            .as_ref()
+            .is_some_and(|c| c.kind == auth.kind && c.settings == auth.settings)
+        {
+            endpoint.authenticated = true;
+            endpoint.auth_type = auth_type(auth);
+            return;
+        }
+        match imported_auth(auth) {
+            Some(imported) => self
+                .unsupported
+                .extend(apply_endpoint_auth(endpoint, &imported, name)),
+            None => self
+                .unsupported
+                .push(format!("{}: {} auth", name, auth.kind)),
+        }
+    }
+}
+
+/// Auth types without an equivalent are reported and never applied, so the endpoint is marked
+/// as using none rather than guessing
+fn auth_type(auth: &Auth) -> AuthType {
+    imported_auth(auth)
+        .map(|a| a.auth_type())
+        .unwrap_or_default()
+}
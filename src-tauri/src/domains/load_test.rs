@@ -0,0 +1,384 @@
+use crate::domains::auth::profile::AuthProfile;
+use crate::domains::runner::{endpoint_tab, environment_variables, with_defaults};
+use crate::io::FileSystem;
+use crate::services::RequestService;
+use crate::types::{QResponse, RequestTab, Service};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+/// Most requests in flight at once; in rate mode, requests due while this many are still
+/// waiting for a response are dropped
+pub const MAX_CONCURRENCY: usize = 256;
+/// How often progress is reported while the test runs
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// Upper bounds of the latency histogram buckets in ms; a last bucket holds the rest
+pub const HISTOGRAM_BOUNDS_MS: [u64; 11] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000, 10000];
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LoadMode {
+    /// `requests` in total, at most `concurrency` at a time
+    Count { requests: usize, concurrency: usize },
+    /// Starts `rps` requests per second for `duration_secs`
+    Rate { rps: u32, duration_secs: u64 },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestOptions {
+    pub endpoint_id: String,
+    /// Defaults to the selected environment of the service
+    #[serde(default)]
+    pub environment: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    pub mode: LoadMode,
+    /// Load tests refuse environments marked unsafe unless this is set
+    #[serde(default)]
+    pub allow_unsafe: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyStats {
+    pub min: u64,
+    pub mean: u64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyBucket {
+    /// Inclusive upper bound in ms; None for the last bucket
+    pub le_ms: Option<u64>,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadTestReport {
+    pub service_id: String,
+    pub endpoint_id: String,
+    pub environment: Option<String>,
+    pub mode: LoadMode,
+    /// RFC 3339 time the test started
+    pub started_at: String,
+    pub duration_ms: u64,
+    /// Requests completed so far
+    pub total: usize,
+    /// Requests that could not be sent or got no response
+    pub errors: usize,
+    /// Requests rate mode skipped because `MAX_CONCURRENCY` requests were in flight
+    #[serde(default)]
+    pub dropped: usize,
+    /// Completed requests per second
+    pub rps: f64,
+    /// Latency in ms of the requests that got a response
+    pub latency: LatencyStats,
+    pub histogram: Vec<LatencyBucket>,
+    pub status_codes: BTreeMap<u16, usize>,
+    /// Error messages and how often they occurred
+    pub error_messages: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum LoadTestProgress {
+    Started {
+        endpoint_id: String,
+    },
+    /// Everything measured so far
+    Progress {
+        report: LoadTestReport,
+    },
+    Finished {
+        report: LoadTestReport,
+    },
+}
+
+/// The outcome of one request
+enum Sample {
+    Response { status: u16, latency_ms: u64 },
+    Error(String),
+}
+
+/// Accumulates samples into a report
+struct Recorder {
+    report: LoadTestReport,
+    latencies: Vec<u64>,
+    started: Instant,
+}
+
+impl Recorder {
+    fn record(&mut self, sample: Sample) {
+        self.report.total += 1;
+        match sample {
+            Sample::Response { status, latency_ms } => {
+                *self.report.status_codes.entry(status).or_default() += 1;
+                self.latencies.push(latency_ms);
+            }
+            Sample::Error(message) => {
+                self.report.errors += 1;
+                *self.report.error_messages.entry(message).or_default() += 1;
+            }
+        }
+    }
+
+    fn snapshot(&mut self) -> LoadTestReport {
+        self.latencies.sort_unstable();
+        let elapsed = self.started.elapsed();
+        let mut report = self.report.clone();
+        report.duration_ms = elapsed.as_millis() as u64;
+        report.rps = if elapsed.is_zero() {
+            0.0
+        } else {
+            report.total as f64 / elapsed.as_secs_f64()
+        };
+        report.latency = latency_stats(&self.latencies);
+        report.histogram = histogram(&self.latencies);
+        report
+    }
+}
+
+/// Percentiles by nearest rank over sorted latencies
+pub fn latency_stats(sorted: &[u64]) -> LatencyStats {
+    if sorted.is_empty() {
+        return LatencyStats::default();
+    }
+    let percentile = |p: f64| {
+        let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    };
+    LatencyStats {
+        min: sorted[0],
+        mean: sorted.iter().sum::<u64>() / sorted.len() as u64,
+        p50: percentile(50.0),
+        p90: percentile(90.0),
+        p99: percentile(99.0),
+        max: sorted[sorted.len() - 1],
+    }
+}
+
+pub fn histogram(latencies: &[u64]) -> Vec<LatencyBucket> {
+    let mut buckets: Vec<LatencyBucket> = HISTOGRAM_BOUNDS_MS
+        .iter()
+        .map(|bound| LatencyBucket {
+            le_ms: Some(*bound),
+            count: 0,
+        })
+        .chain(std::iter::once(LatencyBucket {
+            le_ms: None,
+            count: 0,
+        }))
+        .collect();
+    for latency in latencies {
+        let index = HISTOGRAM_BOUNDS_MS
+            .iter()
+            .position(|bound| latency <= bound)
+            .unwrap_or(HISTOGRAM_BOUNDS_MS.len());
+        buckets[index].count += 1;
+    }
+    buckets
+}
+
+fn validate(mode: &LoadMode) -> Result<(), String> {
+    match mode {
+        LoadMode::Count {
+            requests,
+            concurrency,
+        } => {
+            if *requests == 0 {
+                return Err("A load test needs at least one request".to_string());
+            }
+            if *concurrency == 0 || *concurrency > MAX_CONCURRENCY {
+                return Err(format!(
+                    "Concurrency must be between 1 and {}",
+                    MAX_CONCURRENCY
+                ));
+            }
+        }
+        LoadMode::Rate { rps, duration_secs } => {
+            if *rps == 0 || *duration_secs == 0 {
+                return Err("Rate and duration must be at least 1".to_string());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fires requests at one endpoint as the mode asks, reporting progress every
+/// `PROGRESS_INTERVAL`
+pub async fn run(
+    request_service: &RequestService<'_>,
+    service: &Service,
+    profiles: &[AuthProfile],
+    options: &LoadTestOptions,
+    on_progress: &(dyn Fn(LoadTestProgress) + Send + Sync),
+) -> Result<LoadTestReport, String> {
+    validate(&options.mode)?;
+    let endpoint = service
+        .endpoints
+        .iter()
+        .find(|e| e.id == options.endpoint_id)
+        .ok_or_else(|| format!("Endpoint '{}' not found in service", options.endpoint_id))?;
+    let environment = options
+        .environment
+        .clone()
+        .or_else(|| service.selected_environment.clone());
+    if let Some(name) = environment.as_deref() {
+        let is_unsafe = service
+            .environments
+            .iter()
+            .any(|e| e.name == name && e.is_unsafe);
+        if is_unsafe && !options.allow_unsafe {
+            return Err(format!(
+                "Environment '{}' is marked unsafe; allow unsafe environments to load test it",
+                name
+            ));
+        }
+    }
+    let mut variables = environment_variables(service, environment.as_deref())?;
+    variables.extend(options.variables.clone());
+    let tab = with_defaults(
+        service,
+        profiles,
+        endpoint_tab(service, endpoint, environment.as_deref(), variables),
+    )?;
+
+    on_progress(LoadTestProgress::Started {
+        endpoint_id: endpoint.id.clone(),
+    });
+    let mut recorder = Recorder {
+        report: LoadTestReport {
+            service_id: service.id.clone(),
+            endpoint_id: endpoint.id.clone(),
+            environment,
+            mode: options.mode.clone(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            duration_ms: 0,
+            total: 0,
+            errors: 0,
+            dropped: 0,
+            rps: 0.0,
+            latency: LatencyStats::default(),
+            histogram: vec![],
+            status_codes: BTreeMap::new(),
+            error_messages: BTreeMap::new(),
+        },
+        latencies: Vec::new(),
+        started: Instant::now(),
+    };
+    let mut last_progress = Instant::now();
+
+    match options.mode {
+        LoadMode::Count {
+            requests,
+            concurrency,
+        } => {
+            let mut in_flight = FuturesUnordered::new();
+            let mut started = 0;
+            loop {
+                while started < requests && in_flight.len() < concurrency {
+                    in_flight.push(send(request_service, &tab));
+                    started += 1;
+                }
+                let Some(sample) = in_flight.next().await else {
+                    break;
+                };
+                recorder.record(sample);
+                report_progress(&mut recorder, &mut last_progress, on_progress);
+            }
+        }
+        LoadMode::Rate { rps, duration_secs } => {
+            let deadline = tokio::time::Instant::now() + Duration::from_secs(duration_secs);
+            let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / rps as f64));
+            let slots = Arc::new(Semaphore::new(MAX_CONCURRENCY));
+            let mut in_flight = FuturesUnordered::new();
+            loop {
+                tokio::select! {
+                    _ = ticks.tick(), if tokio::time::Instant::now() < deadline => {
+                        // A slow server must not pile up requests without bound
+                        let Ok(permit) = slots.clone().try_acquire_owned() else {
+                            recorder.report.dropped += 1;
+                            continue;
+                        };
+                        let tab = &tab;
+                        in_flight.push(async move {
+                            let sample = send(request_service, tab).await;
+                            drop(permit);
+                            sample
+                        });
+                    }
+                    Some(sample) = in_flight.next(), if !in_flight.is_empty() => {
+                        recorder.record(sample);
+                        report_progress(&mut recorder, &mut last_progress, on_progress);
+                    }
+                    else => break,
+                }
+            }
+        }
+    }
+
+    let report = recorder.snapshot();
+    on_progress(LoadTestProgress::Finished {
+        report: report.clone(),
+    });
+    Ok(report)
+}
+
+async fn send(request_service: &RequestService<'_>, tab: &RequestTab) -> Sample {
+    match request_service.send_request(tab.clone()).await {
+        Ok(QResponse {
+            error: Some(error), ..
+        }) => Sample::Error(error),
+        Ok(response) => Sample::Response {
+            status: response.status,
+            latency_ms: response.time_elapsed,
+        },
+        Err(e) => Sample::Error(e),
+    }
+}
+
+fn report_progress(
+    recorder: &mut Recorder,
+    last_progress: &mut Instant,
+    on_progress: &(dyn Fn(LoadTestProgress) + Send + Sync),
+) {
+    if last_progress.elapsed() >= PROGRESS_INTERVAL {
+        *last_progress = Instant::now();
+        on_progress(LoadTestProgress::Progress {
+            report: recorder.snapshot(),
+        });
+    }
+}
+
+/// Saves a report as pretty-printed JSON
+pub fn save_report(
+    fs: &dyn FileSystem,
+    path: &Path,
+    report: &LoadTestReport,
+) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !fs.exists(parent) {
+            fs.create_dir_all(parent)?;
+        }
+    }
+    let content = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    fs.write(path, &content)
+}
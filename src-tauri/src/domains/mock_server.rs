@@ -0,0 +1,432 @@
+use crate::types::{Endpoint, ExampleResponse, Service};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper_util::rt::TokioIo;
+use once_cell::sync::Lazy;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// Requests kept in the log of each running server
+pub const LOG_LIMIT: usize = 500;
+/// Picks an example by name, e.g. `X-Mock-Example: 404 notFound`
+pub const EXAMPLE_HEADER: &str = "x-mock-example";
+/// Picks the first example with this status, e.g. `X-Mock-Status: 404`
+pub const STATUS_HEADER: &str = "x-mock-status";
+
+static SERVERS: Lazy<Mutex<HashMap<String, RunningServer>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServerOptions {
+    /// 0 picks a free port
+    #[serde(default)]
+    pub port: u16,
+    /// Added to every response
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Up to this much more latency, at random
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Share of requests, 0 to 1, answered with `error_status` instead of an example
+    #[serde(default)]
+    pub error_rate: f64,
+    #[serde(default = "default_error_status")]
+    pub error_status: u16,
+}
+
+fn default_error_status() -> u16 {
+    500
+}
+
+impl Default for MockServerOptions {
+    fn default() -> Self {
+        Self {
+            port: 0,
+            latency_ms: 0,
+            jitter_ms: 0,
+            error_rate: 0.0,
+            error_status: default_error_status(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServerInfo {
+    pub service_id: String,
+    pub port: u16,
+    pub url: String,
+    /// RFC 3339 time the server started
+    pub started_at: String,
+    pub options: MockServerOptions,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MockRequestLog {
+    pub service_id: String,
+    /// RFC 3339 time the request came in
+    pub received_at: String,
+    pub method: String,
+    /// Path and query as requested
+    pub path: String,
+    pub status: u16,
+    pub endpoint_id: Option<String>,
+    pub example: Option<String>,
+    /// The response was an injected error
+    pub injected_error: bool,
+    pub time_elapsed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub endpoint_id: Option<String>,
+    pub example: Option<String>,
+}
+
+struct RunningServer {
+    info: MockServerInfo,
+    log: Arc<Mutex<VecDeque<MockRequestLog>>>,
+    shutdown: oneshot::Sender<()>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Literal(&'a str),
+    Param,
+}
+
+/// Path segments of an endpoint URL, without scheme, host or a leading `{{BASE_URL}}`.
+/// `{id}`, `:id` and `{{id}}` segments match anything.
+fn route_segments(url: &str) -> Vec<Segment<'_>> {
+    let mut path = url.split(['?', '#']).next().unwrap_or_default();
+    if let Some((_, rest)) = path.split_once("://") {
+        path = rest.find('/').map(|i| &rest[i..]).unwrap_or("/");
+    } else if path.starts_with("{{") {
+        if let Some(end) = path.find("}}") {
+            path = &path[end + 2..];
+        }
+    }
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if s.starts_with(':') || (s.starts_with('{') && s.ends_with('}')) {
+                Segment::Param
+            } else {
+                Segment::Literal(s)
+            }
+        })
+        .collect()
+}
+
+/// The endpoint whose method and URL template match; literal segments beat parameters
+pub fn match_endpoint<'e>(
+    endpoints: &'e [Endpoint],
+    method: &str,
+    path: &str,
+) -> Option<&'e Endpoint> {
+    let requested: Vec<&str> = path
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    endpoints
+        .iter()
+        .filter(|e| e.method.eq_ignore_ascii_case(method))
+        .filter_map(|e| {
+            let route = route_segments(&e.url);
+            let matches = route.len() == requested.len()
+                && route
+                    .iter()
+                    .zip(&requested)
+                    .all(|(segment, part)| match segment {
+                        Segment::Literal(literal) => literal == part,
+                        Segment::Param => true,
+                    });
+            let literals = route
+                .iter()
+                .filter(|s| matches!(s, Segment::Literal(_)))
+                .count();
+            matches.then_some((literals, e))
+        })
+        // max_by_key keeps the last of equals; reverse so the first endpoint wins ties
+        .rev()
+        .max_by_key(|(literals, _)| *literals)
+        .map(|(_, e)| e)
+}
+
+/// The example asked for in the headers, else the first successful one, else the first
+fn pick_example<'e>(
+    examples: &'e [ExampleResponse],
+    headers: &HashMap<String, String>,
+) -> Option<&'e ExampleResponse> {
+    if let Some(name) = headers.get(EXAMPLE_HEADER) {
+        return examples.iter().find(|e| &e.name == name);
+    }
+    if let Some(status) = headers.get(STATUS_HEADER) {
+        return examples.iter().find(|e| e.status.to_string() == *status);
+    }
+    examples
+        .iter()
+        .find(|e| (200..300).contains(&e.status))
+        .or_else(|| examples.first())
+}
+
+fn error_response(status: u16, message: String) -> MockResponse {
+    MockResponse {
+        status,
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: serde_json::json!({ "error": message }).to_string(),
+        endpoint_id: None,
+        example: None,
+    }
+}
+
+/// Answers a request from the examples of the matching endpoint. Header names are lowercase.
+pub fn respond(
+    endpoints: &[Endpoint],
+    method: &str,
+    path: &str,
+    headers: &HashMap<String, String>,
+) -> MockResponse {
+    let Some(endpoint) = match_endpoint(endpoints, method, path) else {
+        return error_response(404, format!("No endpoint matches {} {}", method, path));
+    };
+    let Some(example) = pick_example(&endpoint.examples, headers) else {
+        let mut response = error_response(
+            501,
+            format!(
+                "Endpoint '{}' has no matching example response",
+                endpoint.name
+            ),
+        );
+        response.endpoint_id = Some(endpoint.id.clone());
+        return response;
+    };
+    MockResponse {
+        status: example.status,
+        headers: example
+            .headers
+            .iter()
+            .filter(|h| h.enabled)
+            .map(|h| (h.name.clone(), h.value.clone()))
+            .collect(),
+        body: example.body.clone(),
+        endpoint_id: Some(endpoint.id.clone()),
+        example: Some(example.name.clone()),
+    }
+}
+
+/// Serves the examples of a service on localhost until stopped. Requests are kept in
+/// the server's log and passed to `on_request`.
+pub async fn start(
+    service: &Service,
+    options: MockServerOptions,
+    on_request: Arc<dyn Fn(MockRequestLog) + Send + Sync>,
+) -> Result<MockServerInfo, String> {
+    if !(0.0..=1.0).contains(&options.error_rate) {
+        return Err("The error rate must be between 0 and 1".to_string());
+    }
+    if let Some(running) = SERVERS.lock().unwrap().get(&service.id) {
+        return Err(format!(
+            "A mock server for '{}' is already running on port {}",
+            service.name, running.info.port
+        ));
+    }
+    let listener = TcpListener::bind(("127.0.0.1", options.port))
+        .await
+        .map_err(|e| format!("Failed to start mock server: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let info = MockServerInfo {
+        service_id: service.id.clone(),
+        port,
+        url: format!("http://127.0.0.1:{}", port),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        options: options.clone(),
+    };
+    let (shutdown, stopped) = oneshot::channel();
+    let log = Arc::new(Mutex::new(VecDeque::new()));
+    SERVERS.lock().unwrap().insert(
+        service.id.clone(),
+        RunningServer {
+            info: info.clone(),
+            log: log.clone(),
+            shutdown,
+        },
+    );
+
+    let handler = Arc::new(Handler {
+        service_id: service.id.clone(),
+        endpoints: service.endpoints.clone(),
+        options,
+        log,
+        on_request,
+    });
+    serve(listener, stopped, move |request| {
+        let handler = handler.clone();
+        async move { handler.handle(request).await }
+    });
+    Ok(info)
+}
+
+/// Accepts connections until `stopped` fires, answering every request with `handle`
+pub(crate) fn serve<F, Fut>(listener: TcpListener, mut stopped: oneshot::Receiver<()>, handle: F)
+where
+    F: Fn(hyper::Request<Incoming>) -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = hyper::Response<Full<Bytes>>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            let stream = tokio::select! {
+                _ = &mut stopped => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        eprintln!("Failed to accept a connection: {}", e);
+                        continue;
+                    }
+                },
+            };
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let response = handle(request);
+                    async move { Ok::<_, Infallible>(response.await) }
+                });
+                let _ = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await;
+            });
+        }
+    });
+}
+
+pub(crate) fn into_hyper(response: MockResponse) -> hyper::Response<Full<Bytes>> {
+    let mut builder = hyper::Response::builder().status(response.status);
+    for (name, value) in &response.headers {
+        builder = builder.header(name, value);
+    }
+    builder
+        .body(Full::new(Bytes::from(response.body)))
+        .unwrap_or_else(|e| {
+            let mut fallback = hyper::Response::new(Full::new(Bytes::from(e.to_string())));
+            *fallback.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
+            fallback
+        })
+}
+
+pub fn stop(service_id: &str) -> Result<(), String> {
+    let running = SERVERS
+        .lock()
+        .unwrap()
+        .remove(service_id)
+        .ok_or_else(|| format!("No mock server is running for '{}'", service_id))?;
+    let _ = running.shutdown.send(());
+    Ok(())
+}
+
+pub fn running_servers() -> Vec<MockServerInfo> {
+    let mut servers: Vec<MockServerInfo> = SERVERS
+        .lock()
+        .unwrap()
+        .values()
+        .map(|s| s.info.clone())
+        .collect();
+    servers.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+    servers
+}
+
+/// Requests the server received, oldest first
+pub fn request_log(service_id: &str) -> Result<Vec<MockRequestLog>, String> {
+    let servers = SERVERS.lock().unwrap();
+    let running = servers
+        .get(service_id)
+        .ok_or_else(|| format!("No mock server is running for '{}'", service_id))?;
+    let log = running.log.lock().unwrap();
+    Ok(log.iter().cloned().collect())
+}
+
+struct Handler {
+    service_id: String,
+    endpoints: Vec<Endpoint>,
+    options: MockServerOptions,
+    log: Arc<Mutex<VecDeque<MockRequestLog>>>,
+    on_request: Arc<dyn Fn(MockRequestLog) + Send + Sync>,
+}
+
+impl Handler {
+    async fn handle<B>(&self, request: hyper::Request<B>) -> hyper::Response<Full<Bytes>> {
+        let started = Instant::now();
+        let received_at = chrono::Utc::now().to_rfc3339();
+        let method = request.method().to_string();
+        let path = request
+            .uri()
+            .path_and_query()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "/".to_string());
+        let headers: HashMap<String, String> = request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+
+        let (inject_error, delay) = {
+            let mut rng = rand::thread_rng();
+            let jitter = if self.options.jitter_ms > 0 {
+                rng.gen_range(0..=self.options.jitter_ms)
+            } else {
+                0
+            };
+            (
+                rng.gen::<f64>() < self.options.error_rate,
+                self.options.latency_ms + jitter,
+            )
+        };
+        let mut response = respond(&self.endpoints, &method, &path, &headers);
+        if inject_error {
+            let endpoint_id = response.endpoint_id.take();
+            response = error_response(self.options.error_status, "Injected error".to_string());
+            response.endpoint_id = endpoint_id;
+        }
+        if delay > 0 {
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+        }
+
+        let entry = MockRequestLog {
+            service_id: self.service_id.clone(),
+            received_at,
+            method,
+            path,
+            status: response.status,
+            endpoint_id: response.endpoint_id.clone(),
+            example: response.example.clone(),
+            injected_error: inject_error,
+            time_elapsed: started.elapsed().as_millis() as u64,
+        };
+        {
+            let mut log = self.log.lock().unwrap();
+            if log.len() == LOG_LIMIT {
+                log.pop_front();
+            }
+            log.push_back(entry.clone());
+        }
+        (self.on_request)(entry);
+
+        into_hyper(response)
+    }
+}
@@ -1,5 +1,15 @@
+pub mod assertions;
 pub mod auth;
+pub mod data_file;
 pub mod git;
+pub mod har;
+pub mod import;
+pub mod load_test;
+pub mod mock_server;
+pub mod monitor;
+pub mod proxy;
+pub mod runner;
+pub mod scripting;
 pub mod secrets;
 pub mod service;
 pub mod settings;
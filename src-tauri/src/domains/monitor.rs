@@ -0,0 +1,516 @@
+use crate::domains::assertions::Assertion;
+use crate::domains::auth::profile::AuthProfile;
+use crate::domains::runner::{self, RunOptions, RunRequestResult, WorkflowOptions};
+use crate::domains::service::workflow::Workflow;
+use crate::io::{FileSystem, RealFileSystem, RealHttpClient};
+use crate::services::{ConfigService, RequestService};
+use crate::types::Service;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tauri_plugin_notification::NotificationExt;
+
+/// Monitors never run more often than this
+pub const MIN_INTERVAL_SECS: u64 = 10;
+/// How often the scheduler looks for monitors that are due
+pub const TICK_INTERVAL: Duration = Duration::from_secs(5);
+/// Results kept per monitor; older ones are dropped as new ones come in
+pub const RESULTS_KEPT: usize = 500;
+/// Longest a single run may take, whatever the interval of the monitor
+pub const MAX_RUN_TIME: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum MonitorTarget {
+    Endpoint { endpoint_id: String },
+    Workflow { workflow_id: String },
+}
+
+/// An endpoint or workflow run on an interval while the app is open
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Monitor {
+    pub id: String,
+    pub name: String,
+    pub service_id: String,
+    pub target: MonitorTarget,
+    /// Defaults to the selected environment of the service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    pub interval_secs: u64,
+    /// Checked on every response on top of the endpoint's own assertions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<Assertion>,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorResult {
+    pub id: String,
+    pub monitor_id: String,
+    pub service_id: String,
+    /// RFC 3339 time the run started
+    pub started_at: String,
+    pub passed: bool,
+    pub time_elapsed: u64,
+    /// Why the monitor could not run at all
+    pub error: Option<String>,
+    pub results: Vec<RunRequestResult>,
+}
+
+pub fn validate_monitors(monitors: &[Monitor]) -> Result<(), String> {
+    let mut seen = Vec::new();
+    for monitor in monitors {
+        if monitor.id.is_empty() || seen.contains(&monitor.id.as_str()) {
+            return Err(format!(
+                "Monitor ids must be unique and not empty, got '{}'",
+                monitor.id
+            ));
+        }
+        seen.push(monitor.id.as_str());
+        if monitor.interval_secs < MIN_INTERVAL_SECS {
+            return Err(format!(
+                "Monitor '{}': the interval must be at least {} seconds",
+                monitor.name, MIN_INTERVAL_SECS
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn get_monitors_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
+    let path = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(path.join("monitors.yaml"))
+}
+
+pub fn load_monitors(fs: &dyn FileSystem, path: &Path) -> Result<Vec<Monitor>, String> {
+    if !fs.exists(path) {
+        return Ok(Vec::new());
+    }
+    let content = fs.read_to_string(path)?;
+    serde_yaml::from_str(&content).map_err(|e| format!("Failed to parse monitors.yaml: {}", e))
+}
+
+pub fn save_monitors(fs: &dyn FileSystem, path: &Path, monitors: &[Monitor]) -> Result<(), String> {
+    validate_monitors(monitors)?;
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() && !fs.exists(parent) {
+            fs.create_dir_all(parent)?;
+        }
+    }
+    let content = serde_yaml::to_string(monitors).map_err(|e| e.to_string())?;
+    fs.write(path, &content)
+}
+
+/// Monitor results, kept in the history database
+pub struct MonitorStore {
+    pub conn: Connection,
+}
+
+impl MonitorStore {
+    pub fn new(conn: Connection) -> Self {
+        Self { conn }
+    }
+
+    pub fn init(&self) -> Result<(), String> {
+        self.conn
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS monitor_results (
+                id TEXT PRIMARY KEY,
+                monitor_id TEXT NOT NULL,
+                service_id TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                passed INTEGER NOT NULL,
+                time_elapsed INTEGER NOT NULL,
+                error TEXT,
+                results TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS monitor_results_by_monitor
+                ON monitor_results (monitor_id, started_at);",
+            )
+            .map_err(|e| e.to_string())
+    }
+
+    /// Stores a result and drops the oldest beyond `RESULTS_KEPT`. Returns true when the
+    /// monitor flipped from passing to failing; a monitor without results counts as passing.
+    pub fn record(&self, result: &MonitorResult) -> Result<bool, String> {
+        let was_passing = self
+            .get_results(Some(&result.monitor_id), 1)?
+            .first()
+            .is_none_or(|last| last.passed);
+        let results = serde_json::to_string(&result.results).map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "INSERT INTO monitor_results (
+                id, monitor_id, service_id, started_at, passed, time_elapsed, error, results
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    result.id,
+                    result.monitor_id,
+                    result.service_id,
+                    result.started_at,
+                    result.passed,
+                    result.time_elapsed,
+                    result.error,
+                    results,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+        self.conn
+            .execute(
+                "DELETE FROM monitor_results WHERE monitor_id = ?1 AND id NOT IN (
+                SELECT id FROM monitor_results WHERE monitor_id = ?1
+                ORDER BY started_at DESC LIMIT ?2
+            )",
+                params![result.monitor_id, RESULTS_KEPT],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(was_passing && !result.passed)
+    }
+
+    /// Newest first, of one monitor or of all of them
+    pub fn get_results(
+        &self,
+        monitor_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<MonitorResult>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, monitor_id, service_id, started_at, passed, time_elapsed, error, results
+                FROM monitor_results
+                WHERE ?1 IS NULL OR monitor_id = ?1
+                ORDER BY started_at DESC
+                LIMIT ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![monitor_id, limit], |row| {
+                let results_raw: String = row.get(7)?;
+                Ok(MonitorResult {
+                    id: row.get(0)?,
+                    monitor_id: row.get(1)?,
+                    service_id: row.get(2)?,
+                    started_at: row.get(3)?,
+                    passed: row.get(4)?,
+                    time_elapsed: row.get(5)?,
+                    error: row.get(6)?,
+                    results: serde_json::from_str(&results_raw).unwrap_or_default(),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<rusqlite::Result<_>>()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn clear(&self, monitor_id: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "DELETE FROM monitor_results WHERE monitor_id = ?1",
+                params![monitor_id],
+            )
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// Decides which monitors are due; remembers when each one last ran
+#[derive(Default)]
+pub struct MonitorScheduler {
+    last_run: HashMap<String, Instant>,
+}
+
+impl MonitorScheduler {
+    /// Enabled monitors that never ran or whose interval has passed, marked as run at `now`
+    pub fn due(&mut self, monitors: &[Monitor], now: Instant) -> Vec<Monitor> {
+        self.last_run
+            .retain(|id, _| monitors.iter().any(|m| &m.id == id && m.enabled));
+        let due: Vec<Monitor> = monitors
+            .iter()
+            .filter(|m| m.enabled)
+            .filter(|m| {
+                self.last_run.get(&m.id).is_none_or(|last| {
+                    now.duration_since(*last) >= Duration::from_secs(m.interval_secs)
+                })
+            })
+            .cloned()
+            .collect();
+        for monitor in &due {
+            self.last_run.insert(monitor.id.clone(), now);
+        }
+        due
+    }
+}
+
+/// Runs the monitor's endpoint or workflow once. Failures to run end up in `error`.
+pub async fn run_monitor(
+    request_service: &RequestService<'_>,
+    service: &Service,
+    profiles: &[AuthProfile],
+    workflows: &[Workflow],
+    monitor: &Monitor,
+) -> MonitorResult {
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let started = Instant::now();
+    let mut service = service.clone();
+    for endpoint in &mut service.endpoints {
+        endpoint
+            .assertions
+            .extend(monitor.assertions.iter().cloned());
+    }
+
+    let summary = match &monitor.target {
+        MonitorTarget::Endpoint { endpoint_id } => {
+            let options = RunOptions {
+                endpoint_ids: vec![endpoint_id.clone()],
+                environment: monitor.environment.clone(),
+                ..Default::default()
+            };
+            runner::run(request_service, &service, profiles, &options, &|_| {}).await
+        }
+        MonitorTarget::Workflow { workflow_id } => {
+            match workflows.iter().find(|w| &w.id == workflow_id) {
+                Some(workflow) => {
+                    let options = WorkflowOptions {
+                        environment: monitor.environment.clone(),
+                        ..Default::default()
+                    };
+                    runner::run_workflow(
+                        request_service,
+                        &service,
+                        profiles,
+                        workflow,
+                        &options,
+                        &|_| {},
+                    )
+                    .await
+                }
+                None => Err(format!("Workflow '{}' not found", workflow_id)),
+            }
+        }
+    };
+
+    let (passed, error, mut results) = match summary {
+        Ok(summary) => (summary.failed == 0, None, summary.results),
+        Err(e) => (false, Some(e), Vec::new()),
+    };
+    // Bodies of every run would fill the history database
+    for result in &mut results {
+        result.exchange = None;
+    }
+    MonitorResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        monitor_id: monitor.id.clone(),
+        service_id: monitor.service_id.clone(),
+        started_at,
+        passed,
+        time_elapsed: started.elapsed().as_millis() as u64,
+        error,
+        results,
+    }
+}
+
+/// One line on why a run failed, for notifications
+pub fn failure_reason(result: &MonitorResult) -> String {
+    if let Some(error) = &result.error {
+        return error.clone();
+    }
+    let Some(failed) = result.results.iter().find(|r| !r.passed && !r.skipped) else {
+        return "Failed".to_string();
+    };
+    let reason = failed
+        .error
+        .clone()
+        .or_else(|| failed.script_errors.first().cloned())
+        .or_else(|| {
+            failed
+                .assertions
+                .iter()
+                .find(|a| !a.passed)
+                .map(|a| a.message.clone())
+        })
+        .or_else(|| failed.status.map(|s| format!("Status {}", s)))
+        .unwrap_or_else(|| "Failed".to_string());
+    format!("{}: {}", failed.name, reason)
+}
+
+/// A monitor that just started failing, sent as a `monitor-failing` event
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MonitorFailingEvent {
+    monitor: Monitor,
+    result: MonitorResult,
+}
+
+fn open_store<R: Runtime>(app: &AppHandle<R>) -> Result<MonitorStore, String> {
+    let db_path = crate::history::get_db_path(app)?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    Ok(MonitorStore::new(conn))
+}
+
+pub fn init_db<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    open_store(app)?.init()
+}
+
+pub fn get_results<R: Runtime>(
+    app: &AppHandle<R>,
+    monitor_id: Option<&str>,
+    limit: usize,
+) -> Result<Vec<MonitorResult>, String> {
+    open_store(app)?.get_results(monitor_id, limit)
+}
+
+pub fn clear_results<R: Runtime>(app: &AppHandle<R>, monitor_id: &str) -> Result<(), String> {
+    open_store(app)?.clear(monitor_id)
+}
+
+/// Runs the monitor, giving up once its interval has passed so runs never overlap
+pub async fn run_monitor_with_timeout(
+    request_service: &RequestService<'_>,
+    service: &Service,
+    profiles: &[AuthProfile],
+    workflows: &[Workflow],
+    monitor: &Monitor,
+) -> MonitorResult {
+    let limit = Duration::from_secs(monitor.interval_secs).min(MAX_RUN_TIME);
+    let run = run_monitor(request_service, service, profiles, workflows, monitor);
+    match tokio::time::timeout(limit, run).await {
+        Ok(result) => result,
+        Err(_) => failed_to_run(
+            monitor,
+            format!("Monitor run timed out after {} s", limit.as_secs()),
+        ),
+    }
+}
+
+/// Starts the monitors that are due, each on its own task, then records their results and
+/// raises a `monitor-result` event for each. A monitor that starts failing also raises `monitor-failing` and a
+/// desktop notification.
+pub async fn run_due<R: Runtime>(app: &AppHandle<R>, scheduler: &mut MonitorScheduler) {
+    let app_handle = app.clone();
+    let loaded = tokio::task::spawn_blocking(move || {
+        load_monitors(&RealFileSystem, &get_monitors_path(&app_handle)?)
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|monitors| monitors);
+    let monitors = match loaded {
+        Ok(monitors) => monitors,
+        Err(e) => {
+            eprintln!("Failed to load monitors: {}", e);
+            return;
+        }
+    };
+    let due = scheduler.due(&monitors, Instant::now());
+    if due.is_empty() {
+        return;
+    }
+
+    let app_handle = app.clone();
+    let service_ids: Vec<String> = due.iter().map(|m| m.service_id.clone()).collect();
+    let loaded = tokio::task::spawn_blocking(move || {
+        let config = ConfigService::new(&RealFileSystem);
+        let settings = config.load_settings(&app_handle)?;
+        let mut services = HashMap::new();
+        for stub in settings.services.iter() {
+            if !service_ids.contains(&stub.id) {
+                continue;
+            }
+            let service = config.load_service(&stub.directory);
+            let workflows = config.load_workflows(&stub.directory).unwrap_or_default();
+            services.insert(stub.id.clone(), service.map(|s| (s, workflows)));
+        }
+        Ok::<_, String>((settings.auth_profiles, services))
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|loaded| loaded);
+    let (profiles, services) = match loaded {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("Failed to load services for monitors: {}", e);
+            return;
+        }
+    };
+
+    let cache_path = crate::domains::auth::get_token_cache_path(app).ok();
+    let profiles = Arc::new(profiles);
+    // A slow monitor must not hold back the others or the next tick
+    for monitor in due {
+        let loaded = services.get(&monitor.service_id).cloned();
+        let profiles = profiles.clone();
+        let cache_path = cache_path.clone();
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let request_service = RequestService::new(&RealHttpClient, cache_path);
+            let result = match loaded {
+                Some(Ok((service, workflows))) => {
+                    run_monitor_with_timeout(
+                        &request_service,
+                        &service,
+                        &profiles,
+                        &workflows,
+                        &monitor,
+                    )
+                    .await
+                }
+                Some(Err(e)) => failed_to_run(&monitor, e),
+                None => failed_to_run(
+                    &monitor,
+                    format!("Service '{}' not found", monitor.service_id),
+                ),
+            };
+            record_result(&app, monitor, result).await;
+        });
+    }
+}
+
+async fn record_result<R: Runtime>(app: &AppHandle<R>, monitor: Monitor, result: MonitorResult) {
+    let app_handle = app.clone();
+    let stored = result.clone();
+    let flipped = tokio::task::spawn_blocking(move || open_store(&app_handle)?.record(&stored))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|flipped| flipped);
+    let _ = app.emit("monitor-result", result.clone());
+    match flipped {
+        Ok(true) => {
+            let _ = app
+                .notification()
+                .builder()
+                .title(format!("{} is failing", monitor.name))
+                .body(failure_reason(&result))
+                .show();
+            let _ = app.emit("monitor-failing", MonitorFailingEvent { monitor, result });
+        }
+        Ok(false) => {}
+        Err(e) => eprintln!("Failed to record monitor result: {}", e),
+    }
+}
+
+fn failed_to_run(monitor: &Monitor, error: String) -> MonitorResult {
+    MonitorResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        monitor_id: monitor.id.clone(),
+        service_id: monitor.service_id.clone(),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        passed: false,
+        time_elapsed: 0,
+        error: Some(error),
+        results: Vec::new(),
+    }
+}
@@ -0,0 +1,527 @@
+use crate::domains::import::{service_base_url, set_base_url};
+use crate::domains::mock_server::{self, into_hyper, MockResponse};
+use crate::domains::service::endpoint::default_preflight_config;
+use crate::types::{
+    Endpoint, EndpointMetadata, ExampleResponse, Header, HistoryEntry, NameValue, Scripts, Service,
+};
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use url::Url;
+
+/// Exchanges kept in memory per proxy; all of them also go to history
+pub const EXCHANGE_LIMIT: usize = 500;
+
+/// Headers that only make sense for one connection and are not forwarded or recorded
+const HOP_BY_HOP: [&str; 10] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+/// Request headers left out of endpoints made from a recording
+const CREDENTIALS: [&str; 3] = ["authorization", "cookie", "x-api-key"];
+
+static PROXIES: Lazy<Mutex<HashMap<String, RunningProxy>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ProxyMode {
+    /// Forwards to the upstream and records every exchange
+    #[default]
+    Record,
+    /// Answers from a recording without contacting the upstream
+    Replay,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyOptions {
+    /// 0 picks a free port
+    #[serde(default)]
+    pub port: u16,
+    /// Base URL requests are forwarded to, e.g. `https://api.example.com/v1`
+    pub upstream: String,
+    #[serde(default)]
+    pub mode: ProxyMode,
+    /// Service the recorded history entries belong to
+    #[serde(default)]
+    pub service_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyInfo {
+    pub id: String,
+    pub port: u16,
+    pub url: String,
+    /// RFC 3339 time the proxy started
+    pub started_at: String,
+    pub options: ProxyOptions,
+}
+
+struct RunningProxy {
+    info: ProxyInfo,
+    exchanges: Arc<Mutex<VecDeque<HistoryEntry>>>,
+    shutdown: oneshot::Sender<()>,
+}
+
+/// Starts a proxy on localhost. Record mode forwards every request to the upstream and
+/// passes the exchange to `on_exchange`; replay mode answers from `recording`.
+pub async fn start(
+    options: ProxyOptions,
+    recording: Vec<HistoryEntry>,
+    on_exchange: Arc<dyn Fn(HistoryEntry) + Send + Sync>,
+) -> Result<ProxyInfo, String> {
+    let upstream = Url::parse(&options.upstream)
+        .map_err(|e| format!("Invalid upstream '{}': {}", options.upstream, e))?;
+    if options.mode == ProxyMode::Replay && recording.is_empty() {
+        return Err("Replaying needs at least one recorded exchange".to_string());
+    }
+    // Redirects go back to the client, which decides whether to follow them
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| e.to_string())?;
+    let listener = TcpListener::bind(("127.0.0.1", options.port))
+        .await
+        .map_err(|e| format!("Failed to start proxy: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let info = ProxyInfo {
+        id: uuid::Uuid::new_v4().to_string(),
+        port,
+        url: format!("http://127.0.0.1:{}", port),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        options: options.clone(),
+    };
+    let (shutdown, stopped) = oneshot::channel();
+    let exchanges = Arc::new(Mutex::new(VecDeque::new()));
+    PROXIES.lock().unwrap().insert(
+        info.id.clone(),
+        RunningProxy {
+            info: info.clone(),
+            exchanges: exchanges.clone(),
+            shutdown,
+        },
+    );
+
+    let replay_endpoints = endpoints_from_history(&recording, Some(&upstream), "");
+    let proxy = Arc::new(Proxy {
+        upstream,
+        options,
+        recording,
+        replay_endpoints,
+        client,
+        exchanges,
+        on_exchange,
+    });
+    mock_server::serve(listener, stopped, move |request| {
+        let proxy = proxy.clone();
+        async move { proxy.handle(request).await }
+    });
+    Ok(info)
+}
+
+pub fn stop(id: &str) -> Result<(), String> {
+    let running = PROXIES
+        .lock()
+        .unwrap()
+        .remove(id)
+        .ok_or_else(|| format!("No proxy '{}' is running", id))?;
+    let _ = running.shutdown.send(());
+    Ok(())
+}
+
+pub fn running_proxies() -> Vec<ProxyInfo> {
+    let mut proxies: Vec<ProxyInfo> = PROXIES
+        .lock()
+        .unwrap()
+        .values()
+        .map(|p| p.info.clone())
+        .collect();
+    proxies.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+    proxies
+}
+
+/// Exchanges the proxy handled, oldest first
+pub fn exchanges(id: &str) -> Result<Vec<HistoryEntry>, String> {
+    let proxies = PROXIES.lock().unwrap();
+    let running = proxies
+        .get(id)
+        .ok_or_else(|| format!("No proxy '{}' is running", id))?;
+    let exchanges = running.exchanges.lock().unwrap();
+    Ok(exchanges.iter().cloned().collect())
+}
+
+struct Proxy {
+    upstream: Url,
+    options: ProxyOptions,
+    recording: Vec<HistoryEntry>,
+    replay_endpoints: Vec<Endpoint>,
+    client: reqwest::Client,
+    exchanges: Arc<Mutex<VecDeque<HistoryEntry>>>,
+    on_exchange: Arc<dyn Fn(HistoryEntry) + Send + Sync>,
+}
+
+impl Proxy {
+    async fn handle(&self, request: hyper::Request<Incoming>) -> hyper::Response<Full<Bytes>> {
+        let (parts, body) = request.into_parts();
+        let method = parts.method.to_string();
+        let path = parts
+            .uri
+            .path_and_query()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "/".to_string());
+        if self.options.mode == ProxyMode::Replay {
+            return into_hyper(replay(
+                &self.recording,
+                &self.replay_endpoints,
+                Some(&self.upstream),
+                &method,
+                &path,
+            ));
+        }
+
+        let headers: Vec<(String, String)> = parts
+            .headers
+            .iter()
+            .filter(|(name, _)| forwarded(name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let body = match body.collect().await {
+            Ok(collected) => collected.to_bytes(),
+            Err(e) => {
+                return into_hyper(gateway_error(format!(
+                    "Failed to read the request body: {}",
+                    e
+                )))
+            }
+        };
+        let url = upstream_url(&self.upstream, &path);
+        let Ok(upstream_method) = reqwest::Method::from_bytes(method.as_bytes()) else {
+            return into_hyper(gateway_error(format!("Unsupported method: {}", method)));
+        };
+        let mut upstream_request = self.client.request(upstream_method, &url);
+        for (name, value) in &headers {
+            upstream_request = upstream_request.header(name, value);
+        }
+        if !body.is_empty() {
+            upstream_request = upstream_request.body(body.clone());
+        }
+
+        let started = Instant::now();
+        let response = match upstream_request.send().await {
+            Ok(response) => response,
+            Err(e) => return into_hyper(gateway_error(format!("Upstream request failed: {}", e))),
+        };
+        let status = response.status();
+        let response_headers: Vec<Header> = response
+            .headers()
+            .iter()
+            .filter(|(name, _)| forwarded(name.as_str()) && *name != "content-encoding")
+            .filter_map(|(name, value)| {
+                Some(name_value(
+                    name.to_string(),
+                    value.to_str().ok()?.to_string(),
+                ))
+            })
+            .collect();
+        let response_body = match response.bytes().await {
+            Ok(bytes) => bytes,
+            Err(e) => return into_hyper(gateway_error(format!("Upstream request failed: {}", e))),
+        };
+
+        // History holds text; the client gets the bytes exactly as the upstream sent them
+        let entry = HistoryEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            service_id: self.options.service_id.clone(),
+            endpoint_id: None,
+            method,
+            url,
+            request_headers: headers
+                .into_iter()
+                .map(|(name, value)| name_value(name, value))
+                .collect(),
+            request_body: String::from_utf8_lossy(&body).into_owned(),
+            response_status: status.as_u16(),
+            response_status_text: status.canonical_reason().unwrap_or_default().to_string(),
+            response_headers: response_headers.clone(),
+            response_body: String::from_utf8_lossy(&response_body).into_owned(),
+            time_elapsed: started.elapsed().as_millis() as u64,
+            size: response_body.len() as u64,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            assertions: vec![],
+        };
+        {
+            let mut exchanges = self.exchanges.lock().unwrap();
+            if exchanges.len() == EXCHANGE_LIMIT {
+                exchanges.pop_front();
+            }
+            exchanges.push_back(entry.clone());
+        }
+        (self.on_exchange)(entry);
+
+        let mut builder = hyper::Response::builder().status(status.as_u16());
+        for header in &response_headers {
+            builder = builder.header(&header.name, &header.value);
+        }
+        builder
+            .body(Full::new(response_body))
+            .unwrap_or_else(|e| into_hyper(gateway_error(e.to_string())))
+    }
+}
+
+fn forwarded(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    // Ask for identity so recorded bodies stay readable
+    !HOP_BY_HOP.contains(&name.as_str()) && name != "accept-encoding"
+}
+
+fn name_value(name: String, value: String) -> NameValue {
+    NameValue {
+        name,
+        value,
+        enabled: true,
+        secret_key: None,
+    }
+}
+
+fn gateway_error(message: String) -> MockResponse {
+    MockResponse {
+        status: 502,
+        headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+        body: serde_json::json!({ "error": message }).to_string(),
+        endpoint_id: None,
+        example: None,
+    }
+}
+
+/// The upstream base URL followed by the requested path and query
+pub fn upstream_url(upstream: &Url, path_and_query: &str) -> String {
+    format!(
+        "{}/{}",
+        upstream.as_str().trim_end_matches('/'),
+        path_and_query.trim_start_matches('/')
+    )
+}
+
+/// The path of a recorded URL below the path of `base`, if it has one
+fn relative_path<'u>(url: &'u Url, base: Option<&Url>) -> &'u str {
+    let path = url.path();
+    let prefix = base.map_or("", |b| b.path().trim_end_matches('/'));
+    match path.strip_prefix(prefix) {
+        Some(rest) if !prefix.is_empty() && rest.is_empty() => "/",
+        Some(rest) if !prefix.is_empty() && rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
+
+/// The latest recorded exchange with the same method, path and query, else an example of
+/// the endpoints inferred from the recording. Paths are relative to `base`.
+pub fn replay(
+    recording: &[HistoryEntry],
+    endpoints: &[Endpoint],
+    base: Option<&Url>,
+    method: &str,
+    path_and_query: &str,
+) -> MockResponse {
+    let exact = recording.iter().rev().find(|entry| {
+        entry.method.eq_ignore_ascii_case(method)
+            && Url::parse(&entry.url).is_ok_and(|url| {
+                let path = relative_path(&url, base);
+                let recorded = match url.query() {
+                    Some(query) => format!("{}?{}", path, query),
+                    None => path.to_string(),
+                };
+                recorded == path_and_query
+            })
+    });
+    match exact {
+        Some(entry) => MockResponse {
+            status: entry.response_status,
+            headers: entry
+                .response_headers
+                .iter()
+                .map(|h| (h.name.clone(), h.value.clone()))
+                .collect(),
+            body: entry.response_body.clone(),
+            endpoint_id: None,
+            example: None,
+        },
+        None => mock_server::respond(endpoints, method, path_and_query, &HashMap::new()),
+    }
+}
+
+/// Whether a path segment looks like an id: a number, a UUID or a long hex string
+fn is_id_segment(segment: &str) -> bool {
+    let is_number = segment.chars().all(|c| c.is_ascii_digit());
+    let is_uuid = segment.len() == 36
+        && segment.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+        && segment.matches('-').count() == 4;
+    let is_hex = segment.len() >= 12
+        && segment.chars().all(|c| c.is_ascii_hexdigit())
+        && segment.chars().any(|c| c.is_ascii_digit());
+    !segment.is_empty() && (is_number || is_uuid || is_hex)
+}
+
+/// Replaces id-like segments with `{param}`s named after the segment before them,
+/// e.g. `/users/42/orders/7` becomes `/users/{userId}/orders/{orderId}`
+pub fn path_template(path: &str) -> String {
+    let mut names: Vec<String> = Vec::new();
+    let mut previous = "";
+    let mut segments = Vec::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        if is_id_segment(segment) {
+            let base = previous.strip_suffix('s').unwrap_or(previous);
+            let base = if base.is_empty() || is_id_segment(previous) {
+                "id".to_string()
+            } else {
+                format!("{}Id", base)
+            };
+            let mut name = base.clone();
+            let mut n = 2;
+            while names.contains(&name) {
+                name = format!("{}{}", base, n);
+                n += 1;
+            }
+            segments.push(format!("{{{}}}", name));
+            names.push(name);
+        } else {
+            segments.push(segment.to_string());
+        }
+        previous = segment;
+    }
+    format!("/{}", segments.join("/"))
+}
+
+/// Groups recorded exchanges by method and inferred path template, one endpoint per
+/// group. Every distinct status becomes an example response. Paths are relative to `base`.
+pub fn endpoints_from_history(
+    entries: &[HistoryEntry],
+    base: Option<&Url>,
+    service_id: &str,
+) -> Vec<Endpoint> {
+    let mut endpoints: Vec<Endpoint> = Vec::new();
+    for entry in entries {
+        let Ok(url) = Url::parse(&entry.url) else {
+            continue;
+        };
+        let method = entry.method.to_uppercase();
+        let template = path_template(relative_path(&url, base));
+        let index = match endpoints
+            .iter()
+            .position(|e| e.method == method && e.url == template)
+        {
+            Some(index) => index,
+            None => {
+                endpoints.push(Endpoint {
+                    id: format!("e-{}", uuid::Uuid::new_v4()),
+                    service_id: service_id.to_string(),
+                    name: format!("{} {}", method, template),
+                    method,
+                    url: template,
+                    authenticated: entry
+                        .request_headers
+                        .iter()
+                        .any(|h| CREDENTIALS.contains(&h.name.to_ascii_lowercase().as_str())),
+                    auth_type: Default::default(),
+                    metadata: EndpointMetadata {
+                        version: "1.0".to_string(),
+                        last_updated: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_secs(),
+                    },
+                    params: vec![],
+                    // Credentials come from the service auth, not recorded headers
+                    headers: entry
+                        .request_headers
+                        .iter()
+                        .filter(|h| {
+                            forwarded(&h.name)
+                                && !CREDENTIALS.contains(&h.name.to_ascii_lowercase().as_str())
+                        })
+                        .cloned()
+                        .collect(),
+                    body: entry.request_body.clone(),
+                    preflight: default_preflight_config(),
+                    last_version: 0,
+                    versions: vec![],
+                    auth_profile_id: None,
+                    scripts: Scripts::default(),
+                    assertions: vec![],
+                    examples: vec![],
+                });
+                endpoints.len() - 1
+            }
+        };
+        let endpoint = &mut endpoints[index];
+        for (name, value) in url.query_pairs() {
+            if !endpoint.params.iter().any(|p| p.name == name) {
+                endpoint
+                    .params
+                    .push(name_value(name.into_owned(), value.into_owned()));
+            }
+        }
+        if !endpoint
+            .examples
+            .iter()
+            .any(|e| e.status == entry.response_status)
+        {
+            endpoint.examples.push(ExampleResponse {
+                name: entry.response_status.to_string(),
+                status: entry.response_status,
+                headers: entry
+                    .response_headers
+                    .iter()
+                    .filter(|h| h.name.eq_ignore_ascii_case("content-type"))
+                    .cloned()
+                    .collect(),
+                body: entry.response_body.clone(),
+            });
+        }
+    }
+    endpoints
+}
+
+/// Adds the endpoints recorded in `entries` to the service, skipping requests it already
+/// has. A service without `BASE_URL` gets `base`, else the origin of the first entry, so
+/// the relative endpoint URLs can be sent.
+pub fn add_to_service(service: &mut Service, entries: &[HistoryEntry], base: Option<&Url>) {
+    if service_base_url(service).is_none() {
+        let base_url = match base {
+            Some(base) => Some(base.as_str().trim_end_matches('/').to_string()),
+            None => entries
+                .iter()
+                .filter_map(|entry| Url::parse(&entry.url).ok())
+                .find(|url| matches!(url.scheme(), "http" | "https"))
+                .map(|url| url.origin().ascii_serialization()),
+        };
+        if let Some(base_url) = base_url {
+            set_base_url(service, &base_url);
+        }
+    }
+    for endpoint in endpoints_from_history(entries, base, &service.id) {
+        let exists = service
+            .endpoints
+            .iter()
+            .any(|e| e.method.eq_ignore_ascii_case(&endpoint.method) && e.url == endpoint.url);
+        if !exists {
+            service.endpoints.push(endpoint);
+        }
+    }
+}
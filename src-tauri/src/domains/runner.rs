@@ -0,0 +1,746 @@
+use crate::domains::auth::preflight;
+use crate::domains::auth::profile::{find_profile, referenced_profile_id, AuthProfile};
+use crate::domains::data_file::DataRow;
+use crate::domains::scripting;
+use crate::domains::service::workflow::{Workflow, WORKFLOW_END};
+use crate::services::RequestService;
+use crate::types::{
+    AssertionResult, AuthConfig, BodyConfig, Endpoint, Header, QResponse, RequestTab, Service,
+};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// How many requests a parallel run keeps in flight
+pub const PARALLEL_LIMIT: usize = 8;
+/// Most steps a workflow may execute, which stops `goto` loops
+pub const MAX_WORKFLOW_STEPS: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RunOptions {
+    /// Endpoints to run, in this order; every endpoint of the service when empty
+    #[serde(default)]
+    pub endpoint_ids: Vec<String>,
+    /// Defaults to the selected environment of the service
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// Parallel runs don't pass variables set by scripts from one request to the next
+    #[serde(default)]
+    pub parallel: bool,
+    /// Override the variables of the environment
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// Runs the endpoints once per row. A row's values override the environment and run
+    /// variables; variables set by scripts during the iteration override the row in turn.
+    #[serde(default)]
+    pub data: Vec<DataRow>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowOptions {
+    /// Defaults to the selected environment of the service
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// Override the variables of the environment and the workflow
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRequestResult {
+    /// Index of the data row the request ran with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iteration: Option<usize>,
+    /// Workflow step the request ran for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub step_id: Option<String>,
+    /// The step's condition did not hold, so nothing was sent
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    pub endpoint_id: String,
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    /// None when the request could not be sent
+    pub status: Option<u16>,
+    pub passed: bool,
+    pub time_elapsed: u64,
+    pub assertions: Vec<AssertionResult>,
+    pub script_errors: Vec<String>,
+    pub error: Option<String>,
+    /// What was sent and received, for exporting the run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange: Option<Box<RunExchange>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RunExchange {
+    /// RFC 3339 time the request was sent
+    pub started_at: String,
+    /// With the query and the run's variables filled in; secrets stay placeholders
+    pub url: String,
+    pub request_headers: Vec<Header>,
+    pub request_body: String,
+    pub response_status_text: String,
+    pub response_headers: Vec<Header>,
+    pub response_body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummary {
+    pub service_id: String,
+    pub environment: Option<String>,
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    #[serde(default)]
+    pub skipped: usize,
+    /// Wall-clock duration of the whole run
+    pub time_elapsed: u64,
+    pub results: Vec<RunRequestResult>,
+    /// Variables set by scripts during the run
+    pub variables: HashMap<String, String>,
+    /// One entry per data row, empty when the run had no data
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub iterations: Vec<RunIteration>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunIteration {
+    pub index: usize,
+    pub row: DataRow,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum RunProgress {
+    Started {
+        total: usize,
+    },
+    RequestStarted {
+        index: usize,
+        iteration: Option<usize>,
+        endpoint_id: String,
+        name: String,
+    },
+    RequestFinished {
+        index: usize,
+        result: RunRequestResult,
+    },
+    Finished {
+        summary: RunSummary,
+    },
+}
+
+/// Variables of an environment as the app resolves them; linked secrets stay placeholders
+pub fn environment_variables(
+    service: &Service,
+    environment: Option<&str>,
+) -> Result<HashMap<String, String>, String> {
+    let Some(name) = environment else {
+        return Ok(HashMap::new());
+    };
+    let env = service
+        .environments
+        .iter()
+        .find(|e| e.name == name)
+        .ok_or_else(|| {
+            format!(
+                "Environment '{}' not found in service '{}'",
+                name, service.name
+            )
+        })?;
+    Ok(env
+        .variables
+        .iter()
+        .filter(|v| !v.name.is_empty())
+        .map(|v| {
+            let value = match &v.secret_key {
+                Some(key) => format!("{{{{secret.{}}}}}", key),
+                None => v.value.clone(),
+            };
+            (v.name.clone(), value)
+        })
+        .collect())
+}
+
+/// The request tab the app opens for an endpoint
+pub fn endpoint_tab(
+    service: &Service,
+    endpoint: &Endpoint,
+    environment: Option<&str>,
+    variables: HashMap<String, String>,
+) -> RequestTab {
+    let has_base_url = service
+        .environments
+        .iter()
+        .any(|e| e.variables.iter().any(|v| v.name == "BASE_URL"));
+    let url = if has_base_url && endpoint.url.starts_with('/') {
+        format!("{{{{BASE_URL}}}}{}", endpoint.url)
+    } else {
+        endpoint.url.clone()
+    };
+
+    // Importers record the content type of a body as a header of the endpoint
+    let body_type = endpoint
+        .headers
+        .iter()
+        .find(|h| h.enabled && h.name.eq_ignore_ascii_case("content-type"))
+        .map(|h| h.value.clone())
+        .unwrap_or_else(|| "application/json".to_string());
+
+    RequestTab {
+        id: format!("run-{}", endpoint.id),
+        endpoint_id: Some(endpoint.id.clone()),
+        title: endpoint.name.clone(),
+        method: endpoint.method.clone(),
+        url,
+        params: endpoint.params.clone(),
+        headers: endpoint.headers.clone(),
+        body: BodyConfig {
+            r#type: body_type,
+            content: endpoint.body.clone(),
+        },
+        auth: AuthConfig::default(),
+        active_sub_tab: None,
+        service_id: Some(service.id.clone()),
+        environment: environment.map(str::to_string),
+        auth_profile_id: referenced_profile_id(service, Some(&endpoint.id)),
+        preflight: endpoint.preflight.clone(),
+        scripts: endpoint.scripts.clone(),
+        service_scripts: service.scripts.clone(),
+        assertions: endpoint.assertions.clone(),
+        variables: Some(variables),
+        is_edited: false,
+    }
+}
+
+/// Runs the chosen endpoints of a service, reporting progress as requests start and finish
+pub async fn run(
+    request_service: &RequestService<'_>,
+    service: &Service,
+    profiles: &[AuthProfile],
+    options: &RunOptions,
+    on_progress: &(dyn Fn(RunProgress) + Send + Sync),
+) -> Result<RunSummary, String> {
+    let endpoints: Vec<&Endpoint> = if options.endpoint_ids.is_empty() {
+        service.endpoints.iter().collect()
+    } else {
+        options
+            .endpoint_ids
+            .iter()
+            .map(|id| {
+                service
+                    .endpoints
+                    .iter()
+                    .find(|e| &e.id == id)
+                    .ok_or_else(|| format!("Endpoint '{}' not found in service", id))
+            })
+            .collect::<Result<_, _>>()?
+    };
+    let environment = options
+        .environment
+        .clone()
+        .or_else(|| service.selected_environment.clone());
+    let mut variables = environment_variables(service, environment.as_deref())?;
+    variables.extend(options.variables.clone());
+
+    // A run without data is a single iteration without a row
+    let rows: Vec<Option<(usize, &DataRow)>> = if options.data.is_empty() {
+        vec![None]
+    } else {
+        options.data.iter().enumerate().map(Some).collect()
+    };
+    let iteration_variables = |row: Option<(usize, &DataRow)>| {
+        let mut iteration_variables = variables.clone();
+        if let Some((_, row)) = row {
+            iteration_variables.extend(row.clone());
+        }
+        iteration_variables
+    };
+
+    let started = Instant::now();
+    on_progress(RunProgress::Started {
+        total: endpoints.len() * rows.len(),
+    });
+
+    let mut captured = HashMap::new();
+    let results: Vec<RunRequestResult> = if options.parallel {
+        let requests: Vec<_> = rows
+            .iter()
+            .flat_map(|row| endpoints.iter().map(move |endpoint| (*row, *endpoint)))
+            .enumerate()
+            .map(|(index, (row, endpoint))| {
+                run_endpoint(
+                    request_service,
+                    service,
+                    profiles,
+                    endpoint,
+                    environment.as_deref(),
+                    iteration_variables(row),
+                    index,
+                    row.map(|(iteration, _)| iteration),
+                    on_progress,
+                )
+            })
+            .collect();
+        let outcomes: Vec<_> = stream::iter(requests)
+            .buffered(PARALLEL_LIMIT)
+            .collect()
+            .await;
+        outcomes
+            .into_iter()
+            .map(|(result, set)| {
+                captured.extend(set);
+                result
+            })
+            .collect()
+    } else {
+        let mut results = Vec::new();
+        for row in &rows {
+            // Variables set by scripts carry over to later requests of the same iteration,
+            // taking precedence over the values of the row
+            let mut iteration_captured = HashMap::new();
+            for endpoint in &endpoints {
+                let mut request_variables = iteration_variables(*row);
+                request_variables.extend(iteration_captured.clone());
+                let (result, set) = run_endpoint(
+                    request_service,
+                    service,
+                    profiles,
+                    endpoint,
+                    environment.as_deref(),
+                    request_variables,
+                    results.len(),
+                    row.map(|(iteration, _)| iteration),
+                    on_progress,
+                )
+                .await;
+                iteration_captured.extend(set);
+                results.push(result);
+            }
+            captured.extend(iteration_captured);
+        }
+        results
+    };
+
+    let iterations = options
+        .data
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let ran: Vec<_> = results
+                .iter()
+                .filter(|r| r.iteration == Some(index))
+                .collect();
+            let passed = ran.iter().filter(|r| r.passed).count();
+            RunIteration {
+                index,
+                row: row.clone(),
+                passed,
+                failed: ran.len() - passed,
+            }
+        })
+        .collect();
+
+    let summary = summarize(service, environment, started, results, captured, iterations);
+    on_progress(RunProgress::Finished {
+        summary: summary.clone(),
+    });
+    Ok(summary)
+}
+
+fn summarize(
+    service: &Service,
+    environment: Option<String>,
+    started: Instant,
+    results: Vec<RunRequestResult>,
+    variables: HashMap<String, String>,
+    iterations: Vec<RunIteration>,
+) -> RunSummary {
+    let passed = results.iter().filter(|r| r.passed).count();
+    let skipped = results.iter().filter(|r| r.skipped).count();
+    RunSummary {
+        service_id: service.id.clone(),
+        environment,
+        total: results.len(),
+        passed,
+        failed: results.len() - passed - skipped,
+        skipped,
+        time_elapsed: started.elapsed().as_millis() as u64,
+        results,
+        variables,
+        iterations,
+    }
+}
+
+/// Runs the steps of a workflow, following its conditions and branches. Stops at the
+/// first failed step unless the step continues on failure.
+pub async fn run_workflow(
+    request_service: &RequestService<'_>,
+    service: &Service,
+    profiles: &[AuthProfile],
+    workflow: &Workflow,
+    options: &WorkflowOptions,
+    on_progress: &(dyn Fn(RunProgress) + Send + Sync),
+) -> Result<RunSummary, String> {
+    let endpoint_ids: Vec<&str> = service.endpoints.iter().map(|e| e.id.as_str()).collect();
+    workflow.validate(&endpoint_ids)?;
+    let environment = options
+        .environment
+        .clone()
+        .or_else(|| service.selected_environment.clone());
+    let mut variables = environment_variables(service, environment.as_deref())?;
+    variables.extend(workflow.variables.clone());
+    variables.extend(options.variables.clone());
+
+    let started = Instant::now();
+    on_progress(RunProgress::Started {
+        total: workflow.steps.len(),
+    });
+
+    // Variables set by scripts and extractions, and what each step did, for later steps
+    let mut captured: HashMap<String, String> = HashMap::new();
+    let mut steps = serde_json::Map::new();
+    let mut results = Vec::new();
+    let mut position = 0;
+    while let Some(step) = workflow.steps.get(position) {
+        if results.len() == MAX_WORKFLOW_STEPS {
+            return Err(format!(
+                "Workflow '{}' ran more than {} steps; check its branches for loops",
+                workflow.id, MAX_WORKFLOW_STEPS
+            ));
+        }
+        let index = results.len();
+        let endpoint = service
+            .endpoints
+            .iter()
+            .find(|e| e.id == step.endpoint_id)
+            .expect("validated");
+        let mut step_variables = variables.clone();
+        step_variables.extend(captured.clone());
+        step_variables.extend(step.variables.clone());
+
+        let condition = match &step.when {
+            Some(when) => {
+                scripting::eval_condition(when, &step_variables, &Value::Object(steps.clone()))
+            }
+            None => Ok(true),
+        };
+        let (mut result, response) = match condition {
+            Ok(true) => {
+                on_progress(RunProgress::RequestStarted {
+                    index,
+                    iteration: None,
+                    endpoint_id: endpoint.id.clone(),
+                    name: endpoint.name.clone(),
+                });
+                let run = send_endpoint(
+                    request_service,
+                    service,
+                    profiles,
+                    endpoint,
+                    environment.as_deref(),
+                    step_variables.clone(),
+                    None,
+                )
+                .await;
+                captured.extend(run.variables);
+                (run.result, run.response)
+            }
+            Ok(false) => {
+                let mut result = empty_result(endpoint, None);
+                result.skipped = true;
+                (result, None)
+            }
+            Err(e) => {
+                let mut result = empty_result(endpoint, None);
+                result.error = Some(e);
+                (result, None)
+            }
+        };
+        result.step_id = Some(step.id.clone());
+
+        if let Some(response) = &response {
+            for extraction in &step.extract {
+                match preflight::extract(extraction, response) {
+                    Ok(value) => {
+                        captured.insert(extraction.variable.clone(), value);
+                    }
+                    Err(e) => {
+                        result.passed = false;
+                        result.error.get_or_insert(format!(
+                            "Failed to extract '{}': {}",
+                            extraction.variable, e
+                        ));
+                    }
+                }
+            }
+        }
+        steps.insert(step.id.clone(), step_value(&result, response.as_ref()));
+
+        let mut next = position + 1;
+        if result.passed {
+            let mut branch_variables = step_variables;
+            branch_variables.extend(captured.clone());
+            let steps_value = Value::Object(steps.clone());
+            for branch in &step.next {
+                let holds = match &branch.when {
+                    Some(when) => scripting::eval_condition(when, &branch_variables, &steps_value),
+                    None => Ok(true),
+                };
+                match holds {
+                    Ok(false) => continue,
+                    Ok(true) if branch.goto == WORKFLOW_END => next = workflow.steps.len(),
+                    Ok(true) => {
+                        next = workflow
+                            .steps
+                            .iter()
+                            .position(|s| s.id == branch.goto)
+                            .expect("validated")
+                    }
+                    Err(e) => {
+                        result.passed = false;
+                        result.error.get_or_insert(e);
+                    }
+                }
+                break;
+            }
+        }
+
+        let stop = !result.passed && !result.skipped && !step.continue_on_failure;
+        on_progress(RunProgress::RequestFinished {
+            index,
+            result: result.clone(),
+        });
+        results.push(result);
+        if stop {
+            break;
+        }
+        position = next;
+    }
+
+    let summary = summarize(service, environment, started, results, captured, vec![]);
+    on_progress(RunProgress::Finished {
+        summary: summary.clone(),
+    });
+    Ok(summary)
+}
+
+/// What conditions see of a step as `steps.<id>`
+fn step_value(result: &RunRequestResult, response: Option<&QResponse>) -> Value {
+    let headers: serde_json::Map<String, Value> = response
+        .iter()
+        .flat_map(|r| r.headers.iter())
+        .map(|h| (h.name.to_ascii_lowercase(), Value::String(h.value.clone())))
+        .collect();
+    json!({
+        "status": result.status,
+        "passed": result.passed,
+        "skipped": result.skipped,
+        "timeElapsed": result.time_elapsed,
+        "error": result.error,
+        "headers": headers,
+        "body": response.map(|r| r.body.clone()),
+        "json": response.and_then(|r| serde_json::from_str::<Value>(&r.body).ok()),
+    })
+}
+
+/// Sends one endpoint; also returns the variables its scripts set
+#[allow(clippy::too_many_arguments)]
+async fn run_endpoint(
+    request_service: &RequestService<'_>,
+    service: &Service,
+    profiles: &[AuthProfile],
+    endpoint: &Endpoint,
+    environment: Option<&str>,
+    variables: HashMap<String, String>,
+    index: usize,
+    iteration: Option<usize>,
+    on_progress: &(dyn Fn(RunProgress) + Send + Sync),
+) -> (RunRequestResult, HashMap<String, String>) {
+    on_progress(RunProgress::RequestStarted {
+        index,
+        iteration,
+        endpoint_id: endpoint.id.clone(),
+        name: endpoint.name.clone(),
+    });
+    let run = send_endpoint(
+        request_service,
+        service,
+        profiles,
+        endpoint,
+        environment,
+        variables,
+        iteration,
+    )
+    .await;
+    on_progress(RunProgress::RequestFinished {
+        index,
+        result: run.result.clone(),
+    });
+    (run.result, run.variables)
+}
+
+/// Fills in what the service and its auth profile define for a tab
+pub fn with_defaults(
+    service: &Service,
+    profiles: &[AuthProfile],
+    mut tab: RequestTab,
+) -> Result<RequestTab, String> {
+    let profile = match tab.auth_profile_id.as_deref() {
+        Some(id) => Some(
+            find_profile(profiles, id).ok_or_else(|| format!("Auth profile '{}' not found", id))?,
+        ),
+        None => None,
+    };
+    service.apply_defaults(&mut tab, profile);
+    Ok(tab)
+}
+
+struct EndpointRun {
+    result: RunRequestResult,
+    /// Variables set by the scripts of the request
+    variables: HashMap<String, String>,
+    /// None when the request could not be sent
+    response: Option<QResponse>,
+}
+
+fn empty_result(endpoint: &Endpoint, iteration: Option<usize>) -> RunRequestResult {
+    RunRequestResult {
+        iteration,
+        step_id: None,
+        skipped: false,
+        endpoint_id: endpoint.id.clone(),
+        name: endpoint.name.clone(),
+        method: endpoint.method.clone(),
+        url: endpoint.url.clone(),
+        status: None,
+        passed: false,
+        time_elapsed: 0,
+        assertions: vec![],
+        script_errors: vec![],
+        error: None,
+        exchange: None,
+    }
+}
+
+/// The request of a tab before its scripts run, without looking secrets up
+fn sent_request(tab: &RequestTab) -> RunExchange {
+    let variables = tab.variables.clone().unwrap_or_default();
+    let fill = |text: &str| fill_variables(text, &variables);
+    let query: Vec<(String, String)> = tab
+        .params
+        .iter()
+        .filter(|p| p.enabled)
+        .map(|p| (fill(&p.name), fill(&p.value)))
+        .collect();
+    let url = fill(&tab.url);
+    let url = match url::Url::parse(&url) {
+        Ok(mut parsed) if !query.is_empty() => {
+            parsed.query_pairs_mut().extend_pairs(&query);
+            parsed.to_string()
+        }
+        _ => url,
+    };
+    RunExchange {
+        started_at: chrono::Utc::now().to_rfc3339(),
+        url,
+        request_headers: tab
+            .headers
+            .iter()
+            .filter(|h| h.enabled)
+            .map(|h| Header {
+                name: fill(&h.name),
+                value: fill(&h.value),
+                ..h.clone()
+            })
+            .collect(),
+        request_body: fill(&tab.body.content),
+        ..RunExchange::default()
+    }
+}
+
+fn fill_variables(text: &str, variables: &HashMap<String, String>) -> String {
+    let re = regex::Regex::new(r"\{\{([^}]+)\}\}").expect("Invalid regex");
+    re.replace_all(text, |caps: &regex::Captures| {
+        variables
+            .get(caps[1].trim())
+            .cloned()
+            .unwrap_or_else(|| caps[0].to_string())
+    })
+    .into_owned()
+}
+
+async fn send_endpoint(
+    request_service: &RequestService<'_>,
+    service: &Service,
+    profiles: &[AuthProfile],
+    endpoint: &Endpoint,
+    environment: Option<&str>,
+    variables: HashMap<String, String>,
+    iteration: Option<usize>,
+) -> EndpointRun {
+    let tab = endpoint_tab(service, endpoint, environment, variables);
+    let mut run = EndpointRun {
+        result: RunRequestResult {
+            url: tab.url.clone(),
+            ..empty_result(endpoint, iteration)
+        },
+        variables: HashMap::new(),
+        response: None,
+    };
+    let tab = match with_defaults(service, profiles, tab) {
+        Ok(tab) => tab,
+        Err(e) => {
+            run.result.error = Some(e);
+            return run;
+        }
+    };
+
+    let mut exchange = sent_request(&tab);
+    let started = Instant::now();
+    let result = &mut run.result;
+    match request_service.send_request(tab).await {
+        Ok(mut response) => {
+            exchange.response_status_text = response.status_text.clone();
+            exchange.response_headers = response.headers.clone();
+            exchange.response_body = response.body.clone();
+            let report = response.scripts.take().unwrap_or_default();
+            result.status = Some(response.status);
+            result.time_elapsed = response.time_elapsed;
+            result.script_errors = report.errors;
+            result.error = response.error.clone();
+            result.passed = result.error.is_none()
+                && result.script_errors.is_empty()
+                && if response.assertions.is_empty() {
+                    response.status < 400
+                } else {
+                    response.assertions.iter().all(|a| a.passed)
+                };
+            result.assertions = std::mem::take(&mut response.assertions);
+            run.variables = report.variables;
+            run.response = Some(response);
+        }
+        Err(e) => {
+            result.time_elapsed = started.elapsed().as_millis() as u64;
+            result.error = Some(e);
+        }
+    }
+    result.exchange = Some(Box::new(exchange));
+    run
+}
@@ -0,0 +1,204 @@
+use crate::types::{QResponse, RequestTab};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::serde::{from_dynamic, to_dynamic};
+use rhai::{Dynamic, Engine, EvalAltResult, Map, Scope};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Upper bound on the work a single script may do before it is aborted
+const MAX_OPERATIONS: u64 = 1_000_000;
+const MAX_CALL_LEVELS: usize = 64;
+const MAX_STRING_SIZE: usize = 10 * 1024 * 1024;
+const MAX_COLLECTION_SIZE: usize = 100_000;
+
+/// Rhai scripts run around a request. Empty scripts are skipped.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Scripts {
+    /// Runs after variables are resolved; may change `request` and `variables`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pre_request: String,
+    /// Runs once the response arrived; may read `request` and `response` and set `variables`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub post_response: String,
+}
+
+impl Scripts {
+    pub fn is_empty(&self) -> bool {
+        self.pre_request.trim().is_empty() && self.post_response.trim().is_empty()
+    }
+}
+
+/// What the scripts of one request printed, failed with and set
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptReport {
+    pub logs: Vec<String>,
+    pub errors: Vec<String>,
+    /// Variables set or changed by the scripts, for the caller to persist
+    pub variables: HashMap<String, String>,
+}
+
+/// Runs a pre-request script against the resolved tab. On error the tab is left untouched.
+pub fn run_pre_request(label: &str, script: &str, tab: &mut RequestTab, report: &mut ScriptReport) {
+    if script.trim().is_empty() {
+        return;
+    }
+    let result = (|| -> Result<(), String> {
+        let mut scope = Scope::new();
+        scope.push("request", to_dynamic(&*tab).map_err(|e| e.to_string())?);
+        scope.push("variables", variables_map(tab, report));
+        run(script, &mut scope, report)?;
+
+        let request = scope
+            .get_value::<Dynamic>("request")
+            .ok_or_else(|| "`request` was removed".to_string())?;
+        let mut updated: RequestTab =
+            from_dynamic(&request).map_err(|e| format!("Invalid `request`: {}", e))?;
+        let variables = collect_variables(&scope, tab, report)?;
+        updated.variables = Some(variables);
+        *tab = updated;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        report
+            .errors
+            .push(format!("{} pre-request script: {}", label, e));
+    }
+}
+
+/// Runs a post-response script; only the variables it sets are kept
+pub fn run_post_response(
+    label: &str,
+    script: &str,
+    tab: &RequestTab,
+    response: &QResponse,
+    report: &mut ScriptReport,
+) {
+    if script.trim().is_empty() {
+        return;
+    }
+    let result = (|| -> Result<(), String> {
+        let mut response_value = to_dynamic(response).map_err(|e| e.to_string())?;
+        if let Some(mut map) = response_value.write_lock::<Map>() {
+            // Parsed body for convenience, unit when the body is not JSON
+            let json = serde_json::from_str::<serde_json::Value>(&response.body)
+                .ok()
+                .and_then(|value| to_dynamic(value).ok())
+                .unwrap_or(Dynamic::UNIT);
+            map.insert("json".into(), json);
+        }
+
+        let mut scope = Scope::new();
+        scope.push("request", to_dynamic(tab).map_err(|e| e.to_string())?);
+        scope.push("response", response_value);
+        scope.push("variables", variables_map(tab, report));
+        run(script, &mut scope, report)?;
+        collect_variables(&scope, tab, report)?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        report
+            .errors
+            .push(format!("{} post-response script: {}", label, e));
+    }
+}
+
+/// Evaluates a workflow condition with `variables` and the results of earlier steps as `steps`
+pub fn eval_condition(
+    expression: &str,
+    variables: &HashMap<String, String>,
+    steps: &serde_json::Value,
+) -> Result<bool, String> {
+    let variables: Map = variables
+        .iter()
+        .map(|(name, value)| (name.as_str().into(), value.clone().into()))
+        .collect();
+    let mut scope = Scope::new();
+    scope.push("variables", variables);
+    scope.push("steps", to_dynamic(steps).map_err(|e| e.to_string())?);
+    sandboxed_engine(Arc::new(Mutex::new(Vec::new())))
+        .eval_expression_with_scope::<bool>(&mut scope, expression)
+        .map_err(|e| format!("Condition `{}`: {}", expression, e))
+}
+
+fn run(script: &str, scope: &mut Scope, report: &mut ScriptReport) -> Result<(), String> {
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let result = sandboxed_engine(logs.clone())
+        .run_with_scope(scope, script)
+        .map_err(|e| e.to_string());
+    report.logs.extend(logs.lock().unwrap().drain(..));
+    result
+}
+
+/// An engine without module imports or any host access, with bounded resources.
+/// `print` and `debug` are captured as logs.
+fn sandboxed_engine(logs: Arc<Mutex<Vec<String>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_COLLECTION_SIZE);
+    engine.set_max_map_size(MAX_COLLECTION_SIZE);
+
+    let print_logs = logs.clone();
+    engine.on_print(move |text| print_logs.lock().unwrap().push(text.to_string()));
+    engine.on_debug(move |text, _source, pos| {
+        logs.lock()
+            .unwrap()
+            .push(format!("[debug {}] {}", pos, text))
+    });
+
+    engine.register_fn("parse_json", parse_json);
+    engine.register_fn("to_json", to_json);
+    engine
+}
+
+fn parse_json(text: &str) -> Result<Dynamic, Box<EvalAltResult>> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    to_dynamic(value)
+}
+
+fn to_json(value: Dynamic) -> Result<String, Box<EvalAltResult>> {
+    let value: serde_json::Value = from_dynamic(&value)?;
+    Ok(value.to_string())
+}
+
+/// The tab's variables with those set by earlier scripts on top
+fn variables_map(tab: &RequestTab, report: &ScriptReport) -> Map {
+    tab.variables
+        .iter()
+        .flatten()
+        .chain(report.variables.iter())
+        .map(|(name, value)| (name.as_str().into(), value.clone().into()))
+        .collect()
+}
+
+/// Reads `variables` back as strings and records the ones the script changed
+fn collect_variables(
+    scope: &Scope,
+    tab: &RequestTab,
+    report: &mut ScriptReport,
+) -> Result<HashMap<String, String>, String> {
+    let before = variables_map(tab, report);
+    let map = scope
+        .get_value::<Map>("variables")
+        .ok_or_else(|| "`variables` must stay a map".to_string())?;
+
+    let mut variables = HashMap::new();
+    for (name, value) in map {
+        let value = match value.clone().into_immutable_string() {
+            Ok(s) => s.to_string(),
+            Err(_) => value.to_string(),
+        };
+        if before.get(name.as_str()).map(|old| old.to_string()) != Some(value.clone()) {
+            report.variables.insert(name.to_string(), value.clone());
+        }
+        variables.insert(name.to_string(), value);
+    }
+    Ok(variables)
+}
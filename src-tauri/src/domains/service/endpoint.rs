@@ -1,3 +1,6 @@
+use crate::domains::assertions::Assertion;
+use crate::domains::auth::AuthType;
+use crate::domains::scripting::Scripts;
 use crate::types::{Header, Param};
 use serde::{Deserialize, Serialize};
 
@@ -30,6 +33,64 @@ pub struct PreflightConfig {
     pub token_key: String,
     #[serde(default)]
     pub token_header: Option<String>,
+    /// Keep the acquired token in memory only, never in the persisted token cache
+    #[serde(default)]
+    pub secret_token: bool,
+    /// Refresh the cached token in the background this many seconds before it expires
+    #[serde(default)]
+    pub refresh_before_expiry: Option<u64>,
+    /// Requests run in order before the token request; their extractions feed later templates
+    #[serde(default)]
+    pub steps: Vec<PreflightStep>,
+    /// What the final response yields as the credential
+    #[serde(default)]
+    pub mode: PreflightMode,
+}
+
+/// `token` reads `tokenKey` from a JSON body; `cookies` keeps the session cookies the
+/// login response sets and sends them as a `Cookie` header
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PreflightMode {
+    #[default]
+    Token,
+    Cookies,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightStep {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default = "default_body_type")]
+    pub body_type: String,
+    #[serde(default)]
+    pub body_params: Vec<Param>,
+    #[serde(default)]
+    pub headers: Vec<Header>,
+    #[serde(default)]
+    pub extractions: Vec<PreflightExtraction>,
+}
+
+/// Stores a value from a step response as `{{variable}}` for the following steps
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightExtraction {
+    pub variable: String,
+    /// body (JSON dot path), header or cookie
+    #[serde(default = "default_extraction_source")]
+    pub source: String,
+    pub path: String,
+}
+
+fn default_extraction_source() -> String {
+    "body".to_string()
 }
 
 fn default_method() -> String {
@@ -64,8 +125,8 @@ pub struct RequestConfig {
     pub url: String,
     #[serde(default)]
     pub authenticated: bool,
-    #[serde(default = "default_auth_type")]
-    pub auth_type: String,
+    #[serde(default)]
+    pub auth_type: AuthType,
     #[serde(default)]
     pub params: Vec<Param>,
     #[serde(default)]
@@ -74,13 +135,14 @@ pub struct RequestConfig {
     pub body: String,
     #[serde(default = "default_preflight_config")]
     pub preflight: PreflightConfig,
+    #[serde(default, skip_serializing_if = "Scripts::is_empty")]
+    pub scripts: Scripts,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<Assertion>,
 }
 
-fn default_auth_type() -> String {
-    "none".to_string()
-}
-
-fn default_preflight_config() -> PreflightConfig {
+/// Preflight of endpoints that don't define their own
+pub fn default_preflight_config() -> PreflightConfig {
     PreflightConfig {
         enabled: false,
         method: "POST".to_string(),
@@ -95,6 +157,10 @@ fn default_preflight_config() -> PreflightConfig {
         cache_duration_unit: "seconds".to_string(),
         token_key: "".to_string(),
         token_header: None,
+        secret_token: false,
+        refresh_before_expiry: None,
+        steps: vec![],
+        mode: PreflightMode::Token,
     }
 }
 
@@ -117,8 +183,8 @@ pub struct Endpoint {
     pub url: String,
     #[serde(default)]
     pub authenticated: bool,
-    #[serde(default = "default_auth_type")]
-    pub auth_type: String,
+    #[serde(default)]
+    pub auth_type: AuthType,
     #[serde(default = "default_metadata")]
     pub metadata: EndpointMetadata,
     #[serde(default)]
@@ -133,6 +199,27 @@ pub struct Endpoint {
     pub last_version: i32,
     #[serde(default)]
     pub versions: Vec<EndpointVersion>,
+    /// Shared auth profile overriding the one of the service
+    #[serde(default)]
+    pub auth_profile_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Scripts::is_empty")]
+    pub scripts: Scripts,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<Assertion>,
+    /// Canned responses, served by the mock server
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ExampleResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExampleResponse {
+    pub name: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headers: Vec<Header>,
+    #[serde(default)]
+    pub body: String,
 }
 
 fn default_metadata() -> EndpointMetadata {
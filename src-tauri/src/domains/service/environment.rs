@@ -1,3 +1,5 @@
+use crate::domains::auth::AuthConfig;
+use crate::domains::service::endpoint::PreflightConfig;
 use crate::types::Variable;
 use serde::{Deserialize, Serialize};
 
@@ -8,4 +10,10 @@ pub struct EnvironmentConfig {
     #[serde(default)]
     pub is_unsafe: bool,
     pub variables: Vec<Variable>,
+    /// Replaces the service (or profile) auth while this environment is active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+    /// Replaces the service (or profile) preflight while this environment is active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preflight: Option<PreflightConfig>,
 }
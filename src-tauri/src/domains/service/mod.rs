@@ -1,3 +1,4 @@
 pub mod endpoint;
 pub mod environment;
 pub mod service;
+pub mod workflow;
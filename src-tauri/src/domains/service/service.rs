@@ -1,7 +1,13 @@
-use super::endpoint::{Endpoint, EndpointStub, EndpointVersion, PreflightConfig, RequestConfig};
+use super::endpoint::{
+    Endpoint, EndpointStub, EndpointVersion, PreflightConfig, PreflightMode, RequestConfig,
+};
 use super::environment::EnvironmentConfig;
+use super::workflow::Workflow;
+use crate::domains::auth::profile::AuthProfile;
 use crate::domains::auth::{AuthConfig, AuthType};
+use crate::domains::scripting::Scripts;
 use crate::io::FileSystem;
+use crate::types::RequestTab;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
@@ -23,21 +29,80 @@ pub struct Service {
     pub directory: String,
     pub selected_environment: Option<String>,
     pub git_url: Option<String>,
+    /// Shared auth profile used instead of `auth` and `preflight`
+    #[serde(default)]
+    pub auth_profile_id: Option<String>,
+    /// Scripts run around every request of the service
+    #[serde(default, skip_serializing_if = "Scripts::is_empty")]
+    pub scripts: Scripts,
 }
 
-fn default_auth() -> AuthConfig {
-    AuthConfig {
-        r#type: "none".to_string(),
-        active: true,
-        basic_user: "".to_string(),
-        basic_pass: "".to_string(),
-        bearer_token: "".to_string(),
-        api_key_name: "".to_string(),
-        api_key_value: "".to_string(),
-        api_key_location: "header".to_string(),
+impl Service {
+    /// Auth and preflight a request in `environment` inherits: the environment's overrides
+    /// win over the shared profile, which wins over the service's own config.
+    pub fn effective_auth(
+        &self,
+        environment: Option<&str>,
+        profile: Option<&AuthProfile>,
+    ) -> (AuthConfig, PreflightConfig) {
+        let (mut auth, mut preflight) = match profile {
+            Some(p) => (p.auth.clone(), p.preflight.clone()),
+            None => (self.auth.clone(), self.preflight.clone()),
+        };
+        if let Some(env) =
+            environment.and_then(|name| self.environments.iter().find(|e| e.name == name))
+        {
+            if let Some(env_auth) = &env.auth {
+                auth = env_auth.clone();
+            }
+            if let Some(env_preflight) = &env.preflight {
+                preflight = env_preflight.clone();
+            }
+        }
+        (auth, preflight)
+    }
+
+    /// Fills in what a request of this service leaves unset: the environment, the scripts
+    /// and assertions of its endpoint, and the auth and preflight it inherits. Endpoints
+    /// marked unauthenticated inherit no auth.
+    pub fn apply_defaults(&self, tab: &mut RequestTab, profile: Option<&AuthProfile>) {
+        if tab.environment.is_none() {
+            tab.environment = self.selected_environment.clone();
+        }
+        if tab.service_scripts.is_empty() {
+            tab.service_scripts = self.scripts.clone();
+        }
+        if let Some(endpoint) = self
+            .endpoints
+            .iter()
+            .find(|e| Some(&e.id) == tab.endpoint_id.as_ref())
+        {
+            if tab.scripts.is_empty() {
+                tab.scripts = endpoint.scripts.clone();
+            }
+            if tab.assertions.is_empty() {
+                tab.assertions = endpoint.assertions.clone();
+            }
+            // The app opens endpoint tabs with the service's auth type and no settings, and
+            // saved tabs may hold a copy of the default; neither may hide an environment
+            // override. Auth set on the tab itself is kept.
+            let inherited = profile.map_or(&self.auth, |p| &p.auth);
+            if tab.auth.is_blank() || tab.auth == self.auth || tab.auth == *inherited {
+                tab.auth = AuthConfig::default();
+            }
+            if !endpoint.authenticated {
+                return;
+            }
+        }
+        let (auth, preflight) = self.effective_auth(tab.environment.as_deref(), profile);
+        tab.inherit_auth(auth, preflight);
     }
 }
 
+fn default_auth() -> AuthConfig {
+    AuthConfig::default()
+}
+
 fn default_preflight() -> PreflightConfig {
     PreflightConfig {
         enabled: false,
@@ -53,6 +118,10 @@ fn default_preflight() -> PreflightConfig {
         cache_duration_unit: "seconds".to_string(),
         token_key: "".to_string(),
         token_header: None,
+        secret_token: false,
+        refresh_before_expiry: None,
+        steps: vec![],
+        mode: PreflightMode::Token,
     }
 }
 
@@ -71,6 +140,12 @@ pub struct ServiceFile {
     pub directory: String,
     pub selected_environment: Option<String>,
     pub git_url: Option<String>,
+    /// Shared auth profile used instead of `auth` and `preflight`
+    #[serde(default)]
+    pub auth_profile_id: Option<String>,
+    /// Scripts run around every request of the service
+    #[serde(default, skip_serializing_if = "Scripts::is_empty")]
+    pub scripts: Scripts,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -150,7 +225,7 @@ impl<'a> ServiceDomain<'a> {
                     if let Some(endpoint) = legacy_endpoints.get(&stub.id) {
                         endpoints.push(endpoint.clone());
                     } else {
-                        println!("Warning: Failed to load endpoint {}", stub.id);
+                        eprintln!("Warning: Failed to load endpoint {}", stub.id);
                     }
                 }
             }
@@ -175,6 +250,8 @@ impl<'a> ServiceDomain<'a> {
             directory: service_file.directory,
             selected_environment: service_file.selected_environment,
             git_url: service_file.git_url,
+            auth_profile_id: service_file.auth_profile_id,
+            scripts: service_file.scripts,
         })
     }
 
@@ -212,6 +289,8 @@ impl<'a> ServiceDomain<'a> {
                 headers: endpoint.headers.clone(),
                 body: endpoint.body.clone(),
                 preflight: endpoint.preflight.clone(),
+                scripts: endpoint.scripts.clone(),
+                assertions: endpoint.assertions.clone(),
             };
 
             let should_create_new_version = match endpoint.versions.last() {
@@ -268,6 +347,8 @@ impl<'a> ServiceDomain<'a> {
             directory: service.directory.clone(),
             selected_environment: service.selected_environment.clone(),
             git_url: service.git_url.clone(),
+            auth_profile_id: service.auth_profile_id.clone(),
+            scripts: service.scripts.clone(),
         };
 
         let path = dir.join("service.yaml");
@@ -283,6 +364,59 @@ impl<'a> ServiceDomain<'a> {
         Ok(())
     }
 
+    // Workflows
+
+    /// Workflows of a service directory, sorted by id
+    pub fn load_workflows(&self, directory: &str) -> Result<Vec<Workflow>, String> {
+        let dir = PathBuf::from(directory).join("workflows");
+        if !self.fs.exists(&dir) {
+            return Ok(Vec::new());
+        }
+        let mut workflows = Vec::new();
+        for path in self.fs.read_dir(&dir)? {
+            let is_yaml = matches!(
+                path.extension().and_then(|e| e.to_str()),
+                Some("yaml") | Some("yml")
+            );
+            if !is_yaml {
+                continue;
+            }
+            let content = self.fs.read_to_string(&path)?;
+            let workflow: Workflow = serde_yaml::from_str(&content)
+                .map_err(|e| format!("Invalid workflow {}: {}", path.display(), e))?;
+            workflows.push(workflow);
+        }
+        workflows.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(workflows)
+    }
+
+    pub fn save_workflow(&self, directory: &str, workflow: &Workflow) -> Result<(), String> {
+        let valid_id = !workflow.id.is_empty()
+            && workflow
+                .id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_id {
+            return Err(format!(
+                "Workflow id '{}' may only contain letters, digits, '-' and '_'",
+                workflow.id
+            ));
+        }
+        let dir = PathBuf::from(directory).join("workflows");
+        if !self.fs.exists(&dir) {
+            self.fs.create_dir_all(&dir)?;
+        }
+        let content = serde_yaml::to_string(workflow).map_err(|e| e.to_string())?;
+        self.fs
+            .write(&dir.join(format!("{}.yaml", workflow.id)), &content)?;
+
+        if crate::domains::git::is_git_repo(directory) {
+            let msg = format!("Update workflow {}", workflow.name);
+            let _ = crate::domains::git::commit_changes(directory, &msg);
+        }
+        Ok(())
+    }
+
     // Collections
 
     pub fn get_collections_path<R: tauri::Runtime>(
@@ -0,0 +1,86 @@
+use crate::domains::service::endpoint::PreflightExtraction;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// `goto` target that finishes a workflow
+pub const WORKFLOW_END: &str = "end";
+
+/// A chain of endpoint calls, stored as `workflows/<id>.yaml` in the service directory
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Workflow {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Variables every step starts with, on top of the environment
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowStep {
+    /// Unique within the workflow; conditions and `goto` refer to steps by id
+    pub id: String,
+    pub endpoint_id: String,
+    /// Variables of this step only
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// Values of the response stored as variables for the following steps
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extract: Vec<PreflightExtraction>,
+    /// Rhai expression over `variables` and `steps`; the step is skipped unless it holds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Where to go after the step; the first branch that holds wins, else the next step
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub next: Vec<WorkflowBranch>,
+    /// Keep going when the step fails instead of stopping the workflow
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub continue_on_failure: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowBranch {
+    /// Rhai expression like `when` of a step; a branch without one always holds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Id of the step to continue with, or `end`
+    pub goto: String,
+}
+
+impl Workflow {
+    /// Checks step ids, endpoints and `goto` targets before anything is sent
+    pub fn validate(&self, endpoint_ids: &[&str]) -> Result<(), String> {
+        let mut seen = Vec::new();
+        for step in &self.steps {
+            if step.id.is_empty() || step.id == WORKFLOW_END || seen.contains(&step.id.as_str()) {
+                return Err(format!(
+                    "Workflow '{}': step ids must be unique, not empty and not 'end', got '{}'",
+                    self.id, step.id
+                ));
+            }
+            seen.push(step.id.as_str());
+            if !endpoint_ids.contains(&step.endpoint_id.as_str()) {
+                return Err(format!(
+                    "Workflow '{}': step '{}' refers to unknown endpoint '{}'",
+                    self.id, step.id, step.endpoint_id
+                ));
+            }
+        }
+        for step in &self.steps {
+            for branch in &step.next {
+                if branch.goto != WORKFLOW_END && !seen.contains(&branch.goto.as_str()) {
+                    return Err(format!(
+                        "Workflow '{}': step '{}' goes to unknown step '{}'",
+                        self.id, step.id, branch.goto
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::domains::auth::profile::AuthProfile;
 use crate::domains::service::service::ServiceStub;
 use crate::io::FileSystem;
 use serde::{Deserialize, Serialize};
@@ -9,6 +10,8 @@ use tauri::{AppHandle, Manager, Runtime};
 pub struct UserSettings {
     pub theme: String, // "light", "dark", "system"
     pub services: Vec<ServiceStub>,
+    #[serde(default)]
+    pub auth_profiles: Vec<AuthProfile>,
 }
 
 impl Default for UserSettings {
@@ -16,6 +19,7 @@ impl Default for UserSettings {
         Self {
             theme: "system".to_string(),
             services: Vec::new(),
+            auth_profiles: Vec::new(),
         }
     }
 }
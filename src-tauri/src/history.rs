@@ -1,4 +1,4 @@
-use crate::types::{Header, HistoryEntry};
+use crate::types::{AssertionResult, Header, HistoryEntry};
 use rusqlite::{params, Connection, Result};
 use std::path::PathBuf;
 use tauri::{AppHandle, Manager, Runtime};
@@ -29,12 +29,32 @@ impl HistoryService {
                 response_body TEXT NOT NULL,
                 time_elapsed INTEGER NOT NULL,
                 size INTEGER NOT NULL,
-                created_at TEXT NOT NULL
+                created_at TEXT NOT NULL,
+                assertions TEXT NOT NULL DEFAULT '[]'
             )",
                 [],
             )
             .map_err(|e| e.to_string())?;
 
+        // Databases created before assertions existed lack the column
+        let has_assertions: bool = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('history') WHERE name = 'assertions'",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+            .map_err(|e| e.to_string())?
+            > 0;
+        if !has_assertions {
+            self.conn
+                .execute(
+                    "ALTER TABLE history ADD COLUMN assertions TEXT NOT NULL DEFAULT '[]'",
+                    [],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+
         Ok(())
     }
 
@@ -43,6 +63,7 @@ impl HistoryService {
             serde_json::to_string(&entry.request_headers).map_err(|e| e.to_string())?;
         let response_headers =
             serde_json::to_string(&entry.response_headers).map_err(|e| e.to_string())?;
+        let assertions = serde_json::to_string(&entry.assertions).map_err(|e| e.to_string())?;
 
         self.conn
             .execute(
@@ -51,8 +72,8 @@ impl HistoryService {
                 request_headers, request_body, 
                 response_status, response_status_text, 
                 response_headers, response_body, 
-                time_elapsed, size, created_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                time_elapsed, size, created_at, assertions
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
                 params![
                     entry.id,
                     entry.service_id,
@@ -68,6 +89,7 @@ impl HistoryService {
                     entry.time_elapsed,
                     entry.size,
                     entry.created_at,
+                    assertions,
                 ],
             )
             .map_err(|e| e.to_string())?;
@@ -84,7 +106,7 @@ impl HistoryService {
                     request_headers, request_body, 
                     response_status, response_status_text, 
                     response_headers, response_body, 
-                    time_elapsed, size, created_at 
+                    time_elapsed, size, created_at, assertions
                 FROM history 
                 ORDER BY created_at DESC 
                 LIMIT ?1 OFFSET ?2",
@@ -92,32 +114,7 @@ impl HistoryService {
             .map_err(|e| e.to_string())?;
 
         let history_iter = stmt
-            .query_map(params![limit, offset], |row| {
-                let request_headers_raw: String = row.get(5)?;
-                let response_headers_raw: String = row.get(9)?;
-
-                let request_headers: Vec<Header> =
-                    serde_json::from_str(&request_headers_raw).unwrap_or_default();
-                let response_headers: Vec<Header> =
-                    serde_json::from_str(&response_headers_raw).unwrap_or_default();
-
-                Ok(HistoryEntry {
-                    id: row.get(0)?,
-                    service_id: row.get(1)?,
-                    endpoint_id: row.get(2)?,
-                    method: row.get(3)?,
-                    url: row.get(4)?,
-                    request_headers,
-                    request_body: row.get(6)?,
-                    response_status: row.get(7)?,
-                    response_status_text: row.get(8)?,
-                    response_headers,
-                    response_body: row.get(10)?,
-                    time_elapsed: row.get(11)?,
-                    size: row.get(12)?,
-                    created_at: row.get(13)?,
-                })
-            })
+            .query_map(params![limit, offset], entry_from_row)
             .map_err(|e| e.to_string())?;
 
         let mut history = Vec::new();
@@ -128,6 +125,30 @@ impl HistoryService {
         Ok(history)
     }
 
+    /// The entries with the given ids, oldest first
+    pub fn get_entries(&self, ids: &[String]) -> Result<Vec<HistoryEntry>, String> {
+        let placeholders = vec!["?"; ids.len()].join(", ");
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT 
+                    id, service_id, endpoint_id, method, url, 
+                    request_headers, request_body, 
+                    response_status, response_status_text, 
+                    response_headers, response_body, 
+                    time_elapsed, size, created_at, assertions
+                FROM history 
+                WHERE id IN ({})
+                ORDER BY created_at ASC",
+                placeholders
+            ))
+            .map_err(|e| e.to_string())?;
+        let entries = stmt
+            .query_map(rusqlite::params_from_iter(ids), entry_from_row)
+            .map_err(|e| e.to_string())?;
+        entries.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
     pub fn clear(&self) -> Result<(), String> {
         self.conn
             .execute("DELETE FROM history", [])
@@ -137,6 +158,37 @@ impl HistoryService {
     }
 }
 
+fn entry_from_row(row: &rusqlite::Row) -> Result<HistoryEntry> {
+    let request_headers_raw: String = row.get(5)?;
+    let response_headers_raw: String = row.get(9)?;
+
+    let request_headers: Vec<Header> =
+        serde_json::from_str(&request_headers_raw).unwrap_or_default();
+    let response_headers: Vec<Header> =
+        serde_json::from_str(&response_headers_raw).unwrap_or_default();
+    let assertions_raw: String = row.get(14)?;
+    let assertions: Vec<AssertionResult> =
+        serde_json::from_str(&assertions_raw).unwrap_or_default();
+
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        service_id: row.get(1)?,
+        endpoint_id: row.get(2)?,
+        method: row.get(3)?,
+        url: row.get(4)?,
+        request_headers,
+        request_body: row.get(6)?,
+        response_status: row.get(7)?,
+        response_status_text: row.get(8)?,
+        response_headers,
+        response_body: row.get(10)?,
+        time_elapsed: row.get(11)?,
+        size: row.get(12)?,
+        created_at: row.get(13)?,
+        assertions,
+    })
+}
+
 // Wrapper functions for Tauri commands
 pub fn get_db_path<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf, String> {
     let path = app.path().app_config_dir().map_err(|e| e.to_string())?;
@@ -171,6 +223,16 @@ pub fn get_history<R: Runtime>(
     service.get_history(limit, offset)
 }
 
+pub fn get_history_entries<R: Runtime>(
+    app: &AppHandle<R>,
+    ids: &[String],
+) -> Result<Vec<HistoryEntry>, String> {
+    let db_path = get_db_path(app)?;
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let service = HistoryService::new(conn);
+    service.get_entries(ids)
+}
+
 pub fn clear_history<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
     let db_path = get_db_path(app)?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
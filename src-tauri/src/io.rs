@@ -40,6 +40,21 @@ impl FileSystem for RealFileSystem {
     }
 }
 
+/// Where `{{secret.key}}` placeholders and signing keys are looked up
+#[cfg_attr(test, mockall::automock)]
+pub trait SecretStore: Send + Sync {
+    fn get_secret(&self, key: &str) -> Result<String, String>;
+}
+
+/// Secrets kept in the OS keyring by the app
+pub struct KeyringSecretStore;
+
+impl SecretStore for KeyringSecretStore {
+    fn get_secret(&self, key: &str) -> Result<String, String> {
+        crate::domains::secrets::SecretsDomain::get_secret(key)
+    }
+}
+
 #[async_trait]
 #[cfg_attr(test, mockall::automock)]
 pub trait HttpClient: Send + Sync {
@@ -51,6 +66,19 @@ pub trait HttpClient: Send + Sync {
         body: Option<String>,
         query: Vec<(String, String)>,
     ) -> Result<QResponse, String>;
+
+    /// Returns a redirect as the response instead of following it, so the headers it sets,
+    /// such as a login's `Set-Cookie`, are kept
+    async fn send_request_without_redirects(
+        &self,
+        method: &str,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+        query: Vec<(String, String)>,
+    ) -> Result<QResponse, String> {
+        self.send_request(method, url, headers, body, query).await
+    }
 }
 
 pub struct RealHttpClient;
@@ -65,61 +93,134 @@ impl HttpClient for RealHttpClient {
         body: Option<String>,
         query: Vec<(String, String)>,
     ) -> Result<QResponse, String> {
-        let client = reqwest::Client::new();
-        let mut builder = match method.to_uppercase().as_str() {
-            "GET" => client.get(url),
-            "POST" => client.post(url),
-            "PUT" => client.put(url),
-            "DELETE" => client.delete(url),
-            "PATCH" => client.patch(url),
-            "HEAD" => client.head(url),
-            _ => return Err(format!("Unsupported method: {}", method)),
-        };
-
-        for (name, value) in headers {
-            builder = builder.header(name, value);
-        }
+        send_with(&reqwest::Client::new(), method, url, headers, body, query).await
+    }
 
-        if !query.is_empty() {
-            builder = builder.query(&query);
-        }
+    async fn send_request_without_redirects(
+        &self,
+        method: &str,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+        query: Vec<(String, String)>,
+    ) -> Result<QResponse, String> {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| e.to_string())?;
+        send_with(&client, method, url, headers, body, query).await
+    }
+}
 
-        if let Some(b) = body {
-            builder = builder.body(b);
-        }
+/// Reuses one connection pool for every request, for callers sending many requests in a row
+pub struct PooledHttpClient {
+    client: reqwest::Client,
+}
 
-        let start = std::time::Instant::now();
-        let response = builder.send().await.map_err(|e| e.to_string())?;
-        let elapsed = start.elapsed().as_millis() as u64;
-
-        let status = response.status().as_u16();
-        let status_text = response
-            .status()
-            .canonical_reason()
-            .unwrap_or("Unknown")
-            .to_string();
-
-        let mut res_headers = Vec::new();
-        for (name, value) in response.headers() {
-            res_headers.push(crate::types::Header {
-                name: name.to_string(),
-                value: value.to_str().unwrap_or_default().to_string(),
-                enabled: true,
-                secret_key: None,
-            });
+impl PooledHttpClient {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
         }
+    }
+}
 
-        let body_content = response.text().await.map_err(|e| e.to_string())?;
-        let _size = body_content.len() as u64;
+impl Default for PooledHttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        Ok(QResponse {
-            status,
-            status_text,
-            headers: res_headers,
-            body: body_content,
-            error: None,
-            time_elapsed: elapsed,
-            size: _size,
-        })
+#[async_trait]
+impl HttpClient for PooledHttpClient {
+    async fn send_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+        query: Vec<(String, String)>,
+    ) -> Result<QResponse, String> {
+        send_with(&self.client, method, url, headers, body, query).await
     }
+
+    async fn send_request_without_redirects(
+        &self,
+        method: &str,
+        url: &str,
+        headers: Vec<(String, String)>,
+        body: Option<String>,
+        query: Vec<(String, String)>,
+    ) -> Result<QResponse, String> {
+        RealHttpClient
+            .send_request_without_redirects(method, url, headers, body, query)
+            .await
+    }
+}
+
+async fn send_with(
+    client: &reqwest::Client,
+    method: &str,
+    url: &str,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    query: Vec<(String, String)>,
+) -> Result<QResponse, String> {
+    let mut builder = match method.to_uppercase().as_str() {
+        "GET" => client.get(url),
+        "POST" => client.post(url),
+        "PUT" => client.put(url),
+        "DELETE" => client.delete(url),
+        "PATCH" => client.patch(url),
+        "HEAD" => client.head(url),
+        _ => return Err(format!("Unsupported method: {}", method)),
+    };
+
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+
+    if !query.is_empty() {
+        builder = builder.query(&query);
+    }
+
+    if let Some(b) = body {
+        builder = builder.body(b);
+    }
+
+    let start = std::time::Instant::now();
+    let response = builder.send().await.map_err(|e| e.to_string())?;
+    let elapsed = start.elapsed().as_millis() as u64;
+
+    let status = response.status().as_u16();
+    let status_text = response
+        .status()
+        .canonical_reason()
+        .unwrap_or("Unknown")
+        .to_string();
+
+    let mut res_headers = Vec::new();
+    for (name, value) in response.headers() {
+        res_headers.push(crate::types::Header {
+            name: name.to_string(),
+            value: value.to_str().unwrap_or_default().to_string(),
+            enabled: true,
+            secret_key: None,
+        });
+    }
+
+    let body_content = response.text().await.map_err(|e| e.to_string())?;
+    let _size = body_content.len() as u64;
+
+    Ok(QResponse {
+        status,
+        status_text,
+        headers: res_headers,
+        body: body_content,
+        error: None,
+        time_elapsed: elapsed,
+        size: _size,
+        scripts: None,
+        assertions: vec![],
+    })
 }
@@ -1,3 +1,4 @@
+pub mod cli;
 mod commands;
 pub mod domains;
 pub mod history;
@@ -13,6 +14,7 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_notification::init())
         .setup(|app| {
             #[cfg(target_os = "macos")]
             {
@@ -27,10 +29,45 @@ pub fn run() {
                 }
             }
             history::init_db(app.handle())?;
+            domains::monitor::init_db(app.handle())?;
+            // Let the UI follow auth state as tokens are cached or evicted
+            let handle = app.handle().clone();
+            domains::auth::cache::set_change_listener(move || {
+                use tauri::Emitter;
+                let _ = handle.emit(
+                    "token-cache-changed",
+                    domains::auth::cache::list_cached_tokens(),
+                );
+            });
             // Load token cache
+            domains::auth::remove_legacy_token_cache(app.handle());
             if let Ok(cache_path) = domains::auth::get_token_cache_path(app.handle()) {
                 let _ = domains::auth::cache::load_cache_from_file(&cache_path);
             }
+            // Proactively refresh tokens of preflights that opted in
+            let cache_path = domains::auth::get_token_cache_path(app.handle()).ok();
+            tauri::async_runtime::spawn(async move {
+                let interval = std::time::Duration::from_secs(
+                    domains::auth::preflight::REFRESH_INTERVAL_SECONDS,
+                );
+                loop {
+                    tokio::time::sleep(interval).await;
+                    domains::auth::preflight::refresh_expiring_tokens(
+                        &io::RealHttpClient,
+                        cache_path.as_ref(),
+                    )
+                    .await;
+                }
+            });
+            // Run monitors in the background while the app is open
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut scheduler = domains::monitor::MonitorScheduler::default();
+                loop {
+                    domains::monitor::run_due(&handle, &mut scheduler).await;
+                    tokio::time::sleep(domains::monitor::TICK_INTERVAL).await;
+                }
+            });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -55,10 +92,40 @@ pub fn run() {
             commands::clear_history,
             commands::import_swagger,
             commands::import_curl,
+            commands::import_postman,
+            commands::import_insomnia,
+            commands::import_bruno,
+            commands::get_har_entries,
+            commands::import_har,
+            commands::export_history_har,
+            commands::export_run_har,
             commands::get_secrets,
             commands::add_secret,
             commands::delete_secret,
-            commands::get_secret
+            commands::get_secret,
+            commands::list_cached_tokens,
+            commands::evict_cached_tokens,
+            commands::refresh_service_token,
+            commands::run_service,
+            commands::load_data_file,
+            commands::get_workflows,
+            commands::save_workflow,
+            commands::run_workflow,
+            commands::run_load_test,
+            commands::save_load_test_report,
+            commands::get_monitors,
+            commands::save_monitors,
+            commands::get_monitor_results,
+            commands::clear_monitor_results,
+            commands::start_mock_server,
+            commands::stop_mock_server,
+            commands::get_mock_servers,
+            commands::get_mock_server_log,
+            commands::start_proxy,
+            commands::stop_proxy,
+            commands::get_proxies,
+            commands::get_proxy_exchanges,
+            commands::add_recorded_endpoints
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,5 +1,16 @@
-use crate::io::{FileSystem, HttpClient};
-use crate::types::{PreflightConfig, QResponse, RequestTab, Service, TabState, UserSettings};
+use crate::domains::assertions;
+use crate::domains::auth::digest::{self, DigestContext};
+use crate::domains::auth::hmac_signing::{self, HmacRecipe, SignableRequest};
+use crate::domains::auth::sigv4::SigV4Credentials;
+use crate::domains::auth::{
+    ApiKeyLocation, AuthMethod, AwsSigV4Auth, BasicCredentials, HmacAuth, JwtAuth,
+};
+use crate::domains::scripting::{self, ScriptReport};
+use crate::domains::service::workflow::Workflow;
+use crate::io::{FileSystem, HttpClient, KeyringSecretStore, SecretStore};
+use crate::types::{
+    Header, PreflightConfig, PreflightMode, QResponse, RequestTab, Service, TabState, UserSettings,
+};
 use std::collections::HashMap;
 use tauri::{AppHandle, Runtime};
 
@@ -42,6 +53,16 @@ impl<'a> ConfigService<'a> {
         domain.save_service(service, commit_msg)
     }
 
+    pub fn load_workflows(&self, directory: &str) -> Result<Vec<Workflow>, String> {
+        let domain = crate::domains::service::service::ServiceDomain::new(self.fs);
+        domain.load_workflows(directory)
+    }
+
+    pub fn save_workflow(&self, directory: &str, workflow: &Workflow) -> Result<(), String> {
+        let domain = crate::domains::service::service::ServiceDomain::new(self.fs);
+        domain.save_workflow(directory, workflow)
+    }
+
     pub fn load_collections<R: Runtime>(&self, app: &AppHandle<R>) -> Result<Vec<Service>, String> {
         let path = crate::domains::service::service::ServiceDomain::get_collections_path(app)?;
         let domain = crate::domains::service::service::ServiceDomain::new(self.fs);
@@ -81,43 +102,78 @@ impl<'a> ConfigService<'a> {
 pub struct RequestService<'a> {
     pub http: &'a dyn HttpClient,
     pub cache_path: Option<std::path::PathBuf>,
+    pub secrets: &'a dyn SecretStore,
 }
 
 impl<'a> RequestService<'a> {
     pub fn new(http: &'a dyn HttpClient, cache_path: Option<std::path::PathBuf>) -> Self {
-        Self { http, cache_path }
+        Self {
+            http,
+            cache_path,
+            secrets: &KeyringSecretStore,
+        }
+    }
+
+    /// Looks secrets up somewhere other than the keyring
+    pub fn with_secrets(mut self, secrets: &'a dyn SecretStore) -> Self {
+        self.secrets = secrets;
+        self
     }
 
     pub async fn send_request(&self, mut tab: RequestTab) -> Result<QResponse, String> {
-        // Resolve variables in URL, body, and headers
-        let default_vars = HashMap::new();
-        let vars = tab.variables.as_ref().unwrap_or(&default_vars);
-        tab.url = self.resolve_variables(&tab.url, vars);
-        tab.body.content = self.resolve_variables(&tab.body.content, vars);
+        self.resolve_tab(&mut tab);
 
-        for header in &mut tab.headers {
-            header.name = self.resolve_variables(&header.name, vars);
-            header.value = self.resolve_variables(&header.value, vars);
+        // Service scripts run before those of the endpoint
+        let scripts = [
+            ("Service", tab.service_scripts.clone()),
+            ("Endpoint", tab.scripts.clone()),
+        ];
+        let mut report = ScriptReport::default();
+        for (label, script) in &scripts {
+            scripting::run_pre_request(label, &script.pre_request, &mut tab, &mut report);
+        }
+        if !report.variables.is_empty() {
+            // Placeholders may refer to variables the scripts just set
+            self.resolve_tab(&mut tab);
         }
 
+        let default_vars = HashMap::new();
+        let vars = tab.variables.as_ref().unwrap_or(&default_vars);
+
         // Handle preflight if needed
         let mut token = None;
-        let service_id_str = tab.service_id.as_deref().unwrap_or("");
+        // Services sharing an auth profile share its tokens
+        let profile_scope = tab
+            .auth_profile_id
+            .as_deref()
+            .map(crate::domains::auth::profile::cache_scope);
+        let service_id_str = profile_scope
+            .as_deref()
+            .unwrap_or(tab.service_id.as_deref().unwrap_or(""));
+        let environment = tab.environment.as_deref().unwrap_or("");
 
         if tab.preflight.enabled && !tab.preflight.url.is_empty() {
             token = Some(
-                self.execute_preflight(service_id_str, &tab.preflight, vars)
+                self.execute_preflight(service_id_str, environment, &tab.preflight, vars)
                     .await?,
             );
         } else if !service_id_str.is_empty() {
             // Even if preflight is disabled for this tab, check if we have a cached token for this service
-            if let Some(cached) = crate::domains::auth::cache::get_cached_token(service_id_str) {
-                if crate::domains::auth::cache::is_token_valid(&cached) {
-                    token = Some(cached.token);
-                }
+            if let Some(cached) = crate::domains::auth::cache::get_cached_token_for_service(
+                service_id_str,
+                environment,
+                tab.preflight.mode,
+            ) {
+                token = Some(cached.token);
             }
         }
 
+        // A cookie session is sent as a Cookie header instead of a token
+        let session_cookies = match tab.preflight.mode {
+            PreflightMode::Cookies => token.take(),
+            PreflightMode::Token => None,
+        };
+
         if let Some(token_val) = token {
             let token_header = tab
                 .preflight
@@ -127,8 +183,9 @@ impl<'a> RequestService<'a> {
                 .cloned()
                 .unwrap_or_else(|| "Authorization".to_string());
             if token_header.to_lowercase() == "authorization" {
-                tab.auth.bearer_token = token_val;
-                tab.auth.r#type = "bearer".to_string();
+                tab.auth.method = AuthMethod::Bearer {
+                    bearer_token: token_val,
+                };
             } else {
                 tab.headers.push(crate::types::Header {
                     name: token_header,
@@ -136,93 +193,326 @@ impl<'a> RequestService<'a> {
                     enabled: true,
                     secret_key: None,
                 });
-                tab.auth.r#type = "none".to_string();
+                tab.auth.method = AuthMethod::None;
             }
         }
 
         let mut headers = Vec::new();
-        for h in &tab.headers {
+        for h in tab.headers.iter().filter(|h| h.enabled) {
             headers.push((h.name.clone(), h.value.clone()));
         }
+        if let Some(cookies) = &session_cookies {
+            append_cookie(&mut headers, cookies);
+        }
 
         // Add auth headers
-        match tab.auth.r#type.as_str() {
-            "bearer" => {
-                if !tab.auth.bearer_token.is_empty() {
-                    headers.push((
-                        "Authorization".to_string(),
-                        format!("Bearer {}", tab.auth.bearer_token),
-                    ));
-                }
+        match &tab.auth.method {
+            AuthMethod::Bearer { bearer_token } if !bearer_token.is_empty() => {
+                headers.push((
+                    "Authorization".to_string(),
+                    format!("Bearer {}", bearer_token),
+                ));
             }
-            "basic" => {
-                if !tab.auth.basic_user.is_empty() {
-                    let auth = format!("{}:{}", tab.auth.basic_user, tab.auth.basic_pass);
-                    use base64::{engine::general_purpose, Engine as _};
-                    let encoded = general_purpose::STANDARD.encode(auth);
-                    headers.push(("Authorization".to_string(), format!("Basic {}", encoded)));
-                }
+            AuthMethod::Basic(credentials) if !credentials.basic_user.is_empty() => {
+                let auth = format!("{}:{}", credentials.basic_user, credentials.basic_pass);
+                use base64::{engine::general_purpose, Engine as _};
+                let encoded = general_purpose::STANDARD.encode(auth);
+                headers.push(("Authorization".to_string(), format!("Basic {}", encoded)));
             }
-            "apikey" => {
-                if !tab.auth.api_key_name.is_empty() {
-                    if tab.auth.api_key_location == "header" {
-                        headers.push((
-                            tab.auth.api_key_name.clone(),
-                            tab.auth.api_key_value.clone(),
-                        ));
+            AuthMethod::ApiKey(api_key) if !api_key.api_key_name.is_empty() => {
+                match api_key.api_key_location {
+                    ApiKeyLocation::Header => {
+                        headers.push((api_key.api_key_name.clone(), api_key.api_key_value.clone()))
                     }
+                    ApiKeyLocation::Cookie => append_cookie(
+                        &mut headers,
+                        &format!("{}={}", api_key.api_key_name, api_key.api_key_value),
+                    ),
+                    ApiKeyLocation::Query => {}
+                }
+            }
+            AuthMethod::Jwt(jwt) => {
+                let token = self.mint_jwt(jwt, vars)?;
+                if jwt.jwt_header.is_empty() || jwt.jwt_header.eq_ignore_ascii_case("authorization")
+                {
+                    headers.push(("Authorization".to_string(), format!("Bearer {}", token)));
+                } else {
+                    headers.push((jwt.jwt_header.clone(), token));
                 }
             }
             _ => {}
         }
 
         let mut query = Vec::new();
-        for p in &tab.params {
+        for p in tab.params.iter().filter(|p| p.enabled) {
             query.push((p.name.clone(), p.value.clone()));
         }
 
         // Add apikey to query if location is query
-        if tab.auth.r#type == "apikey"
-            && tab.auth.api_key_location == "query"
-            && !tab.auth.api_key_name.is_empty()
-        {
-            query.push((
-                tab.auth.api_key_name.clone(),
-                tab.auth.api_key_value.clone(),
-            ));
+        if let AuthMethod::ApiKey(api_key) = &tab.auth.method {
+            if api_key.api_key_location == ApiKeyLocation::Query && !api_key.api_key_name.is_empty()
+            {
+                query.push((api_key.api_key_name.clone(), api_key.api_key_value.clone()));
+            }
         }
 
+        // A Content-Type header set on the request wins over the body type
         if !tab.body.content.is_empty()
             && tab.method.to_uppercase() != "GET"
             && tab.method.to_uppercase() != "HEAD"
+            && !headers
+                .iter()
+                .any(|(name, _)| name.eq_ignore_ascii_case("content-type"))
         {
             headers.push(("Content-Type".to_string(), tab.body.r#type.clone()));
         }
 
-        self.http
+        // Signing must come last, once the final headers, query and body are known
+        if let AuthMethod::Hmac(hmac) = &tab.auth.method {
+            let signature_headers = self.hmac_headers(&tab, hmac, &headers, &query, vars)?;
+            headers.extend(signature_headers);
+        }
+
+        if let AuthMethod::AwsSigV4(aws) = &tab.auth.method {
+            let credentials = self.aws_credentials(aws, vars)?;
+            tab.url = crate::domains::auth::sigv4::sign_request(
+                &tab.method,
+                &tab.url,
+                &query,
+                &mut headers,
+                tab.body.content.as_bytes(),
+                &credentials,
+                chrono::Utc::now(),
+            )?;
+            query = Vec::new();
+        }
+
+        let body = if tab.body.content.is_empty() {
+            None
+        } else {
+            Some(tab.body.content.clone())
+        };
+
+        let mut response = if let AuthMethod::Digest(credentials) = &tab.auth.method {
+            self.send_with_digest(&tab, credentials, headers, body, query)
+                .await?
+        } else {
+            self.http
+                .send_request(&tab.method, &tab.url, headers, body, query)
+                .await?
+        };
+
+        for (label, script) in &scripts {
+            scripting::run_post_response(
+                label,
+                &script.post_response,
+                &tab,
+                &response,
+                &mut report,
+            );
+        }
+        if scripts.iter().any(|(_, script)| !script.is_empty()) {
+            response.scripts = Some(report);
+        }
+        response.assertions = assertions::evaluate(&tab.assertions, &response);
+        Ok(response)
+    }
+
+    /// Resolves variables in URL, body, and headers
+    fn resolve_tab(&self, tab: &mut RequestTab) {
+        let vars = tab.variables.clone().unwrap_or_default();
+        tab.url = self.resolve_variables(&tab.url, &vars);
+        tab.body.content = self.resolve_variables(&tab.body.content, &vars);
+
+        for header in &mut tab.headers {
+            header.name = self.resolve_variables(&header.name, &vars);
+            header.value = self.resolve_variables(&header.value, &vars);
+        }
+        for param in &mut tab.params {
+            param.name = self.resolve_variables(&param.name, &vars);
+            param.value = self.resolve_variables(&param.value, &vars);
+        }
+
+        // Inherited auth comes straight from the service or environment config
+        match &mut tab.auth.method {
+            AuthMethod::Bearer { bearer_token } => {
+                *bearer_token = self.resolve_variables(bearer_token, &vars);
+            }
+            AuthMethod::Basic(credentials) | AuthMethod::Digest(credentials) => {
+                credentials.basic_user = self.resolve_variables(&credentials.basic_user, &vars);
+                credentials.basic_pass = self.resolve_variables(&credentials.basic_pass, &vars);
+            }
+            AuthMethod::ApiKey(api_key) => {
+                api_key.api_key_name = self.resolve_variables(&api_key.api_key_name, &vars);
+                api_key.api_key_value = self.resolve_variables(&api_key.api_key_value, &vars);
+            }
+            _ => {}
+        }
+    }
+
+    /// Sends the request, answering a Digest 401 challenge with a single retry
+    async fn send_with_digest(
+        &self,
+        tab: &RequestTab,
+        credentials: &BasicCredentials,
+        mut headers: Vec<(String, String)>,
+        body: Option<String>,
+        query: Vec<(String, String)>,
+    ) -> Result<QResponse, String> {
+        let response = self
+            .http
             .send_request(
                 &tab.method,
                 &tab.url,
-                headers,
-                if tab.body.content.is_empty() {
-                    None
-                } else {
-                    Some(tab.body.content.clone())
-                },
-                query,
+                headers.clone(),
+                body.clone(),
+                query.clone(),
             )
+            .await?;
+
+        if response.status != 401 {
+            return Ok(response);
+        }
+        let challenge = match digest::select_challenge(
+            response
+                .headers
+                .iter()
+                .filter(|h| h.name.eq_ignore_ascii_case("www-authenticate"))
+                .map(|h| h.value.as_str()),
+        ) {
+            Some(challenge) => challenge,
+            None => return Ok(response),
+        };
+
+        // The digest covers the request-target exactly as it goes on the wire
+        let target = wire_url(&tab.url, &query)?;
+        let uri = match target.query() {
+            Some(q) => format!("{}?{}", target.path(), q),
+            None => target.path().to_string(),
+        };
+
+        let cnonce = digest::generate_cnonce();
+        let authorization = digest::authorization_header(
+            &challenge,
+            &credentials.basic_user,
+            &credentials.basic_pass,
+            &DigestContext {
+                method: &tab.method,
+                uri: &uri,
+                cnonce: &cnonce,
+                nc: 1,
+            },
+        )?;
+        headers.push(("Authorization".to_string(), authorization));
+
+        self.http
+            .send_request(&tab.method, &tab.url, headers, body, query)
             .await
     }
 
+    fn hmac_headers(
+        &self,
+        tab: &RequestTab,
+        hmac: &HmacAuth,
+        headers: &[(String, String)],
+        query: &[(String, String)],
+        variables: &HashMap<String, String>,
+    ) -> Result<Vec<(String, String)>, String> {
+        let key = self
+            .secrets
+            .get_secret(&hmac.hmac_key_secret)
+            .map_err(|e| format!("Failed to load HMAC key '{}': {}", hmac.hmac_key_secret, e))?;
+        let url = wire_url(&tab.url, query)?;
+        // Built-in placeholders are left for the signer, whatever the environment defines
+        let variables: HashMap<String, String> = variables
+            .iter()
+            .filter(|(name, _)| !hmac_signing::is_built_in_placeholder(name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        let variables = &variables;
+        let template = self.resolve_variables(&hmac.hmac_template, variables);
+        let recipe_headers: Vec<Header> = hmac
+            .hmac_headers
+            .iter()
+            .map(|h| Header {
+                name: self.resolve_variables(&h.name, variables),
+                value: self.resolve_variables(&h.value, variables),
+                ..h.clone()
+            })
+            .collect();
+
+        hmac_signing::sign(
+            &HmacRecipe {
+                algorithm: &hmac.hmac_algorithm,
+                encoding: &hmac.hmac_encoding,
+                template: &template,
+                headers: &recipe_headers,
+            },
+            key.as_bytes(),
+            &SignableRequest {
+                method: &tab.method,
+                url: &url,
+                headers,
+                body: &tab.body.content,
+            },
+            chrono::Utc::now(),
+            &uuid::Uuid::new_v4().to_string(),
+        )
+    }
+
+    fn mint_jwt(
+        &self,
+        auth: &JwtAuth,
+        variables: &HashMap<String, String>,
+    ) -> Result<String, String> {
+        let claims = self.resolve_variables(&auth.jwt_claims, variables);
+        crate::domains::auth::jwt::get_or_mint(
+            &auth.jwt_algorithm,
+            &auth.jwt_key_secret,
+            &claims,
+            |key| {
+                self.secrets
+                    .get_secret(key)
+                    .map_err(|e| format!("Failed to load JWT signing key '{}': {}", key, e))
+            },
+        )
+    }
+
+    fn aws_credentials(
+        &self,
+        auth: &AwsSigV4Auth,
+        variables: &HashMap<String, String>,
+    ) -> Result<SigV4Credentials, String> {
+        let secret = |key: &str| {
+            self.secrets
+                .get_secret(key)
+                .map_err(|e| format!("Failed to load AWS credential '{}': {}", key, e))
+        };
+
+        Ok(SigV4Credentials {
+            access_key: secret(&auth.aws_access_key_secret)?,
+            secret_key: secret(&auth.aws_secret_key_secret)?,
+            session_token: if auth.aws_session_token_secret.is_empty() {
+                None
+            } else {
+                Some(secret(&auth.aws_session_token_secret)?)
+            },
+            region: self.resolve_variables(&auth.aws_region, variables),
+            service: self.resolve_variables(&auth.aws_service, variables),
+        })
+    }
+
     async fn execute_preflight(
         &self,
         service_id: &str,
+        environment: &str,
         config: &PreflightConfig,
         variables: &HashMap<String, String>,
     ) -> Result<String, String> {
         crate::domains::auth::preflight::execute_preflight(
             self.http,
             service_id,
+            environment,
             config,
             variables,
             self.cache_path.as_ref(),
@@ -246,10 +536,10 @@ impl<'a> RequestService<'a> {
 
                     if var_name.starts_with("secret.") {
                         let key = &var_name[7..];
-                        match crate::domains::secrets::SecretsDomain::get_secret(key) {
+                        match self.secrets.get_secret(key) {
                             Ok(val) => val,
                             Err(e) => {
-                                println!("Failed to resolve secret {}: {}", key, e);
+                                eprintln!("Failed to resolve secret {}: {}", key, e);
                                 caps[0].to_string()
                             }
                         }
@@ -270,3 +560,27 @@ impl<'a> RequestService<'a> {
         result
     }
 }
+
+/// Adds a `name=value` pair to the request's Cookie header, creating it if needed
+fn append_cookie(headers: &mut Vec<(String, String)>, pair: &str) {
+    match headers
+        .iter_mut()
+        .find(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+    {
+        Some((_, value)) if !value.is_empty() => {
+            value.push_str("; ");
+            value.push_str(pair);
+        }
+        Some((_, value)) => value.push_str(pair),
+        None => headers.push(("Cookie".to_string(), pair.to_string())),
+    }
+}
+
+/// Joins the URL with the extra query pairs the same way the HTTP client appends them
+fn wire_url(url: &str, query: &[(String, String)]) -> Result<url::Url, String> {
+    let mut target = url::Url::parse(url).map_err(|e| e.to_string())?;
+    if !query.is_empty() {
+        target.query_pairs_mut().extend_pairs(query);
+    }
+    Ok(target)
+}
@@ -1,6 +1,6 @@
 use crate::io::{MockFileSystem, MockHttpClient};
 use crate::services::RequestService;
-use crate::types::{PreflightConfig, QResponse, RequestTab};
+use crate::types::{PreflightConfig, PreflightMode, QResponse, RequestTab, Scripts};
 use mockall::predicate;
 
 #[tokio::test]
@@ -28,6 +28,8 @@ async fn test_send_request_with_preflight() {
                     error: None,
                     time_elapsed: 10,
                     size: 30,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -57,6 +59,8 @@ async fn test_send_request_with_preflight() {
                     error: None,
                     time_elapsed: 20,
                     size: 4,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -74,16 +78,7 @@ async fn test_send_request_with_preflight() {
             r#type: "none".to_string(),
             content: "".to_string(),
         },
-        auth: crate::types::AuthConfig {
-            r#type: "none".to_string(),
-            active: true,
-            bearer_token: "".to_string(),
-            basic_user: "".to_string(),
-            basic_pass: "".to_string(),
-            api_key_name: "".to_string(),
-            api_key_value: "".to_string(),
-            api_key_location: "header".to_string(),
-        },
+        auth: crate::types::AuthConfig::default(),
         active_sub_tab: Some("headers".to_string()),
         service_id: Some("service1".to_string()),
         preflight: PreflightConfig {
@@ -100,9 +95,18 @@ async fn test_send_request_with_preflight() {
             cache_duration_unit: "seconds".to_string(),
             token_key: "access_token".to_string(),
             token_header: Some("Authorization".to_string()),
+            secret_token: false,
+            refresh_before_expiry: None,
+            steps: vec![],
+            mode: PreflightMode::Token,
         },
         variables: None,
         is_edited: false,
+        environment: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+        service_scripts: Scripts::default(),
+        assertions: vec![],
     };
 
     let result = service.send_request(tab).await;
@@ -142,6 +146,8 @@ async fn test_variable_resolution() {
                     error: None,
                     time_elapsed: 10,
                     size: 2,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -166,16 +172,7 @@ async fn test_variable_resolution() {
             r#type: "application/json".to_string(),
             content: "{\"id\": \"item-{{ITEM_ID}}\"}".to_string(),
         },
-        auth: crate::types::AuthConfig {
-            r#type: "none".to_string(),
-            active: true,
-            bearer_token: "".to_string(),
-            basic_user: "".to_string(),
-            basic_pass: "".to_string(),
-            api_key_name: "".to_string(),
-            api_key_value: "".to_string(),
-            api_key_location: "header".to_string(),
-        },
+        auth: crate::types::AuthConfig::default(),
         active_sub_tab: None,
         service_id: None,
         preflight: PreflightConfig {
@@ -192,9 +189,18 @@ async fn test_variable_resolution() {
             cache_duration_unit: "seconds".to_string(),
             token_key: "access_token".to_string(),
             token_header: None,
+            secret_token: false,
+            refresh_before_expiry: None,
+            steps: vec![],
+            mode: PreflightMode::Token,
         },
         variables: Some(variables),
         is_edited: false,
+        environment: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+        service_scripts: Scripts::default(),
+        assertions: vec![],
     };
 
     let result = service.send_request(tab).await;
@@ -246,6 +252,8 @@ async fn test_status_codes_and_headers() {
                     error: None,
                     time_elapsed: 5,
                     size: 22,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -282,16 +290,7 @@ fn create_mock_tab(
             r#type: "none".to_string(),
             content: "".to_string(),
         },
-        auth: crate::types::AuthConfig {
-            r#type: "none".to_string(),
-            active: true,
-            bearer_token: "".to_string(),
-            basic_user: "".to_string(),
-            basic_pass: "".to_string(),
-            api_key_name: "".to_string(),
-            api_key_value: "".to_string(),
-            api_key_location: "header".to_string(),
-        },
+        auth: crate::types::AuthConfig::default(),
         active_sub_tab: None,
         service_id: None,
         preflight: PreflightConfig {
@@ -308,8 +307,17 @@ fn create_mock_tab(
             cache_duration_unit: "seconds".to_string(),
             token_key: "access_token".to_string(),
             token_header: None,
+            secret_token: false,
+            refresh_before_expiry: None,
+            steps: vec![],
+            mode: PreflightMode::Token,
         },
         variables,
         is_edited: false,
+        environment: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+        service_scripts: Scripts::default(),
+        assertions: vec![],
     }
 }
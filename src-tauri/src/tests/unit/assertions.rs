@@ -0,0 +1,126 @@
+use crate::domains::assertions::{evaluate, Assertion, JsonType};
+use crate::types::{Header, QResponse};
+use serde_json::json;
+
+fn response(body: &str) -> QResponse {
+    QResponse {
+        status: 201,
+        status_text: "Created".to_string(),
+        headers: vec![Header {
+            name: "Content-Type".to_string(),
+            value: "application/json; charset=utf-8".to_string(),
+            enabled: true,
+            secret_key: None,
+        }],
+        body: body.to_string(),
+        error: None,
+        time_elapsed: 120,
+        size: body.len() as u64,
+        scripts: None,
+        assertions: vec![],
+    }
+}
+
+#[test]
+fn test_assertions_report_pass_and_fail() {
+    let response = response(r#"{"id": 7, "name": "widget", "tags": ["a", "b"], "price": 9.5}"#);
+    let assertions = vec![
+        Assertion::Status { equals: 201 },
+        Assertion::HeaderEquals {
+            name: "content-type".to_string(),
+            value: "text/plain".to_string(),
+        },
+        Assertion::HeaderMatches {
+            name: "Content-Type".to_string(),
+            pattern: "^application/json".to_string(),
+        },
+        Assertion::JsonPathEquals {
+            path: "$.name".to_string(),
+            value: json!("widget"),
+        },
+        Assertion::JsonPathExists {
+            path: "$.tags[1]".to_string(),
+        },
+        Assertion::JsonPathExists {
+            path: "$.owner".to_string(),
+        },
+        Assertion::JsonPathType {
+            path: "$.price".to_string(),
+            json_type: JsonType::Number,
+        },
+        Assertion::JsonPathType {
+            path: "$.id".to_string(),
+            json_type: JsonType::String,
+        },
+        Assertion::BodyContains {
+            text: "widget".to_string(),
+        },
+        Assertion::BodyMatches {
+            pattern: r#""id":\s*\d+"#.to_string(),
+        },
+        Assertion::ResponseTimeBelow { ms: 100 },
+    ];
+
+    let passed: Vec<bool> = evaluate(&assertions, &response)
+        .iter()
+        .map(|r| r.passed)
+        .collect();
+    assert_eq!(
+        passed,
+        vec![true, false, true, true, true, false, true, false, true, true, false]
+    );
+}
+
+#[test]
+fn test_json_schema_assertion_lists_violations() {
+    let schema = json!({
+        "type": "object",
+        "required": ["id", "name"],
+        "properties": { "id": { "type": "integer" } }
+    });
+    let assertions = vec![Assertion::JsonSchema { schema }];
+
+    let valid = evaluate(&assertions, &response(r#"{"id": 1, "name": "a"}"#));
+    assert!(valid[0].passed);
+
+    let invalid = evaluate(&assertions, &response(r#"{"id": "1"}"#));
+    assert!(!invalid[0].passed);
+    assert!(invalid[0].message.contains("/id"), "{}", invalid[0].message);
+    assert!(
+        invalid[0].message.contains("name"),
+        "{}",
+        invalid[0].message
+    );
+
+    let not_json = evaluate(&assertions, &response("<html></html>"));
+    assert!(!not_json[0].passed);
+    assert!(not_json[0]
+        .message
+        .starts_with("Response body is not valid JSON"));
+}
+
+#[test]
+fn test_assertions_round_trip_through_endpoint_yaml() {
+    let yaml = r#"
+- type: status
+  equals: 200
+- type: jsonPathType
+  path: $.items
+  jsonType: array
+- type: responseTimeBelow
+  ms: 500
+"#;
+    let assertions: Vec<Assertion> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(
+        assertions[1],
+        Assertion::JsonPathType {
+            path: "$.items".to_string(),
+            json_type: JsonType::Array,
+        }
+    );
+    let written = serde_yaml::to_string(&assertions).unwrap();
+    assert_eq!(
+        serde_yaml::from_str::<Vec<Assertion>>(&written).unwrap(),
+        assertions
+    );
+}
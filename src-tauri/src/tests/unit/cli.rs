@@ -0,0 +1,109 @@
+use crate::cli::{junit_report, secret_env_var, text_report, CliSecrets};
+use crate::domains::runner::{RunIteration, RunRequestResult, RunSummary};
+use crate::io::SecretStore;
+use crate::types::{Assertion, AssertionResult};
+use std::collections::HashMap;
+
+fn summary() -> RunSummary {
+    let result = |id: &str, name: &str, passed: bool| RunRequestResult {
+        iteration: None,
+        step_id: None,
+        skipped: false,
+        endpoint_id: id.to_string(),
+        name: name.to_string(),
+        method: "GET".to_string(),
+        url: format!("{{{{BASE_URL}}}}/{}", id),
+        status: Some(200),
+        passed,
+        time_elapsed: 42,
+        assertions: vec![],
+        script_errors: vec![],
+        error: None,
+        exchange: None,
+    };
+    let mut failed = result("orders", "Orders <all>", false);
+    failed.assertions.push(AssertionResult {
+        assertion: Assertion::Status { equals: 201 },
+        passed: false,
+        message: "Status is 200".to_string(),
+    });
+    let mut unreachable = result("health", "Health", false);
+    unreachable.status = None;
+    unreachable.error = Some("connection refused".to_string());
+
+    RunSummary {
+        service_id: "svc".to_string(),
+        environment: Some("ci".to_string()),
+        total: 3,
+        passed: 1,
+        failed: 2,
+        skipped: 0,
+        time_elapsed: 1500,
+        results: vec![result("login", "Login", true), failed, unreachable],
+        variables: HashMap::new(),
+        iterations: vec![],
+    }
+}
+
+#[test]
+fn test_cli_secrets_prefer_environment_over_file() {
+    assert_eq!(secret_env_var("api-key.v2"), "XREST_SECRET_API_KEY_V2");
+
+    let secrets = CliSecrets::new(
+        HashMap::from([("XREST_SECRET_API_KEY".to_string(), "from-env".to_string())]),
+        HashMap::from([
+            ("api-key".to_string(), "from-file".to_string()),
+            ("db".to_string(), "pg".to_string()),
+        ]),
+    );
+    assert_eq!(secrets.get_secret("api-key").unwrap(), "from-env");
+    assert_eq!(secrets.get_secret("db").unwrap(), "pg");
+    let err = secrets.get_secret("missing").unwrap_err();
+    assert!(err.contains("XREST_SECRET_MISSING"));
+}
+
+#[test]
+fn test_text_report_lists_failure_reasons() {
+    let report = text_report("Orders", &summary());
+    assert!(report.starts_with("Orders (ci)\n"));
+    assert!(report.contains("PASS GET     Login 200 (42 ms)"));
+    assert!(report.contains("FAIL GET     Health --- (42 ms)\n       connection refused\n"));
+    assert!(report.contains("Status is 200"));
+    assert!(report.ends_with("1 passed, 2 failed, 3 total in 1500 ms\n"));
+}
+
+#[test]
+fn test_junit_report_counts_failures_and_escapes_names() {
+    let report = junit_report("Orders", &summary());
+    assert!(report.contains(
+        "<testsuite name=\"Orders\" tests=\"3\" failures=\"2\" skipped=\"0\" time=\"1.500\">"
+    ));
+    assert!(report.contains("<testcase classname=\"Orders\" name=\"GET Login\" time=\"0.042\"/>"));
+    assert!(report.contains("name=\"GET Orders &lt;all&gt;\""));
+    assert!(report.contains("<failure message=\"connection refused\">"));
+    assert_eq!(report.matches("<failure ").count(), 2);
+}
+
+#[test]
+fn test_reports_name_the_data_row_of_each_result() {
+    let mut summary = summary();
+    for (i, result) in summary.results.iter_mut().enumerate() {
+        result.iteration = Some(i);
+    }
+    summary.iterations = (0..3)
+        .map(|index| RunIteration {
+            index,
+            row: HashMap::from([("tenant".to_string(), format!("t{}", index + 1))]),
+            passed: usize::from(index == 0),
+            failed: usize::from(index != 0),
+        })
+        .collect();
+
+    let text = text_report("Orders", &summary);
+    assert!(text.contains("  Row 3 (tenant=t3)\n  FAIL GET     Health"));
+    assert!(text.contains("Failed rows: 2, 3\n"));
+
+    let junit = junit_report("Orders", &summary);
+    assert!(junit.contains("name=\"GET Health [row 3]\""));
+    assert!(junit.contains(">Row 3 (tenant=t3)\nconnection refused</failure>"));
+}
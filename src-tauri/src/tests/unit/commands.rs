@@ -54,9 +54,66 @@ paths:
     assert_eq!(endpoints[0].headers[0].name, "X-Request-ID");
 }
 
+#[test]
+fn test_parse_spec_keeps_example_responses() {
+    let content = r#"{
+        "openapi": "3.0.0",
+        "info": { "title": "Test API", "version": "1.0.0" },
+        "paths": {
+            "/users/{id}": {
+                "get": {
+                    "responses": {
+                        "200": {
+                            "description": "A user",
+                            "content": {
+                                "application/json": { "example": { "id": 1 } }
+                            }
+                        },
+                        "404": {
+                            "description": "Missing",
+                            "content": {
+                                "text/plain": {
+                                    "examples": { "gone": { "value": "No such user" } }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }"#;
+    let (_, endpoints) = parse_spec_content(content, "s1").unwrap();
+    let examples = &endpoints[0].examples;
+    assert_eq!(examples.len(), 2);
+    assert_eq!(examples[0].status, 200);
+    assert_eq!(examples[0].body, "{\n  \"id\": 1\n}");
+    assert_eq!(examples[1].name, "404 gone");
+    assert_eq!(examples[1].body, "No such user");
+    assert_eq!(examples[1].headers[0].value, "text/plain");
+
+    let content = r#"
+swagger: "2.0"
+paths:
+  /items:
+    get:
+      responses:
+        "200":
+          description: "Items"
+          examples:
+            application/json: [{ "id": 1 }]
+"#;
+    let (_, endpoints) = parse_spec_content(content, "s1").unwrap();
+    assert_eq!(endpoints[0].examples[0].status, 200);
+    assert_eq!(
+        endpoints[0].examples[0].body,
+        "[\n  {\n    \"id\": 1\n  }\n]"
+    );
+}
+
 #[test]
 fn test_curl_to_endpoint_parsing() {
     use crate::commands::curl_to_endpoint;
+    use crate::types::AuthType;
 
     // Test simple GET
     let curl = "curl https://api.example.com/users";
@@ -67,8 +124,7 @@ fn test_curl_to_endpoint_parsing() {
 
     // Test POST with body and headers
     let curl = r#"curl -X POST https://api.example.com/login -H "Content-Type: application/json" -d '{"user":"test"}'"#;
-    let endpoint =
-        curl_to_endpoint("s1".to_string(), curl, true, Some("bearer".to_string())).unwrap();
+    let endpoint = curl_to_endpoint("s1".to_string(), curl, true, Some(AuthType::Bearer)).unwrap();
     assert_eq!(endpoint.method, "POST");
     assert_eq!(endpoint.url, "https://api.example.com/login");
     assert_eq!(endpoint.name, "login");
@@ -78,5 +134,5 @@ fn test_curl_to_endpoint_parsing() {
         .iter()
         .any(|h| h.name == "content-type" && h.value == "application/json"));
     assert_eq!(endpoint.authenticated, true);
-    assert_eq!(endpoint.auth_type, "bearer");
+    assert_eq!(endpoint.auth_type, AuthType::Bearer);
 }
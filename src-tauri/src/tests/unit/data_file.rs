@@ -0,0 +1,61 @@
+use crate::domains::data_file::{load_data_file, parse_csv, parse_json};
+use crate::io::MockFileSystem;
+use std::collections::HashMap;
+use std::path::Path;
+
+fn row(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_parse_csv_rows_by_header() {
+    let rows = parse_csv("tenant , payload\nacme,\"{\"\"a\"\": 1}\"\nglobex,\n").unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            row(&[("tenant", "acme"), ("payload", "{\"a\": 1}")]),
+            row(&[("tenant", "globex"), ("payload", "")]),
+        ]
+    );
+    assert!(parse_csv("a,b\n1,2,3\n").unwrap_err().starts_with("Row 1"));
+}
+
+#[test]
+fn test_parse_json_stringifies_values() {
+    let rows = parse_json(r#"[{"id": 7, "name": "bob", "tags": ["x"], "note": null}]"#).unwrap();
+    assert_eq!(
+        rows,
+        vec![row(&[
+            ("id", "7"),
+            ("name", "bob"),
+            ("tags", "[\"x\"]"),
+            ("note", "")
+        ])]
+    );
+    assert_eq!(parse_json("[{}, 3]").unwrap_err(), "Row 2 is not an object");
+    assert!(parse_json(r#"{"id": 1}"#).is_err());
+}
+
+#[test]
+fn test_load_data_file_picks_format_from_extension() {
+    let mut fs = MockFileSystem::new();
+    fs.expect_read_to_string()
+        .returning(|path| match path.extension().and_then(|e| e.to_str()) {
+            Some("CSV") => Ok("id\n1\n".to_string()),
+            _ => Ok("[]".to_string()),
+        });
+
+    assert_eq!(
+        load_data_file(&fs, Path::new("rows.CSV")).unwrap(),
+        vec![row(&[("id", "1")])]
+    );
+    assert!(load_data_file(&fs, Path::new("rows.json"))
+        .unwrap()
+        .is_empty());
+    assert!(load_data_file(&fs, Path::new("rows.txt"))
+        .unwrap_err()
+        .contains("expected .csv or .json"));
+}
@@ -2,7 +2,10 @@ use crate::domains::auth::AuthConfig;
 use crate::domains::service::service::ServiceDomain;
 use crate::domains::settings::SettingsDomain;
 use crate::io::MockFileSystem;
-use crate::types::{AuthType, Endpoint, EndpointMetadata, PreflightConfig, Service};
+use crate::types::{
+    AuthMethod, AuthType, Endpoint, EndpointMetadata, PreflightConfig, PreflightMode, Scripts,
+    Service,
+};
 use mockall::predicate::*;
 use std::path::PathBuf;
 
@@ -126,16 +129,7 @@ fn test_save_service_versioning() {
         environments: vec![],
         is_authenticated: false,
         auth_type: Some(AuthType::None),
-        auth: AuthConfig {
-            r#type: "none".to_string(),
-            active: true,
-            basic_user: "".to_string(),
-            basic_pass: "".to_string(),
-            bearer_token: "".to_string(),
-            api_key_name: "".to_string(),
-            api_key_value: "".to_string(),
-            api_key_location: "header".to_string(),
-        },
+        auth: AuthConfig::default(),
         preflight: PreflightConfig {
             enabled: false,
             method: "POST".to_string(),
@@ -150,6 +144,10 @@ fn test_save_service_versioning() {
             cache_duration_unit: "seconds".to_string(),
             token_key: "".to_string(),
             token_header: None,
+            secret_token: false,
+            refresh_before_expiry: None,
+            steps: vec![],
+            mode: PreflightMode::Token,
         },
         endpoints: vec![Endpoint {
             id: "e1".to_string(),
@@ -158,7 +156,7 @@ fn test_save_service_versioning() {
             method: "GET".to_string(),
             url: "/items".to_string(),
             authenticated: false,
-            auth_type: "none".to_string(),
+            auth_type: AuthType::None,
             metadata: EndpointMetadata {
                 version: "0".to_string(),
                 last_updated: 0,
@@ -180,13 +178,23 @@ fn test_save_service_versioning() {
                 cache_duration_unit: "seconds".to_string(),
                 token_key: "access_token".to_string(),
                 token_header: None,
+                secret_token: false,
+                refresh_before_expiry: None,
+                steps: vec![],
+                mode: PreflightMode::Token,
             },
             last_version: 0,
             versions: vec![],
+            auth_profile_id: None,
+            scripts: Scripts::default(),
+            assertions: vec![],
+            examples: vec![],
         }],
         directory: service_dir.to_string(),
         selected_environment: None,
         git_url: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
     };
 
     mock_fs.expect_exists().returning(|_| true);
@@ -213,3 +221,171 @@ fn test_save_service_versioning() {
     assert_eq!(service.endpoints[0].last_version, 2);
     assert_eq!(service.endpoints[0].versions.len(), 2);
 }
+
+#[test]
+fn test_environment_overrides_auth_and_preflight() {
+    let mut mock_fs = MockFileSystem::new();
+    let service_dir = "/tmp/test-env-overrides";
+
+    let service_file_yaml = r#"
+id: s-env
+name: Env Service
+isAuthenticated: true
+endpoints: []
+directory: /tmp/test-env-overrides
+authProfileId: gateway
+auth:
+  type: bearer
+  active: true
+  basicUser: ''
+  basicPass: ''
+  bearerToken: service-token
+  apiKeyName: ''
+  apiKeyValue: ''
+  apiKeyLocation: header
+"#;
+    let environments_yaml = r#"
+- name: DEV
+  variables: []
+  auth:
+    type: apikey
+    active: true
+    basicUser: ''
+    basicPass: ''
+    bearerToken: ''
+    apiKeyName: X-API-Key
+    apiKeyValue: dev-key
+    apiKeyLocation: header
+- name: PROD
+  isUnsafe: true
+  variables: []
+  preflight:
+    enabled: true
+    url: https://idp.prod.example.com/token
+    tokenKey: access_token
+"#;
+
+    mock_fs
+        .expect_exists()
+        .with(eq(PathBuf::from(service_dir).join("environments.yaml")))
+        .returning(|_| true);
+    mock_fs.expect_exists().returning(|_| true);
+    mock_fs
+        .expect_read_to_string()
+        .with(eq(PathBuf::from(service_dir).join("service.yaml")))
+        .returning(move |_| Ok(service_file_yaml.to_string()));
+    mock_fs
+        .expect_read_to_string()
+        .with(eq(PathBuf::from(service_dir).join("environments.yaml")))
+        .returning(move |_| Ok(environments_yaml.to_string()));
+    mock_fs
+        .expect_read_to_string()
+        .returning(|_| Err("not found".to_string()));
+
+    let service = ServiceDomain::new(&mock_fs)
+        .load_service(service_dir)
+        .unwrap();
+    assert_eq!(service.auth_profile_id.as_deref(), Some("gateway"));
+
+    let (auth, preflight) = service.effective_auth(Some("DEV"), None);
+    match auth.method {
+        AuthMethod::ApiKey(api_key) => assert_eq!(api_key.api_key_value, "dev-key"),
+        other => panic!("Expected the DEV API key, got {:?}", other),
+    }
+    assert!(!preflight.enabled);
+
+    let (auth, preflight) = service.effective_auth(Some("PROD"), None);
+    assert_eq!(
+        auth.method,
+        AuthMethod::Bearer {
+            bearer_token: "service-token".to_string()
+        }
+    );
+    assert!(preflight.enabled);
+    assert_eq!(preflight.url, "https://idp.prod.example.com/token");
+
+    // Without an environment override the shared profile replaces the service config
+    let profile: crate::domains::auth::profile::AuthProfile = serde_yaml::from_str(
+        r#"
+id: gateway
+name: Gateway IdP
+auth:
+  type: none
+  active: true
+  basicUser: ''
+  basicPass: ''
+  bearerToken: ''
+  apiKeyName: ''
+  apiKeyValue: ''
+  apiKeyLocation: header
+preflight:
+  enabled: true
+  url: https://idp.example.com/token
+"#,
+    )
+    .unwrap();
+    let (auth, preflight) = service.effective_auth(Some("STAGE"), Some(&profile));
+    assert_eq!(auth.method, AuthMethod::None);
+    assert_eq!(preflight.url, "https://idp.example.com/token");
+    let (auth, preflight) = service.effective_auth(Some("PROD"), Some(&profile));
+    assert_eq!(auth.method, AuthMethod::None);
+    assert_eq!(preflight.url, "https://idp.prod.example.com/token");
+}
+
+#[test]
+fn test_load_save_workflows() {
+    let workflow: crate::domains::service::workflow::Workflow = serde_yaml::from_str(
+        r#"
+id: smoke
+name: Smoke
+steps:
+  - id: login
+    endpointId: login
+    extract:
+      - { variable: token, path: access_token }
+  - id: orders
+    endpointId: orders
+    when: steps.login.passed
+    next:
+      - { goto: end }
+"#,
+    )
+    .unwrap();
+
+    let written = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+    let mut mock_fs = MockFileSystem::new();
+    mock_fs.expect_exists().returning(|_| true);
+    let sink = written.clone();
+    mock_fs
+        .expect_write()
+        .with(eq(PathBuf::from("/svc/workflows/smoke.yaml")), always())
+        .times(1)
+        .returning(move |_, content| {
+            *sink.lock().unwrap() = content.to_string();
+            Ok(())
+        });
+    let domain = ServiceDomain::new(&mock_fs);
+    domain.save_workflow("/svc", &workflow).unwrap();
+    let content = written.lock().unwrap().clone();
+    assert!(!content.contains("continueOnFailure"));
+    assert!(!content.contains("variables"));
+
+    let mut bad_id = workflow.clone();
+    bad_id.id = "../escape".to_string();
+    assert!(domain.save_workflow("/svc", &bad_id).is_err());
+
+    let mut mock_fs = MockFileSystem::new();
+    mock_fs.expect_exists().returning(|_| true);
+    mock_fs.expect_read_dir().returning(|_| {
+        Ok(vec![
+            PathBuf::from("/svc/workflows/smoke.yaml"),
+            PathBuf::from("/svc/workflows/README.md"),
+        ])
+    });
+    mock_fs
+        .expect_read_to_string()
+        .with(eq(PathBuf::from("/svc/workflows/smoke.yaml")))
+        .returning(move |_| Ok(content.clone()));
+    let loaded = ServiceDomain::new(&mock_fs).load_workflows("/svc").unwrap();
+    assert_eq!(loaded, vec![workflow]);
+}
@@ -0,0 +1,350 @@
+use crate::domains::har::{
+    add_to_service, endpoints_from_har, entry_summaries, history_to_har, parse_har, run_to_har,
+    save_har, HarExportOptions, HarImportOptions,
+};
+use crate::domains::runner::{RunExchange, RunRequestResult, RunSummary};
+use crate::io::MockFileSystem;
+use crate::types::{Assertion, AssertionResult, Header, HistoryEntry, NameValue, Service};
+use mockall::predicate;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const SAMPLE_HAR: &str = include_str!("../../../../tests/sample.har");
+
+fn header(name: &str, value: &str) -> Header {
+    Header {
+        name: name.to_string(),
+        value: value.to_string(),
+        enabled: true,
+        secret_key: None,
+    }
+}
+
+fn names(values: &[NameValue]) -> Vec<&str> {
+    values.iter().map(|v| v.name.as_str()).collect()
+}
+
+#[test]
+fn test_har_entry_summaries() {
+    let har = parse_har(SAMPLE_HAR).unwrap();
+    let summaries = entry_summaries(&har);
+    assert_eq!(summaries.len(), 3);
+    assert_eq!(summaries[1].index, 1);
+    assert_eq!(summaries[1].method, "POST");
+    assert_eq!(summaries[1].url, "https://api.example.com/v1/pets");
+    assert_eq!(summaries[1].status, 201);
+    assert_eq!(summaries[1].mime_type, "application/json");
+    assert_eq!(summaries[2].status, 0);
+
+    assert!(parse_har("{}").unwrap_err().starts_with("Invalid HAR file"));
+}
+
+#[test]
+fn test_har_import_keeps_request_details() {
+    let har = parse_har(SAMPLE_HAR).unwrap();
+    let endpoints =
+        endpoints_from_har(&har, &[0, 1, 2], &HarImportOptions::default(), "svc").unwrap();
+    assert_eq!(endpoints.len(), 3);
+
+    let list = &endpoints[0];
+    assert_eq!(list.service_id, "svc");
+    assert_eq!(list.name, "GET /v1/pets");
+    assert_eq!(list.url, "https://api.example.com/v1/pets");
+    assert_eq!(names(&list.params), vec!["limit", "tag"]);
+    assert_eq!(list.params[0].value, "10");
+    // Pseudo and connection headers are dropped
+    assert_eq!(
+        names(&list.headers),
+        vec!["accept", "authorization", "cookie"]
+    );
+    assert_eq!(list.examples.len(), 1);
+    assert_eq!(list.examples[0].status, 200);
+    assert_eq!(list.examples[0].body, r#"[{"id":1,"name":"Rex"}]"#);
+    assert_eq!(names(&list.examples[0].headers), vec!["content-type"]);
+
+    let create = &endpoints[1];
+    assert_eq!(create.method, "POST");
+    assert_eq!(create.body, r#"{"name":"Rex"}"#);
+    assert_eq!(names(&create.headers), vec!["content-type", "x-api-key"]);
+
+    let login = &endpoints[2];
+    assert_eq!(login.body, "user=rex&pass=a+b%26c");
+    assert_eq!(names(&login.headers), vec!["Content-Type"]);
+    assert_eq!(login.headers[0].value, "application/x-www-form-urlencoded");
+    // No response arrived, so there is nothing to keep as an example
+    assert!(login.examples.is_empty());
+}
+
+#[test]
+fn test_har_import_strips_cookies_and_auth() {
+    let har = parse_har(SAMPLE_HAR).unwrap();
+    let options = HarImportOptions {
+        strip_cookies: true,
+        strip_auth: true,
+    };
+    let endpoints = endpoints_from_har(&har, &[1, 0], &options, "svc").unwrap();
+    assert_eq!(names(&endpoints[0].headers), vec!["content-type"]);
+    assert_eq!(names(&endpoints[1].headers), vec!["accept"]);
+    // Endpoints whose credentials were stripped send the service auth instead
+    assert!(endpoints[0].authenticated && endpoints[1].authenticated);
+
+    let cookies_only = HarImportOptions {
+        strip_cookies: true,
+        strip_auth: false,
+    };
+    let endpoints = endpoints_from_har(&har, &[0], &cookies_only, "svc").unwrap();
+    assert_eq!(
+        names(&endpoints[0].headers),
+        vec!["accept", "authorization"]
+    );
+    assert!(!endpoints[0].authenticated);
+
+    let err = endpoints_from_har(&har, &[3], &options, "svc").unwrap_err();
+    assert_eq!(err, "HAR file has no entry 3");
+}
+
+#[test]
+fn test_history_export_round_trip() {
+    let entry = HistoryEntry {
+        id: "h1".to_string(),
+        service_id: None,
+        endpoint_id: None,
+        method: "PUT".to_string(),
+        url: "https://api.example.com/v1/pets/2?notify=true".to_string(),
+        request_headers: vec![
+            header("Content-Type", "application/json"),
+            header("Authorization", "Bearer abc"),
+            Header {
+                enabled: false,
+                ..header("X-Debug", "1")
+            },
+        ],
+        request_body: r#"{"name":"Max"}"#.to_string(),
+        response_status: 200,
+        response_status_text: "OK".to_string(),
+        response_headers: vec![
+            header("Content-Type", "application/json"),
+            header("Set-Cookie", "session=s3cret"),
+        ],
+        response_body: r#"{"id":2}"#.to_string(),
+        time_elapsed: 35,
+        size: 8,
+        created_at: "2026-03-02T10:20:00+00:00".to_string(),
+        assertions: vec![],
+    };
+    let har = history_to_har(std::slice::from_ref(&entry), &HarExportOptions::default());
+    assert_eq!(har.log.version, "1.2");
+    assert_eq!(har.log.creator.name, "xrest");
+
+    let written = Arc::new(Mutex::new(String::new()));
+    let mut mock_fs = MockFileSystem::new();
+    mock_fs.expect_exists().returning(|_| false);
+    mock_fs
+        .expect_create_dir_all()
+        .with(predicate::eq(PathBuf::from("/exports")))
+        .times(1)
+        .returning(|_| Ok(()));
+    let sink = written.clone();
+    mock_fs
+        .expect_write()
+        .times(1)
+        .returning(move |_, content| {
+            *sink.lock().unwrap() = content.to_string();
+            Ok(())
+        });
+    save_har(&mock_fs, Path::new("/exports/debug.har"), &har).unwrap();
+    let content = written.lock().unwrap().clone();
+    let saved = parse_har(&content).unwrap();
+    assert_eq!(saved, har);
+
+    let exported = &saved.log.entries[0];
+    assert_eq!(exported.started_date_time, "2026-03-02T10:20:00+00:00");
+    assert_eq!(exported.time, 35.0);
+    assert_eq!(exported.request.headers.len(), 2);
+    // Credentials are redacted unless asked for
+    assert_eq!(exported.request.headers[1].value, "[redacted]");
+    assert_eq!(exported.response.headers[1].value, "[redacted]");
+    assert!(!content.contains("abc") && !content.contains("s3cret"));
+    let with_credentials = history_to_har(
+        &[entry],
+        &HarExportOptions {
+            include_credentials: true,
+        },
+    );
+    let kept = &with_credentials.log.entries[0];
+    assert_eq!(kept.request.headers[1].value, "Bearer abc");
+    assert_eq!(kept.response.headers[1].value, "session=s3cret");
+    assert_eq!(exported.request.query_string[0].name, "notify");
+    let post_data = exported.request.post_data.as_ref().unwrap();
+    assert_eq!(post_data.mime_type, "application/json");
+    assert_eq!(post_data.text, r#"{"name":"Max"}"#);
+    assert_eq!(exported.response.status, 200);
+    assert_eq!(
+        exported.response.content.text.as_deref(),
+        Some(r#"{"id":2}"#)
+    );
+
+    // An exported entry imports back as the same request
+    let endpoints = endpoints_from_har(&saved, &[0], &HarImportOptions::default(), "svc").unwrap();
+    assert_eq!(endpoints[0].url, "https://api.example.com/v1/pets/2");
+    assert_eq!(endpoints[0].body, r#"{"name":"Max"}"#);
+    assert_eq!(endpoints[0].examples[0].body, r#"{"id":2}"#);
+
+    let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+    assert!(json["log"]["entries"][0]["response"]["redirectURL"].is_string());
+    assert!(json["log"]["entries"][0]["response"]
+        .get("_error")
+        .is_none());
+}
+
+#[test]
+fn test_run_export() {
+    let result = |name: &str, status: Option<u16>| RunRequestResult {
+        iteration: None,
+        step_id: None,
+        skipped: false,
+        endpoint_id: name.to_lowercase(),
+        name: name.to_string(),
+        method: "GET".to_string(),
+        url: format!("https://api.example.com/{}", name.to_lowercase()),
+        status,
+        passed: status == Some(200),
+        time_elapsed: 12,
+        assertions: vec![],
+        script_errors: vec![],
+        error: None,
+        exchange: None,
+    };
+    let mut failed = result("Orders", Some(500));
+    failed.assertions.push(AssertionResult {
+        assertion: Assertion::Status { equals: 200 },
+        passed: false,
+        message: "Status is 500".to_string(),
+    });
+    let mut unreachable = result("Health", None);
+    unreachable.error = Some("connection refused".to_string());
+    let mut skipped = result("Cleanup", None);
+    skipped.skipped = true;
+
+    let mut pets = result("Pets", Some(200));
+    pets.exchange = Some(Box::new(RunExchange {
+        started_at: "2026-03-02T11:00:01+00:00".to_string(),
+        url: "https://api.example.com/pets?limit=5".to_string(),
+        request_headers: vec![header("Cookie", "session=s3cret")],
+        request_body: String::new(),
+        response_status_text: "OK".to_string(),
+        response_headers: vec![header("Content-Type", "application/json")],
+        response_body: "[]".to_string(),
+    }));
+
+    let summary = RunSummary {
+        service_id: "svc".to_string(),
+        environment: None,
+        total: 4,
+        passed: 1,
+        failed: 2,
+        skipped: 1,
+        time_elapsed: 50,
+        results: vec![pets, failed, unreachable, skipped],
+        variables: HashMap::new(),
+        iterations: vec![],
+    };
+    let har = run_to_har(
+        &summary,
+        "2026-03-02T11:00:00+00:00",
+        &HarExportOptions::default(),
+    );
+    let entries = &har.log.entries;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].comment.as_deref(), Some("Pets"));
+    // Each step carries what it sent and received
+    assert_eq!(entries[0].started_date_time, "2026-03-02T11:00:01+00:00");
+    assert_eq!(
+        entries[0].request.url,
+        "https://api.example.com/pets?limit=5"
+    );
+    assert_eq!(entries[0].request.query_string[0].name, "limit");
+    assert_eq!(entries[0].request.headers[0].value, "[redacted]");
+    assert_eq!(entries[0].response.status_text, "OK");
+    assert_eq!(entries[0].response.content.text.as_deref(), Some("[]"));
+    assert_eq!(entries[0].response.content.mime_type, "application/json");
+    // Results kept without an exchange fall back to the run's start
+    assert_eq!(entries[1].started_date_time, "2026-03-02T11:00:00+00:00");
+    assert_eq!(entries[1].response.status, 500);
+    assert_eq!(entries[1].comment.as_deref(), Some("Orders: Status is 500"));
+    assert_eq!(entries[2].response.status, 0);
+    assert_eq!(
+        entries[2].response.error.as_deref(),
+        Some("connection refused")
+    );
+    assert!(entries[2].request.post_data.is_none());
+}
+
+#[test]
+fn test_har_import_moves_the_origin_into_base_url() {
+    let har = parse_har(SAMPLE_HAR).unwrap();
+    let endpoints =
+        endpoints_from_har(&har, &[0, 1, 2], &HarImportOptions::default(), "svc").unwrap();
+    let mut other = endpoints[0].clone();
+    other.url = "https://cdn.example.com/v1/pets".to_string();
+
+    let mut service: Service = serde_yaml::from_str(
+        "id: svc\nname: Pets\nisAuthenticated: false\ndirectory: /tmp/pets\nenvironments: []\nendpoints: []",
+    )
+    .unwrap();
+    add_to_service(&mut service, endpoints.clone());
+    add_to_service(&mut service, vec![endpoints[0].clone(), other]);
+
+    // A service without environments gets one holding the origin
+    assert_eq!(service.environments.len(), 1);
+    assert_eq!(service.selected_environment.as_deref(), Some("Default"));
+    assert_eq!(service.environments[0].variables[0].name, "BASE_URL");
+    assert_eq!(
+        service.environments[0].variables[0].value,
+        "https://api.example.com"
+    );
+    let urls: Vec<&str> = service.endpoints.iter().map(|e| e.url.as_str()).collect();
+    // The repeated request is skipped and another origin keeps its full URL
+    assert_eq!(
+        urls,
+        vec![
+            "/v1/pets",
+            "/v1/pets",
+            "/v1/login",
+            "https://cdn.example.com/v1/pets"
+        ]
+    );
+}
+
+#[test]
+fn test_har_import_uses_the_services_base_url() {
+    let har = parse_har(SAMPLE_HAR).unwrap();
+    let endpoints = endpoints_from_har(&har, &[0], &HarImportOptions::default(), "svc").unwrap();
+    let mut service: Service = serde_yaml::from_str(
+        r#"
+id: svc
+name: Pets
+isAuthenticated: false
+directory: /tmp/pets
+selectedEnvironment: prod
+environments:
+  - name: dev
+    variables:
+      - { name: BASE_URL, value: "http://localhost:8080", enabled: true }
+  - name: prod
+    variables:
+      - { name: BASE_URL, value: "https://api.example.com/", enabled: true }
+endpoints: []
+"#,
+    )
+    .unwrap();
+    add_to_service(&mut service, endpoints);
+
+    assert_eq!(service.endpoints[0].url, "/v1/pets");
+    // Environments are left alone when the service has a base URL
+    assert_eq!(
+        service.environments[0].variables[0].value,
+        "http://localhost:8080"
+    );
+}
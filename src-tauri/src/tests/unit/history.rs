@@ -1,5 +1,5 @@
 use crate::history::HistoryService;
-use crate::types::HistoryEntry;
+use crate::types::{Assertion, AssertionResult, HistoryEntry};
 use rusqlite::Connection;
 
 #[test]
@@ -26,6 +26,11 @@ fn test_history_lifecycle() {
         time_elapsed: 10,
         size: 4,
         created_at: "2023-01-01T00:00:00Z".to_string(),
+        assertions: vec![AssertionResult {
+            assertion: Assertion::Status { equals: 200 },
+            passed: true,
+            message: "Status is 200".to_string(),
+        }],
     };
 
     assert!(service.save(entry.clone()).is_ok());
@@ -34,9 +39,76 @@ fn test_history_lifecycle() {
     let history = service.get_history(10, 0).unwrap();
     assert_eq!(history.len(), 1);
     assert_eq!(history[0].id, "h1");
+    assert_eq!(history[0].assertions, entry.assertions);
 
     // Clear history
     assert!(service.clear().is_ok());
     let history = service.get_history(10, 0).unwrap();
     assert_eq!(history.len(), 0);
 }
+
+#[test]
+fn test_init_adds_assertions_column_to_existing_history() {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute(
+        "CREATE TABLE history (
+            id TEXT PRIMARY KEY, service_id TEXT, endpoint_id TEXT, method TEXT NOT NULL,
+            url TEXT NOT NULL, request_headers TEXT NOT NULL, request_body TEXT NOT NULL,
+            response_status INTEGER NOT NULL, response_status_text TEXT NOT NULL,
+            response_headers TEXT NOT NULL, response_body TEXT NOT NULL,
+            time_elapsed INTEGER NOT NULL, size INTEGER NOT NULL, created_at TEXT NOT NULL
+        )",
+        [],
+    )
+    .unwrap();
+    conn.execute(
+        "INSERT INTO history VALUES ('old', NULL, NULL, 'GET', '/', '[]', '', 200, 'OK', '[]', '', 1, 0, '2023-01-01T00:00:00Z')",
+        [],
+    )
+    .unwrap();
+
+    let service = HistoryService::new(conn);
+    service.init().unwrap();
+    // A second init must not try to add the column again
+    service.init().unwrap();
+
+    let history = service.get_history(10, 0).unwrap();
+    assert_eq!(history[0].id, "old");
+    assert!(history[0].assertions.is_empty());
+}
+
+#[test]
+fn test_get_entries_by_id() {
+    let service = HistoryService::new(Connection::open_in_memory().unwrap());
+    service.init().unwrap();
+    for (id, created_at) in [("b", "2023-01-02T00:00:00Z"), ("a", "2023-01-01T00:00:00Z")] {
+        service
+            .save(HistoryEntry {
+                id: id.to_string(),
+                service_id: None,
+                endpoint_id: None,
+                method: "GET".to_string(),
+                url: "/".to_string(),
+                request_headers: vec![],
+                request_body: "".to_string(),
+                response_status: 200,
+                response_status_text: "OK".to_string(),
+                response_headers: vec![],
+                response_body: "".to_string(),
+                time_elapsed: 1,
+                size: 0,
+                created_at: created_at.to_string(),
+                assertions: vec![],
+            })
+            .unwrap();
+    }
+
+    let entries = service
+        .get_entries(&["b".to_string(), "a".to_string(), "missing".to_string()])
+        .unwrap();
+    assert_eq!(
+        entries.iter().map(|e| e.id.as_str()).collect::<Vec<_>>(),
+        vec!["a", "b"]
+    );
+    assert!(service.get_entries(&[]).unwrap().is_empty());
+}
@@ -0,0 +1,252 @@
+use crate::domains::load_test::{
+    histogram, latency_stats, run, save_report, LoadMode, LoadTestOptions, LoadTestProgress,
+    MAX_CONCURRENCY,
+};
+use crate::io::{MockFileSystem, MockHttpClient};
+use crate::services::RequestService;
+use crate::types::{QResponse, Service};
+use mockall::predicate;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+const SERVICE_YAML: &str = r#"
+id: svc-load
+name: Orders
+isAuthenticated: false
+directory: /tmp/orders
+selectedEnvironment: dev
+environments:
+  - name: dev
+    variables:
+      - { name: BASE_URL, value: "https://dev.example.com", enabled: true }
+  - name: prod
+    isUnsafe: true
+    variables:
+      - { name: BASE_URL, value: "https://prod.example.com", enabled: true }
+endpoints:
+  - { id: list, name: List, method: GET, url: /orders }
+"#;
+
+fn options(mode: LoadMode) -> LoadTestOptions {
+    LoadTestOptions {
+        endpoint_id: "list".to_string(),
+        environment: None,
+        variables: HashMap::new(),
+        mode,
+        allow_unsafe: false,
+    }
+}
+
+fn response(status: u16, time_elapsed: u64) -> QResponse {
+    QResponse {
+        status,
+        status_text: String::new(),
+        headers: vec![],
+        body: String::new(),
+        error: None,
+        time_elapsed,
+        size: 0,
+        scripts: None,
+        assertions: vec![],
+    }
+}
+
+#[test]
+fn test_latency_percentiles_and_histogram() {
+    let sorted: Vec<u64> = (1..=100).collect();
+    let stats = latency_stats(&sorted);
+    assert_eq!(
+        (stats.min, stats.p50, stats.p90, stats.p99, stats.max, stats.mean),
+        (1, 50, 90, 99, 100, 50)
+    );
+    assert_eq!(latency_stats(&[]).max, 0);
+
+    let buckets = histogram(&[3, 5, 6, 700, 20_000]);
+    assert_eq!(buckets[0].le_ms, Some(5));
+    assert_eq!(buckets[0].count, 2);
+    assert_eq!(buckets[1].count, 1);
+    assert_eq!(buckets[7].count, 1);
+    assert_eq!(buckets.last().unwrap().le_ms, None);
+    assert_eq!(buckets.last().unwrap().count, 1);
+}
+
+#[tokio::test]
+async fn test_count_mode_collects_statuses_and_errors() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let sent = Arc::new(AtomicUsize::new(0));
+    let counter = sent.clone();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::eq("https://dev.example.com/orders"),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(20)
+        .returning(move |_, _, _, _, _| {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                match n % 5 {
+                    0 => Err("connection reset".to_string()),
+                    1 => Ok(response(503, 40)),
+                    _ => Ok(response(200, 10)),
+                }
+            })
+        });
+
+    let events = Mutex::new(Vec::new());
+    let report = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &options(LoadMode::Count {
+            requests: 20,
+            concurrency: 4,
+        }),
+        &|progress| events.lock().unwrap().push(progress),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.total, 20);
+    assert_eq!(report.errors, 4);
+    assert_eq!(report.status_codes.get(&200), Some(&12));
+    assert_eq!(report.status_codes.get(&503), Some(&4));
+    assert_eq!(report.error_messages.get("connection reset"), Some(&4));
+    assert_eq!((report.latency.p50, report.latency.max), (10, 40));
+    assert_eq!(report.environment.as_deref(), Some("dev"));
+
+    let events = events.into_inner().unwrap();
+    assert!(matches!(events[0], LoadTestProgress::Started { .. }));
+    assert!(matches!(
+        events.last(),
+        Some(LoadTestProgress::Finished { report: finished }) if *finished == report
+    ));
+}
+
+#[tokio::test]
+async fn test_rate_mode_stops_after_the_duration() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(response(200, 1)) }));
+
+    let report = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &options(LoadMode::Rate {
+            rps: 50,
+            duration_secs: 1,
+        }),
+        &|_| {},
+    )
+    .await
+    .unwrap();
+    assert!(report.total > 25 && report.total <= 51, "{}", report.total);
+    assert!(report.duration_ms < 2000);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_mode_drops_requests_beyond_the_in_flight_limit() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    // Every request outlives the run, so slots are never freed
+    mock_http.expect_send_request().returning(|_, _, _, _, _| {
+        Box::pin(async {
+            tokio::time::sleep(std::time::Duration::from_millis(1200)).await;
+            Ok(response(200, 1200))
+        })
+    });
+
+    let report = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &options(LoadMode::Rate {
+            rps: 1000,
+            duration_secs: 1,
+        }),
+        &|_| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(report.total, MAX_CONCURRENCY);
+    assert!(report.dropped > 0);
+}
+
+#[tokio::test]
+async fn test_unsafe_environments_and_invalid_modes_are_refused() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(response(200, 1)) }));
+    let request_service = RequestService::new(&mock_http, None);
+    let single = LoadMode::Count {
+        requests: 1,
+        concurrency: 1,
+    };
+
+    let mut prod = options(single.clone());
+    prod.environment = Some("prod".to_string());
+    let err = run(&request_service, &service, &[], &prod, &|_| {})
+        .await
+        .unwrap_err();
+    assert!(err.contains("unsafe"));
+
+    prod.allow_unsafe = true;
+    let report = run(&request_service, &service, &[], &prod, &|_| {})
+        .await
+        .unwrap();
+    assert_eq!(report.total, 1);
+
+    let no_concurrency = options(LoadMode::Count {
+        requests: 5,
+        concurrency: 0,
+    });
+    assert!(
+        run(&request_service, &service, &[], &no_concurrency, &|_| {})
+            .await
+            .is_err()
+    );
+}
+
+#[test]
+fn test_save_report_writes_json() {
+    let report: crate::domains::load_test::LoadTestReport = serde_json::from_value(
+        serde_json::json!({
+            "serviceId": "svc", "endpointId": "list", "environment": null,
+            "mode": { "type": "count", "requests": 1, "concurrency": 1 },
+            "startedAt": "2026-01-01T00:00:00Z", "durationMs": 5, "total": 1, "errors": 0,
+            "rps": 200.0, "latency": { "min": 5, "mean": 5, "p50": 5, "p90": 5, "p99": 5, "max": 5 },
+            "histogram": [], "statusCodes": { "200": 1 }, "errorMessages": {}
+        }),
+    )
+    .unwrap();
+    let mut mock_fs = MockFileSystem::new();
+    mock_fs.expect_exists().returning(|_| false);
+    mock_fs
+        .expect_create_dir_all()
+        .with(predicate::eq(PathBuf::from("/reports")))
+        .times(1)
+        .returning(|_| Ok(()));
+    let expected = report.clone();
+    mock_fs
+        .expect_write()
+        .times(1)
+        .returning(move |_, content| {
+            let saved: crate::domains::load_test::LoadTestReport =
+                serde_json::from_str(content).unwrap();
+            assert_eq!(saved, expected);
+            Ok(())
+        });
+    save_report(&mock_fs, Path::new("/reports/list.json"), &report).unwrap();
+}
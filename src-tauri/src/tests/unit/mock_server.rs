@@ -0,0 +1,181 @@
+use crate::domains::mock_server::{
+    match_endpoint, request_log, respond, running_servers, start, stop, MockServerOptions,
+};
+use crate::types::Service;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const SERVICE_YAML: &str = r#"
+id: svc-mock
+name: Pets
+isAuthenticated: false
+directory: /tmp/pets
+environments: []
+endpoints:
+  - id: list
+    name: List pets
+    method: GET
+    url: "{{BASE_URL}}/pets"
+    examples:
+      - name: "200"
+        status: 200
+        headers:
+          - { name: Content-Type, value: application/json, enabled: true }
+        body: '[{"id": 1}]'
+  - id: get
+    name: Get pet
+    method: GET
+    url: /pets/{petId}
+    examples:
+      - name: "404 missing"
+        status: 404
+        body: '{"error": "missing"}'
+      - name: "200"
+        status: 200
+        body: '{"id": 1}'
+  - id: mine
+    name: My pets
+    method: GET
+    url: https://api.example.com/pets/mine
+  - id: delete
+    name: Delete pet
+    method: DELETE
+    url: /pets/:petId
+"#;
+
+fn service(id: &str) -> Service {
+    let mut service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    service.id = id.to_string();
+    service
+}
+
+fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_match_endpoint_prefers_literal_segments() {
+    let endpoints = service("svc-mock").endpoints;
+    let matched =
+        |method: &str, path: &str| match_endpoint(&endpoints, method, path).map(|e| e.id.as_str());
+    assert_eq!(matched("GET", "/pets"), Some("list"));
+    assert_eq!(matched("get", "/pets/?limit=2"), Some("list"));
+    assert_eq!(matched("GET", "/pets/7"), Some("get"));
+    assert_eq!(matched("GET", "/pets/mine"), Some("mine"));
+    assert_eq!(matched("DELETE", "/pets/7"), Some("delete"));
+    assert_eq!(matched("POST", "/pets"), None);
+    assert_eq!(matched("GET", "/pets/7/toys"), None);
+}
+
+#[test]
+fn test_respond_picks_examples() {
+    let endpoints = service("svc-mock").endpoints;
+    let response = respond(&endpoints, "GET", "/pets/7", &HashMap::new());
+    assert_eq!(response.status, 200);
+    assert_eq!(response.example.as_deref(), Some("200"));
+
+    let response = respond(
+        &endpoints,
+        "GET",
+        "/pets/7",
+        &headers(&[("x-mock-status", "404")]),
+    );
+    assert_eq!(response.status, 404);
+    assert_eq!(response.body, r#"{"error": "missing"}"#);
+
+    let response = respond(
+        &endpoints,
+        "GET",
+        "/pets/7",
+        &headers(&[("x-mock-example", "404 missing")]),
+    );
+    assert_eq!(response.example.as_deref(), Some("404 missing"));
+
+    let response = respond(&endpoints, "GET", "/pets/mine", &HashMap::new());
+    assert_eq!(response.status, 501);
+    assert_eq!(response.endpoint_id.as_deref(), Some("mine"));
+
+    let response = respond(&endpoints, "GET", "/owners", &HashMap::new());
+    assert_eq!(response.status, 404);
+    assert_eq!(response.endpoint_id, None);
+}
+
+#[tokio::test]
+async fn test_mock_server_serves_and_logs_requests() {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let info = start(
+        &service("svc-mock-serve"),
+        MockServerOptions::default(),
+        Arc::new(move |entry| sink.lock().unwrap().push(entry)),
+    )
+    .await
+    .unwrap();
+    assert!(info.port > 0);
+    assert!(running_servers()
+        .iter()
+        .any(|s| s.service_id == "svc-mock-serve"));
+    assert!(start(
+        &service("svc-mock-serve"),
+        MockServerOptions::default(),
+        Arc::new(|_| {})
+    )
+    .await
+    .unwrap_err()
+    .contains("already running"));
+
+    let response = reqwest::get(format!("{}/pets?limit=1", info.url))
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "application/json"
+    );
+    assert_eq!(response.text().await.unwrap(), r#"[{"id": 1}]"#);
+
+    let log = request_log("svc-mock-serve").unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].path, "/pets?limit=1");
+    assert_eq!(log[0].endpoint_id.as_deref(), Some("list"));
+    assert_eq!(received.lock().unwrap().clone(), log);
+
+    stop("svc-mock-serve").unwrap();
+    assert!(request_log("svc-mock-serve").is_err());
+    assert!(stop("svc-mock-serve").is_err());
+}
+
+#[tokio::test]
+async fn test_mock_server_injects_errors_and_latency() {
+    let options = MockServerOptions {
+        latency_ms: 50,
+        error_rate: 1.0,
+        error_status: 503,
+        ..Default::default()
+    };
+    let info = start(&service("svc-mock-errors"), options, Arc::new(|_| {}))
+        .await
+        .unwrap();
+
+    let started = std::time::Instant::now();
+    let response = reqwest::get(format!("{}/pets/1", info.url)).await.unwrap();
+    assert!(started.elapsed().as_millis() >= 50);
+    assert_eq!(response.status().as_u16(), 503);
+    let log = request_log("svc-mock-errors").unwrap();
+    assert!(log[0].injected_error);
+    assert_eq!(log[0].endpoint_id.as_deref(), Some("get"));
+    stop("svc-mock-errors").unwrap();
+
+    let invalid = MockServerOptions {
+        error_rate: 2.0,
+        ..Default::default()
+    };
+    assert!(
+        start(&service("svc-mock-invalid"), invalid, Arc::new(|_| {}))
+            .await
+            .is_err()
+    );
+}
@@ -1,8 +1,30 @@
 #[cfg(test)]
+pub mod assertions;
+#[cfg(test)]
+pub mod auth;
+#[cfg(test)]
+pub mod cli;
+#[cfg(test)]
 pub mod commands;
 #[cfg(test)]
+pub mod data_file;
+#[cfg(test)]
 pub mod domains_integration;
 #[cfg(test)]
+pub mod har;
+#[cfg(test)]
 pub mod history;
 #[cfg(test)]
+pub mod import;
+#[cfg(test)]
+pub mod load_test;
+#[cfg(test)]
+pub mod mock_server;
+#[cfg(test)]
+pub mod monitor;
+#[cfg(test)]
+pub mod proxy;
+#[cfg(test)]
+pub mod runner;
+#[cfg(test)]
 pub mod services;
@@ -0,0 +1,254 @@
+use crate::domains::monitor::{
+    failure_reason, load_monitors, run_monitor, run_monitor_with_timeout, save_monitors, Monitor,
+    MonitorResult, MonitorScheduler, MonitorStore, MonitorTarget,
+};
+use crate::domains::service::workflow::Workflow;
+use crate::io::{MockFileSystem, MockHttpClient};
+use crate::services::RequestService;
+use crate::types::{Assertion, QResponse, Service};
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SERVICE_YAML: &str = r#"
+id: svc-monitor
+name: Orders
+isAuthenticated: false
+directory: /tmp/orders
+selectedEnvironment: dev
+environments:
+  - name: dev
+    variables:
+      - { name: BASE_URL, value: "https://dev.example.com", enabled: true }
+endpoints:
+  - id: health
+    name: Health
+    method: GET
+    url: /health
+  - id: orders
+    name: List orders
+    method: GET
+    url: /orders
+"#;
+
+const MONITORS_YAML: &str = r#"
+- id: m-health
+  name: Health
+  serviceId: svc-monitor
+  target: { type: endpoint, endpointId: health }
+  intervalSecs: 60
+  assertions:
+    - { type: responseTimeBelow, ms: 500 }
+- id: m-checkout
+  name: Checkout
+  serviceId: svc-monitor
+  target: { type: workflow, workflowId: checkout }
+  environment: dev
+  intervalSecs: 300
+  enabled: false
+"#;
+
+fn response(status: u16, time_elapsed: u64) -> QResponse {
+    QResponse {
+        status,
+        status_text: String::new(),
+        headers: vec![],
+        body: "{}".to_string(),
+        error: None,
+        time_elapsed,
+        size: 2,
+        scripts: None,
+        assertions: vec![],
+    }
+}
+
+fn monitor(id: &str, interval_secs: u64) -> Monitor {
+    Monitor {
+        id: id.to_string(),
+        name: id.to_string(),
+        service_id: "svc-monitor".to_string(),
+        target: MonitorTarget::Endpoint {
+            endpoint_id: "health".to_string(),
+        },
+        environment: None,
+        interval_secs,
+        assertions: vec![],
+        enabled: true,
+    }
+}
+
+fn result(id: &str, started_at: &str, passed: bool) -> MonitorResult {
+    MonitorResult {
+        id: id.to_string(),
+        monitor_id: "m-health".to_string(),
+        service_id: "svc-monitor".to_string(),
+        started_at: started_at.to_string(),
+        passed,
+        time_elapsed: 3,
+        error: None,
+        results: vec![],
+    }
+}
+
+#[test]
+fn test_load_and_save_monitors() {
+    let mut mock_fs = MockFileSystem::new();
+    mock_fs.expect_exists().returning(|_| true);
+    mock_fs
+        .expect_read_to_string()
+        .returning(|_| Ok(MONITORS_YAML.to_string()));
+    let written = Arc::new(Mutex::new(String::new()));
+    let sink = written.clone();
+    mock_fs
+        .expect_write()
+        .times(1)
+        .returning(move |_, content| {
+            *sink.lock().unwrap() = content.to_string();
+            Ok(())
+        });
+
+    let path = Path::new("/config/monitors.yaml");
+    let monitors = load_monitors(&mock_fs, path).unwrap();
+    assert_eq!(monitors.len(), 2);
+    assert!(monitors[0].enabled);
+    assert_eq!(
+        monitors[0].assertions,
+        vec![Assertion::ResponseTimeBelow { ms: 500 }]
+    );
+    assert_eq!(
+        monitors[1].target,
+        MonitorTarget::Workflow {
+            workflow_id: "checkout".to_string()
+        }
+    );
+    assert!(!monitors[1].enabled);
+
+    save_monitors(&mock_fs, path, &monitors).unwrap();
+    let saved: Vec<Monitor> = serde_yaml::from_str(&written.lock().unwrap()).unwrap();
+    assert_eq!(saved, monitors);
+
+    let err = save_monitors(&mock_fs, path, &[monitor("fast", 1)]).unwrap_err();
+    assert!(err.contains("at least 10 seconds"));
+    let err = save_monitors(&mock_fs, path, &[monitor("m", 60), monitor("m", 60)]).unwrap_err();
+    assert!(err.contains("unique"));
+}
+
+#[test]
+fn test_scheduler_runs_monitors_once_per_interval() {
+    let mut scheduler = MonitorScheduler::default();
+    let mut disabled = monitor("off", 10);
+    disabled.enabled = false;
+    let monitors = vec![monitor("fast", 10), monitor("slow", 60), disabled];
+    let start = Instant::now();
+
+    let ids = |due: Vec<Monitor>| due.into_iter().map(|m| m.id).collect::<Vec<_>>();
+    assert_eq!(ids(scheduler.due(&monitors, start)), vec!["fast", "slow"]);
+    assert!(scheduler
+        .due(&monitors, start + Duration::from_secs(5))
+        .is_empty());
+    assert_eq!(
+        ids(scheduler.due(&monitors, start + Duration::from_secs(10))),
+        vec!["fast"]
+    );
+    assert_eq!(
+        ids(scheduler.due(&monitors, start + Duration::from_secs(60))),
+        vec!["fast", "slow"]
+    );
+}
+
+#[test]
+fn test_store_reports_flip_from_passing_to_failing() {
+    let store = MonitorStore::new(Connection::open_in_memory().unwrap());
+    store.init().unwrap();
+    // Init is safe to run on every start
+    store.init().unwrap();
+
+    // A monitor without results counts as passing
+    assert!(!store
+        .record(&result("r1", "2026-01-01T00:00:00Z", true))
+        .unwrap());
+    assert!(store
+        .record(&result("r2", "2026-01-01T00:01:00Z", false))
+        .unwrap());
+    // Still failing is not a new flip
+    assert!(!store
+        .record(&result("r3", "2026-01-01T00:02:00Z", false))
+        .unwrap());
+    assert!(!store
+        .record(&result("r4", "2026-01-01T00:03:00Z", true))
+        .unwrap());
+
+    let results = store.get_results(Some("m-health"), 2).unwrap();
+    assert_eq!(
+        results.iter().map(|r| r.id.as_str()).collect::<Vec<_>>(),
+        vec!["r4", "r3"]
+    );
+    assert_eq!(store.get_results(None, 10).unwrap().len(), 4);
+    assert!(store.get_results(Some("other"), 10).unwrap().is_empty());
+
+    store.clear("m-health").unwrap();
+    assert!(store.get_results(None, 10).unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_run_monitor_adds_its_assertions() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .returning(|_, url, _, _, _| {
+            assert_eq!(url, "https://dev.example.com/health");
+            Box::pin(async { Ok(response(200, 900)) })
+        });
+    let request_service = RequestService::new(&mock_http, None);
+    let mut slow = monitor("m-health", 60);
+    slow.assertions = vec![Assertion::ResponseTimeBelow { ms: 500 }];
+
+    let result = run_monitor(&request_service, &service, &[], &[], &slow).await;
+    assert!(!result.passed);
+    assert_eq!(result.monitor_id, "m-health");
+    assert_eq!(result.results.len(), 1);
+    assert!(failure_reason(&result).starts_with("Health: "));
+}
+
+#[tokio::test]
+async fn test_run_monitor_reports_missing_workflow() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mock_http = MockHttpClient::new();
+    let request_service = RequestService::new(&mock_http, None);
+    let mut checkout = monitor("m-checkout", 60);
+    checkout.target = MonitorTarget::Workflow {
+        workflow_id: "checkout".to_string(),
+    };
+    let workflows: Vec<Workflow> = vec![];
+
+    let result = run_monitor(&request_service, &service, &[], &workflows, &checkout).await;
+    assert!(!result.passed);
+    assert_eq!(failure_reason(&result), "Workflow 'checkout' not found");
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_monitor_run_times_out_at_its_interval() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http.expect_send_request().returning(|_, _, _, _, _| {
+        Box::pin(async {
+            tokio::time::sleep(Duration::from_secs(120)).await;
+            Ok(response(200, 120_000))
+        })
+    });
+    let request_service = RequestService::new(&mock_http, None);
+
+    let result = run_monitor_with_timeout(
+        &request_service,
+        &service,
+        &[],
+        &[],
+        &monitor("m-health", 60),
+    )
+    .await;
+    assert!(!result.passed);
+    assert_eq!(failure_reason(&result), "Monitor run timed out after 60 s");
+}
@@ -0,0 +1,333 @@
+use crate::domains::proxy::{
+    add_to_service, endpoints_from_history, exchanges, path_template, replay, start, stop,
+    ProxyMode, ProxyOptions,
+};
+use crate::types::{Header, HistoryEntry, Service};
+use mockito::Matcher;
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+fn header(name: &str, value: &str) -> Header {
+    Header {
+        name: name.to_string(),
+        value: value.to_string(),
+        enabled: true,
+        secret_key: None,
+    }
+}
+
+fn entry(method: &str, url: &str, status: u16, body: &str) -> HistoryEntry {
+    HistoryEntry {
+        id: format!("{} {} {}", method, url, status),
+        service_id: None,
+        endpoint_id: None,
+        method: method.to_string(),
+        url: url.to_string(),
+        request_headers: vec![
+            header("Accept", "application/json"),
+            header("Authorization", "Bearer secret"),
+        ],
+        request_body: String::new(),
+        response_status: status,
+        response_status_text: String::new(),
+        response_headers: vec![
+            header("Content-Type", "application/json"),
+            header("Date", "today"),
+        ],
+        response_body: body.to_string(),
+        time_elapsed: 4,
+        size: body.len() as u64,
+        created_at: "2026-01-01T00:00:00Z".to_string(),
+        assertions: vec![],
+    }
+}
+
+fn options(mode: ProxyMode) -> ProxyOptions {
+    ProxyOptions {
+        port: 0,
+        upstream: "https://api.example.com/v1".to_string(),
+        mode,
+        service_id: Some("svc-proxy".to_string()),
+    }
+}
+
+#[test]
+fn test_path_template_names_id_segments() {
+    assert_eq!(path_template("/users/42"), "/users/{userId}");
+    assert_eq!(
+        path_template("/users/42/orders/9b2f6a1c-3d4e-4f5a-8b6c-7d8e9f0a1b2c"),
+        "/users/{userId}/orders/{orderId}"
+    );
+    assert_eq!(path_template("/blobs/5f3a9c2e7b1d"), "/blobs/{blobId}");
+    assert_eq!(path_template("/7/8"), "/{id}/{id2}");
+    assert_eq!(path_template("/users/me"), "/users/me");
+    assert_eq!(path_template("/"), "/");
+}
+
+#[test]
+fn test_endpoints_from_history_groups_requests() {
+    let entries = vec![
+        entry(
+            "GET",
+            "https://api.example.com/v1/users/1?expand=roles",
+            200,
+            "{}",
+        ),
+        entry(
+            "GET",
+            "https://api.example.com/v1/users/2",
+            200,
+            "{\"id\": 2}",
+        ),
+        entry(
+            "GET",
+            "https://api.example.com/v1/users/3?page=2",
+            404,
+            "{}",
+        ),
+        entry("post", "https://api.example.com/v1/users", 201, "{}"),
+    ];
+    let base = Url::parse("https://api.example.com/v1/").unwrap();
+    let endpoints = endpoints_from_history(&entries, Some(&base), "svc-proxy");
+
+    assert_eq!(endpoints.len(), 2);
+    let users = &endpoints[0];
+    assert_eq!(users.method, "GET");
+    assert_eq!(users.url, "/users/{userId}");
+    assert_eq!(users.service_id, "svc-proxy");
+    assert_eq!(
+        users
+            .params
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["expand", "page"]
+    );
+    assert_eq!(
+        users.headers,
+        vec![header("Accept", "application/json")],
+        "credentials stay out of endpoint definitions"
+    );
+    assert_eq!(
+        users.examples.iter().map(|e| e.status).collect::<Vec<_>>(),
+        vec![200, 404]
+    );
+    assert_eq!(
+        users.examples[0].headers,
+        vec![header("Content-Type", "application/json")]
+    );
+    assert_eq!(endpoints[1].method, "POST");
+    assert_eq!(endpoints[1].url, "/users");
+    // The recorded requests carried credentials, which the service auth now supplies
+    assert!(users.authenticated);
+
+    let without_base = endpoints_from_history(&entries[..1], None, "svc-proxy");
+    assert_eq!(without_base[0].url, "/v1/users/{userId}");
+}
+
+#[test]
+fn test_recorded_endpoints_set_base_url_on_a_service_without_environments() {
+    let service_yaml =
+        "id: svc-proxy\nname: Proxy\nisAuthenticated: false\ndirectory: /tmp/proxy\nenvironments: []\nendpoints: []";
+    let entries = vec![
+        entry("GET", "https://api.example.com/v1/users/1", 200, "{}"),
+        entry("GET", "https://api.example.com/v1/users/2", 200, "{}"),
+    ];
+    let base = Url::parse("https://api.example.com/v1/").unwrap();
+
+    let mut service: Service = serde_yaml::from_str(service_yaml).unwrap();
+    add_to_service(&mut service, &entries, Some(&base));
+    add_to_service(&mut service, &entries, Some(&base));
+    assert_eq!(service.endpoints.len(), 1);
+    assert_eq!(service.endpoints[0].url, "/users/{userId}");
+    assert_eq!(service.selected_environment.as_deref(), Some("Default"));
+    let variables = &service.environments[0].variables;
+    assert_eq!(variables.len(), 1);
+    assert_eq!(variables[0].name, "BASE_URL");
+    assert_eq!(variables[0].value, "https://api.example.com/v1");
+
+    // Without an upstream the paths are absolute, so the origin is the base
+    let mut service: Service = serde_yaml::from_str(service_yaml).unwrap();
+    add_to_service(&mut service, &entries, None);
+    assert_eq!(service.endpoints[0].url, "/v1/users/{userId}");
+    assert_eq!(
+        service.environments[0].variables[0].value,
+        "https://api.example.com"
+    );
+}
+
+#[test]
+fn test_replay_prefers_exact_exchanges() {
+    let recording = vec![
+        entry("GET", "https://api.example.com/v1/users/1", 200, "old"),
+        entry("GET", "https://api.example.com/v1/users/1", 200, "new"),
+        entry("GET", "https://api.example.com/v1/users/2?x=1", 200, "two"),
+    ];
+    let base = Url::parse("https://api.example.com/v1").unwrap();
+    let endpoints = endpoints_from_history(&recording, Some(&base), "");
+
+    let response = replay(&recording, &endpoints, Some(&base), "GET", "/users/1");
+    assert_eq!(response.body, "new");
+    let response = replay(&recording, &endpoints, Some(&base), "GET", "/users/2?x=1");
+    assert_eq!(response.body, "two");
+    // Unrecorded ids fall back to the examples of the inferred endpoint
+    let response = replay(&recording, &endpoints, Some(&base), "GET", "/users/5");
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, "old");
+    let response = replay(&recording, &endpoints, Some(&base), "DELETE", "/users/1");
+    assert_eq!(response.status, 404);
+}
+
+/// Options for a recording proxy in front of a local upstream
+fn record_options(upstream: &str) -> ProxyOptions {
+    ProxyOptions {
+        upstream: format!("{}/v1", upstream),
+        ..options(ProxyMode::Record)
+    }
+}
+
+#[tokio::test]
+async fn test_proxy_forwards_and_records_exchanges() {
+    let mut upstream = mockito::Server::new_async().await;
+    let created = upstream
+        .mock("POST", "/v1/users?notify=true")
+        .match_header("x-trace", "t1")
+        .match_header("accept-encoding", Matcher::Missing)
+        .match_body(r#"{"name":"ada"}"#)
+        .with_status(201)
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"id": 7}"#)
+        .expect(1)
+        .create_async()
+        .await;
+    let recorded = Arc::new(Mutex::new(Vec::new()));
+    let sink = recorded.clone();
+    let info = start(
+        record_options(&upstream.url()),
+        vec![],
+        Arc::new(move |entry| sink.lock().unwrap().push(entry)),
+    )
+    .await
+    .unwrap();
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/users?notify=true", info.url))
+        .header("x-trace", "t1")
+        .body(r#"{"name":"ada"}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(response.text().await.unwrap(), r#"{"id": 7}"#);
+    created.assert_async().await;
+
+    let exchanges = exchanges(&info.id).unwrap();
+    assert_eq!(exchanges.len(), 1);
+    assert_eq!(exchanges[0].service_id.as_deref(), Some("svc-proxy"));
+    assert_eq!(exchanges[0].response_status, 201);
+    assert_eq!(exchanges[0].request_body, r#"{"name":"ada"}"#);
+    let recorded: Vec<String> = recorded
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|e| e.id.clone())
+        .collect();
+    assert_eq!(recorded, vec![exchanges[0].id.clone()]);
+    stop(&info.id).unwrap();
+    assert!(stop(&info.id).is_err());
+}
+
+#[tokio::test]
+async fn test_proxy_passes_any_method_and_redirects_through() {
+    let mut upstream = mockito::Server::new_async().await;
+    upstream
+        .mock("OPTIONS", "/v1/users")
+        .with_status(204)
+        .with_header("allow", "GET, POST, OPTIONS")
+        .create_async()
+        .await;
+    upstream
+        .mock("GET", "/v1/old")
+        .with_status(302)
+        .with_header("location", "/v1/new")
+        .create_async()
+        .await;
+    let info = start(record_options(&upstream.url()), vec![], Arc::new(|_| {}))
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let response = client
+        .request(reqwest::Method::OPTIONS, format!("{}/users", info.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 204);
+    assert_eq!(response.headers()["allow"], "GET, POST, OPTIONS");
+
+    // The client sees the redirect instead of the page it points to
+    let response = client
+        .get(format!("{}/old", info.url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 302);
+    assert_eq!(response.headers()["location"], "/v1/new");
+    stop(&info.id).unwrap();
+}
+
+#[tokio::test]
+async fn test_proxy_forwards_binary_bodies_unchanged() {
+    let request_body: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0xff, 0x00, 0xfe];
+    let response_body: Vec<u8> = vec![0x1f, 0x8b, 0x08, 0x00, 0xff, 0xc3];
+    let mut upstream = mockito::Server::new_async().await;
+    let upload = upstream
+        .mock("PUT", "/v1/avatar")
+        .match_body(request_body.clone())
+        .with_status(200)
+        .with_header("content-type", "application/octet-stream")
+        .with_body(response_body.clone())
+        .expect(1)
+        .create_async()
+        .await;
+    let info = start(record_options(&upstream.url()), vec![], Arc::new(|_| {}))
+        .await
+        .unwrap();
+
+    let response = reqwest::Client::new()
+        .put(format!("{}/avatar", info.url))
+        .body(request_body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.bytes().await.unwrap().to_vec(), response_body);
+    upload.assert_async().await;
+    stop(&info.id).unwrap();
+}
+
+#[tokio::test]
+async fn test_proxy_replays_without_upstream() {
+    let recording = vec![entry(
+        "GET",
+        "https://api.example.com/v1/users/1",
+        200,
+        "ada",
+    )];
+    let info = start(options(ProxyMode::Replay), recording, Arc::new(|_| {}))
+        .await
+        .unwrap();
+
+    let response = reqwest::get(format!("{}/users/1", info.url)).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.text().await.unwrap(), "ada");
+    assert!(exchanges(&info.id).unwrap().is_empty());
+    stop(&info.id).unwrap();
+
+    assert!(start(options(ProxyMode::Replay), vec![], Arc::new(|_| {}))
+        .await
+        .is_err());
+}
@@ -0,0 +1,531 @@
+use crate::domains::runner::{run, run_workflow, RunOptions, RunProgress, WorkflowOptions};
+use crate::domains::service::workflow::Workflow;
+use crate::io::MockHttpClient;
+use crate::services::RequestService;
+use crate::types::{QResponse, Service};
+use mockall::predicate;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+const SERVICE_YAML: &str = r#"
+id: svc-runner
+name: Orders
+isAuthenticated: false
+directory: /tmp/orders
+selectedEnvironment: dev
+environments:
+  - name: dev
+    variables:
+      - { name: BASE_URL, value: "https://dev.example.com", enabled: true }
+  - name: prod
+    variables:
+      - { name: BASE_URL, value: "https://prod.example.com", enabled: true }
+endpoints:
+  - id: login
+    name: Login
+    method: POST
+    url: /login
+    scripts:
+      postResponse: variables.token = response.json.access_token;
+    assertions:
+      - { type: status, equals: 200 }
+  - id: orders
+    name: List orders
+    method: GET
+    url: /orders
+    headers:
+      - { name: X-Token, value: "{{token}}", enabled: true }
+    assertions:
+      - { type: jsonPathExists, path: $.orders }
+  - id: health
+    name: Health
+    method: GET
+    url: /health
+"#;
+
+fn ok(body: &'static str) -> QResponse {
+    QResponse {
+        status: 200,
+        status_text: "OK".to_string(),
+        headers: vec![],
+        body: body.to_string(),
+        error: None,
+        time_elapsed: 5,
+        size: body.len() as u64,
+        scripts: None,
+        assertions: vec![],
+    }
+}
+
+#[tokio::test]
+async fn test_sequential_run_passes_captured_variables_along() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://prod.example.com/login"),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(ok(r#"{"access_token": "t-1"}"#)) }));
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::eq("https://prod.example.com/orders"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers.iter().any(|(n, v)| n == "X-Token" && v == "t-1")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(ok(r#"{"items": []}"#)) }));
+
+    let events = Mutex::new(Vec::new());
+    let summary = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &RunOptions {
+            endpoint_ids: vec!["login".to_string(), "orders".to_string()],
+            environment: Some("prod".to_string()),
+            ..Default::default()
+        },
+        &|progress| events.lock().unwrap().push(progress),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(summary.environment.as_deref(), Some("prod"));
+    assert_eq!((summary.total, summary.passed, summary.failed), (2, 1, 1));
+    assert_eq!(summary.results[0].endpoint_id, "login");
+    assert!(summary.results[0].passed);
+    assert!(!summary.results[1].passed);
+    assert_eq!(summary.results[1].status, Some(200));
+    assert_eq!(summary.variables.get("token").unwrap(), "t-1");
+
+    let events = events.into_inner().unwrap();
+    assert_eq!(events.len(), 6);
+    assert!(matches!(events[0], RunProgress::Started { total: 2 }));
+    assert!(matches!(
+        events[1],
+        RunProgress::RequestStarted { index: 0, .. }
+    ));
+    assert!(matches!(
+        events[4],
+        RunProgress::RequestFinished { index: 1, .. }
+    ));
+    assert!(matches!(events[5], RunProgress::Finished { .. }));
+}
+
+#[tokio::test]
+async fn test_run_sends_enabled_headers_and_resolved_params() {
+    let service: Service = serde_yaml::from_str(
+        r#"
+id: svc-search
+name: Search
+isAuthenticated: false
+directory: /tmp/search
+environments:
+  - name: dev
+    variables:
+      - { name: BASE_URL, value: "https://dev.example.com", enabled: true }
+      - { name: PAGE_SIZE, value: "25", enabled: true }
+endpoints:
+  - id: search
+    name: Search
+    method: POST
+    url: /search
+    params:
+      - { name: limit, value: "{{PAGE_SIZE}}", enabled: true }
+      - { name: debug, value: "1", enabled: false }
+    headers:
+      - { name: Content-Type, value: application/x-www-form-urlencoded, enabled: true }
+      - { name: X-Trace, value: "on", enabled: false }
+    body: q=rex
+"#,
+    )
+    .unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://dev.example.com/search"),
+            predicate::eq(vec![(
+                "Content-Type".to_string(),
+                "application/x-www-form-urlencoded".to_string(),
+            )]),
+            predicate::eq(Some("q=rex".to_string())),
+            predicate::eq(vec![("limit".to_string(), "25".to_string())]),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(ok("{}")) }));
+
+    let summary = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &RunOptions {
+            environment: Some("dev".to_string()),
+            ..Default::default()
+        },
+        &|_| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.passed, 1);
+
+    // The exchange keeps what went out, for exporting the run
+    let exchange = summary.results[0].exchange.as_ref().unwrap();
+    assert_eq!(exchange.url, "https://dev.example.com/search?limit=25");
+    assert_eq!(exchange.request_headers.len(), 1);
+    assert_eq!(exchange.request_body, "q=rex");
+    assert_eq!(exchange.response_body, "{}");
+    assert!(chrono::DateTime::parse_from_rfc3339(&exchange.started_at).is_ok());
+}
+
+#[tokio::test]
+async fn test_parallel_run_keeps_endpoint_order_and_reports_send_errors() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .returning(|_, url, _, _, _| {
+            let url = url.to_string();
+            Box::pin(async move {
+                if url.ends_with("/health") {
+                    Err("connection refused".to_string())
+                } else {
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    Ok(ok(r#"{"orders": []}"#))
+                }
+            })
+        });
+
+    let summary = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &RunOptions {
+            parallel: true,
+            ..Default::default()
+        },
+        &|_| {},
+    )
+    .await
+    .unwrap();
+
+    let ids: Vec<&str> = summary
+        .results
+        .iter()
+        .map(|r| r.endpoint_id.as_str())
+        .collect();
+    assert_eq!(ids, vec!["login", "orders", "health"]);
+    assert_eq!(summary.results[0].url, "{{BASE_URL}}/login");
+    assert!(summary.results[1].passed);
+    assert_eq!(summary.results[2].status, None);
+    assert_eq!(
+        summary.results[2].error.as_deref(),
+        Some("connection refused")
+    );
+    assert_eq!(summary.failed, 1);
+}
+
+#[tokio::test]
+async fn test_run_rejects_unknown_endpoints_and_environments() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mock_http = MockHttpClient::new();
+    let request_service = RequestService::new(&mock_http, None);
+
+    let unknown_endpoint = RunOptions {
+        endpoint_ids: vec!["missing".to_string()],
+        ..Default::default()
+    };
+    assert!(
+        run(&request_service, &service, &[], &unknown_endpoint, &|_| {})
+            .await
+            .is_err()
+    );
+
+    let unknown_environment = RunOptions {
+        environment: Some("staging".to_string()),
+        ..Default::default()
+    };
+    assert!(run(
+        &request_service,
+        &service,
+        &[],
+        &unknown_environment,
+        &|_| {}
+    )
+    .await
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_data_rows_run_endpoints_once_per_iteration() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .returning(|_, url, _, _, _| {
+            let url = url.to_string();
+            Box::pin(async move {
+                let mut response = ok("{}");
+                if url.starts_with("https://globex.") {
+                    response.status = 500;
+                }
+                Ok(response)
+            })
+        });
+    let row = |tenant: &str| HashMap::from([("tenant".to_string(), tenant.to_string())]);
+
+    let summary = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &RunOptions {
+            endpoint_ids: vec!["health".to_string()],
+            variables: HashMap::from([(
+                "BASE_URL".to_string(),
+                "https://{{tenant}}.example.com".to_string(),
+            )]),
+            data: vec![row("acme"), row("globex"), row("initech")],
+            ..Default::default()
+        },
+        &|_| {},
+    )
+    .await
+    .unwrap();
+
+    assert_eq!((summary.total, summary.failed), (3, 1));
+    let iterations: Vec<_> = summary.results.iter().map(|r| r.iteration).collect();
+    assert_eq!(iterations, vec![Some(0), Some(1), Some(2)]);
+    assert_eq!(summary.iterations.len(), 3);
+    assert_eq!(summary.iterations[1].row, row("globex"));
+    assert_eq!(
+        (summary.iterations[1].passed, summary.iterations[1].failed),
+        (0, 1)
+    );
+    assert_eq!(summary.iterations[2].passed, 1);
+}
+
+#[tokio::test]
+async fn test_script_variables_override_row_values() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+        )
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(ok(r#"{"access_token": "t-1"}"#)) }));
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::always(),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers.iter().any(|(n, v)| n == "X-Token" && v == "t-1")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(ok(r#"{"orders": []}"#)) }));
+
+    let summary = run(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &RunOptions {
+            endpoint_ids: vec!["login".to_string(), "orders".to_string()],
+            data: vec![HashMap::from([(
+                "token".to_string(),
+                "from-row".to_string(),
+            )])],
+            ..Default::default()
+        },
+        &|_| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(summary.passed, 2);
+}
+
+fn workflow(yaml: &str) -> Workflow {
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+#[tokio::test]
+async fn test_workflow_extracts_values_skips_and_branches() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(ok(r#"{"access_token": "t-9"}"#)) }));
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::eq("https://dev.example.com/orders"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers.iter().any(|(n, v)| n == "X-Token" && v == "t-9")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(ok(r#"{"orders": []}"#)) }));
+
+    let workflow = workflow(
+        r#"
+id: checkout
+name: Checkout
+variables: { role: viewer }
+steps:
+  - id: login
+    endpointId: login
+    extract:
+      - { variable: token, path: access_token }
+  - id: audit
+    endpointId: health
+    when: variables.role == "admin"
+  - id: list
+    endpointId: orders
+    when: steps.login.passed && steps.audit.skipped
+    next:
+      - { when: 'steps.list.json.orders.len() == 0', goto: end }
+  - id: never
+    endpointId: health
+"#,
+    );
+    let events = Mutex::new(Vec::new());
+    let summary = run_workflow(
+        &RequestService::new(&mock_http, None),
+        &service,
+        &[],
+        &workflow,
+        &WorkflowOptions::default(),
+        &|progress| events.lock().unwrap().push(progress),
+    )
+    .await
+    .unwrap();
+
+    let steps: Vec<_> = summary
+        .results
+        .iter()
+        .map(|r| (r.step_id.as_deref().unwrap(), r.skipped, r.passed))
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            ("login", false, true),
+            ("audit", true, false),
+            ("list", false, true)
+        ]
+    );
+    assert_eq!((summary.passed, summary.failed, summary.skipped), (2, 0, 1));
+    assert_eq!(summary.variables.get("token").unwrap(), "t-9");
+    // Skipped steps finish without starting
+    let events = events.into_inner().unwrap();
+    assert_eq!(events.len(), 1 + 2 + 3 + 1);
+}
+
+#[tokio::test]
+async fn test_workflow_stops_at_a_failed_step_unless_it_continues() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .returning(|_, url, _, _, _| {
+            let url = url.to_string();
+            Box::pin(async move {
+                let mut response = ok("{}");
+                if url.ends_with("/login") {
+                    response.status = 401;
+                }
+                Ok(response)
+            })
+        });
+    let request_service = RequestService::new(&mock_http, None);
+    let steps = |continue_on_failure: bool| {
+        workflow(&format!(
+            r#"
+id: flow
+name: Flow
+steps:
+  - {{ id: login, endpointId: login, continueOnFailure: {} }}
+  - {{ id: health, endpointId: health }}
+"#,
+            continue_on_failure
+        ))
+    };
+
+    let stopped = run_workflow(
+        &request_service,
+        &service,
+        &[],
+        &steps(false),
+        &WorkflowOptions::default(),
+        &|_| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!(stopped.results.len(), 1);
+    assert_eq!(stopped.failed, 1);
+
+    let continued = run_workflow(
+        &request_service,
+        &service,
+        &[],
+        &steps(true),
+        &WorkflowOptions::default(),
+        &|_| {},
+    )
+    .await
+    .unwrap();
+    assert_eq!((continued.passed, continued.failed), (1, 1));
+}
+
+#[tokio::test]
+async fn test_workflow_is_validated_before_sending() {
+    let service: Service = serde_yaml::from_str(SERVICE_YAML).unwrap();
+    let mock_http = MockHttpClient::new();
+    let request_service = RequestService::new(&mock_http, None);
+    let invalid = [
+        "{ id: a, name: A, steps: [{ id: x, endpointId: nope }] }",
+        "{ id: a, name: A, steps: [{ id: x, endpointId: login }, { id: x, endpointId: login }] }",
+        "{ id: a, name: A, steps: [{ id: x, endpointId: login, next: [{ goto: y }] }] }",
+    ];
+    for yaml in invalid {
+        let result = run_workflow(
+            &request_service,
+            &service,
+            &[],
+            &workflow(yaml),
+            &WorkflowOptions::default(),
+            &|_| {},
+        )
+        .await;
+        assert!(result.is_err(), "{}", yaml);
+    }
+}
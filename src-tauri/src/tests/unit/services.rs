@@ -1,6 +1,9 @@
 use crate::io::MockHttpClient;
 use crate::services::RequestService;
-use crate::types::{AuthConfig, BodyConfig, PreflightConfig, QResponse, RequestTab};
+use crate::types::{
+    AuthConfig, AuthMethod, BodyConfig, PreflightConfig, PreflightMode, QResponse, RequestTab,
+    Scripts,
+};
 use mockall::predicate;
 use std::collections::HashMap;
 
@@ -29,6 +32,8 @@ async fn test_preflight_token_caching() {
                     error: None,
                     time_elapsed: 10,
                     size: 40,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -58,6 +63,8 @@ async fn test_preflight_token_caching() {
                     error: None,
                     time_elapsed: 10,
                     size: 4,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -78,6 +85,10 @@ async fn test_preflight_token_caching() {
         cache_duration_unit: "seconds".to_string(),
         token_key: "access_token".to_string(),
         token_header: Some("Authorization".to_string()),
+        secret_token: false,
+        refresh_before_expiry: None,
+        steps: vec![],
+        mode: PreflightMode::Token,
     };
 
     let tab = RequestTab {
@@ -92,21 +103,17 @@ async fn test_preflight_token_caching() {
             r#type: "none".to_string(),
             content: "".to_string(),
         },
-        auth: AuthConfig {
-            r#type: "none".to_string(),
-            active: true,
-            bearer_token: "".to_string(),
-            basic_user: "".to_string(),
-            basic_pass: "".to_string(),
-            api_key_name: "".to_string(),
-            api_key_value: "".to_string(),
-            api_key_location: "header".to_string(),
-        },
+        auth: AuthConfig::default(),
         active_sub_tab: None,
         service_id: Some("test-service".to_string()),
         preflight,
         variables: None,
         is_edited: false,
+        environment: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+        service_scripts: Scripts::default(),
+        assertions: vec![],
     };
 
     // First request - should trigger preflight
@@ -143,6 +150,8 @@ async fn test_preflight_token_sharing_between_endpoints() {
                     error: None,
                     time_elapsed: 10,
                     size: 40,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -172,6 +181,8 @@ async fn test_preflight_token_sharing_between_endpoints() {
                     error: None,
                     time_elapsed: 1,
                     size: 2,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -192,6 +203,10 @@ async fn test_preflight_token_sharing_between_endpoints() {
         cache_duration_unit: "seconds".to_string(),
         token_key: "access_token".to_string(),
         token_header: Some("Authorization".to_string()),
+        secret_token: false,
+        refresh_before_expiry: None,
+        steps: vec![],
+        mode: PreflightMode::Token,
     };
 
     let mut tab_a = create_mock_tab("GET", "https://api.a.com", None);
@@ -234,6 +249,8 @@ async fn test_preflight_token_implicit_sharing() {
                     error: None,
                     time_elapsed: 10,
                     size: 40,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -263,6 +280,8 @@ async fn test_preflight_token_implicit_sharing() {
                     error: None,
                     time_elapsed: 1,
                     size: 2,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -283,6 +302,10 @@ async fn test_preflight_token_implicit_sharing() {
         cache_duration_unit: "seconds".to_string(),
         token_key: "access_token".to_string(),
         token_header: Some("Authorization".to_string()),
+        secret_token: false,
+        refresh_before_expiry: None,
+        steps: vec![],
+        mode: PreflightMode::Token,
     };
 
     let preflight_off = PreflightConfig {
@@ -334,6 +357,8 @@ async fn test_preflight_token_caching_no_service_id() {
                     error: None,
                     time_elapsed: 1,
                     size: 1,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -358,6 +383,8 @@ async fn test_preflight_token_caching_no_service_id() {
                     error: None,
                     time_elapsed: 1,
                     size: 1,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -383,6 +410,8 @@ async fn test_preflight_token_caching_no_service_id() {
                     error: None,
                     time_elapsed: 1,
                     size: 1,
+                    scripts: None,
+                    assertions: vec![],
                 })
             })
         });
@@ -408,6 +437,1081 @@ async fn test_preflight_token_caching_no_service_id() {
     // But since they have different URLs, they should have different cache keys.
 }
 
+#[tokio::test]
+async fn test_preflight_token_is_scoped_to_environment() {
+    let mut mock_http = MockHttpClient::new();
+
+    // Only the main request of tab B goes out, without a token from another environment
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::always(),
+            predicate::eq("https://api.prod.com"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                !headers.iter().any(|(n, _)| n == "Authorization")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| {
+            Box::pin(async {
+                Ok(QResponse {
+                    status: 200,
+                    status_text: "OK".to_string(),
+                    headers: vec![],
+                    body: "ok".to_string(),
+                    error: None,
+                    time_elapsed: 1,
+                    size: 2,
+                    scripts: None,
+                    assertions: vec![],
+                })
+            })
+        });
+
+    let dev_key = crate::domains::auth::cache::generate_key(
+        "service-env",
+        "dev",
+        "https://auth.example.com/token",
+        "POST",
+        "",
+        &[],
+    );
+    let prod_key = crate::domains::auth::cache::generate_key(
+        "service-env",
+        "prod",
+        "https://auth.example.com/token",
+        "POST",
+        "",
+        &[],
+    );
+    assert_ne!(dev_key, prod_key);
+    crate::domains::auth::cache::set_cached_token(
+        dev_key,
+        "dev_token".to_string(),
+        u64::MAX,
+        false,
+    );
+
+    let service = RequestService::new(&mock_http, None);
+    let mut tab = create_mock_tab("GET", "https://api.prod.com", None);
+    tab.service_id = Some("service-env".to_string());
+    tab.environment = Some("prod".to_string());
+    service.send_request(tab).await.unwrap();
+}
+
+fn token_response(token: &'static str) -> QResponse {
+    QResponse {
+        status: 200,
+        status_text: "OK".to_string(),
+        headers: vec![],
+        body: format!(r#"{{"access_token": "{}"}}"#, token),
+        error: None,
+        time_elapsed: 10,
+        size: 40,
+        scripts: None,
+        assertions: vec![],
+    }
+}
+
+#[tokio::test]
+async fn test_concurrent_preflights_share_one_fetch() {
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .returning(|_, _, _, _, _| {
+            Box::pin(async {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                Ok(token_response("single_flight_token"))
+            })
+        });
+
+    let mut config = create_mock_tab("GET", "", None).preflight;
+    config.enabled = true;
+    config.method = "POST".to_string();
+    config.url = "https://auth.single-flight.com/token".to_string();
+    let variables = HashMap::new();
+
+    let run = || {
+        crate::domains::auth::preflight::execute_preflight(
+            &mock_http,
+            "service-single-flight",
+            "dev",
+            &config,
+            &variables,
+            None,
+        )
+    };
+    let (a, b, c) = tokio::join!(run(), run(), run());
+    assert_eq!(a.unwrap(), "single_flight_token");
+    assert_eq!(b.unwrap(), "single_flight_token");
+    assert_eq!(c.unwrap(), "single_flight_token");
+    assert!(!crate::domains::auth::preflight::in_flight_keys()
+        .iter()
+        .any(|key| key.starts_with("service-single-flight:")));
+}
+
+#[tokio::test]
+async fn test_cancelled_preflights_leave_no_in_flight_entry() {
+    let mut mock_http = MockHttpClient::new();
+    mock_http.expect_send_request().returning(|_, _, _, _, _| {
+        Box::pin(async {
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            Ok(token_response("never_returned"))
+        })
+    });
+
+    let mut config = create_mock_tab("GET", "", None).preflight;
+    config.enabled = true;
+    config.method = "POST".to_string();
+    config.url = "https://auth.cancelled.com/token".to_string();
+    let variables = HashMap::new();
+
+    let run = || {
+        crate::domains::auth::preflight::execute_preflight(
+            &mock_http,
+            "service-cancelled",
+            "dev",
+            &config,
+            &variables,
+            None,
+        )
+    };
+    // One caller holds the lock and one waits on it when both are dropped
+    let both = async { tokio::join!(run(), run()) };
+    let timed_out = tokio::time::timeout(std::time::Duration::from_millis(50), both).await;
+    assert!(timed_out.is_err());
+    assert!(!crate::domains::auth::preflight::in_flight_keys()
+        .iter()
+        .any(|key| key.starts_with("service-cancelled:")));
+}
+
+#[tokio::test]
+async fn test_tokens_near_expiry_are_refreshed_in_background() {
+    let mut mock_http = MockHttpClient::new();
+    let mut seq = mockall::Sequence::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("first_token")) }));
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .in_sequence(&mut seq)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("refreshed_token")) }));
+
+    let mut config = create_mock_tab("GET", "", None).preflight;
+    config.enabled = true;
+    config.method = "POST".to_string();
+    config.url = "https://auth.refresh.com/token".to_string();
+    config.cache_duration = "30".to_string();
+    config.refresh_before_expiry = Some(60);
+    let variables = HashMap::new();
+
+    let token = crate::domains::auth::preflight::execute_preflight(
+        &mock_http,
+        "service-refresh",
+        "dev",
+        &config,
+        &variables,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(token, "first_token");
+
+    let refreshed =
+        crate::domains::auth::preflight::refresh_expiring_tokens(&mock_http, None).await;
+    assert_eq!(refreshed, 1);
+
+    let token = crate::domains::auth::preflight::execute_preflight(
+        &mock_http,
+        "service-refresh",
+        "dev",
+        &config,
+        &variables,
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(token, "refreshed_token");
+}
+
+#[tokio::test]
+async fn test_multi_step_preflight_chains_extracted_values() {
+    use crate::domains::service::endpoint::{PreflightExtraction, PreflightStep};
+    use crate::types::NameValue;
+
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request_without_redirects()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://auth.chain.com/login"),
+            predicate::always(),
+            predicate::eq(Some(r#"{"user": "alice"}"#.to_string())),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| {
+            Box::pin(async {
+                Ok(QResponse {
+                    status: 200,
+                    status_text: "OK".to_string(),
+                    headers: vec![NameValue {
+                        name: "Set-Cookie".to_string(),
+                        value: "csrf=xyz789; Path=/; HttpOnly".to_string(),
+                        enabled: true,
+                        secret_key: None,
+                    }],
+                    body: r#"{"session": {"id": "sess-123"}}"#.to_string(),
+                    error: None,
+                    time_elapsed: 5,
+                    size: 30,
+                    scripts: None,
+                    assertions: vec![],
+                })
+            })
+        });
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://auth.chain.com/token"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers.iter().any(|(n, v)| n == "X-CSRF" && v == "xyz789")
+            }),
+            predicate::eq(Some(r#"{"session": "sess-123"}"#.to_string())),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("chained_token")) }));
+
+    let mut config = create_mock_tab("GET", "", None).preflight;
+    config.enabled = true;
+    config.method = "POST".to_string();
+    config.url = "https://auth.chain.com/token".to_string();
+    config.body = r#"{"session": "{{sessionId}}"}"#.to_string();
+    config.headers = vec![NameValue {
+        name: "X-CSRF".to_string(),
+        value: "{{csrf}}".to_string(),
+        enabled: true,
+        secret_key: None,
+    }];
+    config.steps = vec![PreflightStep {
+        name: "Login".to_string(),
+        method: "POST".to_string(),
+        url: "https://auth.chain.com/login".to_string(),
+        body: r#"{"user": "{{user}}"}"#.to_string(),
+        body_type: "application/json".to_string(),
+        body_params: vec![],
+        headers: vec![],
+        extractions: vec![
+            PreflightExtraction {
+                variable: "sessionId".to_string(),
+                source: "body".to_string(),
+                path: "session.id".to_string(),
+            },
+            PreflightExtraction {
+                variable: "csrf".to_string(),
+                source: "cookie".to_string(),
+                path: "csrf".to_string(),
+            },
+        ],
+    }];
+    let variables = HashMap::from([("user".to_string(), "alice".to_string())]);
+
+    let result = crate::domains::auth::preflight::test_preflight(
+        &mock_http,
+        "service-chain",
+        "dev",
+        &config,
+        &variables,
+        None,
+    )
+    .await;
+
+    assert!(result.success, "{:?}", result.error);
+    assert_eq!(result.token.as_deref(), Some("chained_token"));
+    assert_eq!(result.steps.len(), 1);
+    assert_eq!(result.steps[0].name, "Login");
+    assert_eq!(result.steps[0].extracted["sessionId"], "sess-123");
+    assert_eq!(result.steps[0].extracted["csrf"], "xyz789");
+}
+
+#[tokio::test]
+async fn test_multi_step_preflight_reports_failed_extraction() {
+    use crate::domains::service::endpoint::{PreflightExtraction, PreflightStep};
+
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("unused")) }));
+
+    let mut config = create_mock_tab("GET", "", None).preflight;
+    config.enabled = true;
+    config.url = "https://auth.chain-fail.com/token".to_string();
+    config.steps = vec![PreflightStep {
+        name: "".to_string(),
+        method: "GET".to_string(),
+        url: "https://auth.chain-fail.com/session".to_string(),
+        body: "".to_string(),
+        body_type: "application/json".to_string(),
+        body_params: vec![],
+        headers: vec![],
+        extractions: vec![PreflightExtraction {
+            variable: "sessionId".to_string(),
+            source: "header".to_string(),
+            path: "X-Session".to_string(),
+        }],
+    }];
+
+    let result = crate::domains::auth::preflight::test_preflight(
+        &mock_http,
+        "service-chain-fail",
+        "dev",
+        &config,
+        &HashMap::new(),
+        None,
+    )
+    .await;
+
+    assert!(!result.success);
+    assert_eq!(
+        result.error.as_deref(),
+        Some("Preflight step 'Step 1' failed: 'X-Session' not found in response header")
+    );
+    assert_eq!(result.steps.len(), 1);
+    assert_eq!(result.request_url, "https://auth.chain-fail.com/session");
+}
+
+#[tokio::test]
+async fn test_multi_step_preflight_stops_at_failed_status() {
+    use crate::domains::service::endpoint::PreflightStep;
+
+    let mut mock_http = MockHttpClient::new();
+    // Only the failing step is sent, never the token request after it
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://auth.chain-status.com/login"),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| {
+            Box::pin(async {
+                let mut response = token_response("unused");
+                response.status = 401;
+                response.body = "bad credentials".to_string();
+                Ok(response)
+            })
+        });
+
+    let mut config = create_mock_tab("GET", "", None).preflight;
+    config.enabled = true;
+    config.url = "https://auth.chain-status.com/token".to_string();
+    config.steps = vec![PreflightStep {
+        name: "Login".to_string(),
+        method: "POST".to_string(),
+        url: "https://auth.chain-status.com/login".to_string(),
+        body: "".to_string(),
+        body_type: "application/json".to_string(),
+        body_params: vec![],
+        headers: vec![],
+        extractions: vec![],
+    }];
+
+    let result = crate::domains::auth::preflight::test_preflight(
+        &mock_http,
+        "service-chain-status",
+        "dev",
+        &config,
+        &HashMap::new(),
+        None,
+    )
+    .await;
+
+    assert!(!result.success);
+    assert_eq!(
+        result.error.as_deref(),
+        Some("Preflight step 'Login' failed: status 401")
+    );
+    assert_eq!(result.steps.len(), 1);
+    assert_eq!(result.response_status, 401);
+    assert_eq!(result.response_body, "bad credentials");
+}
+
+#[tokio::test]
+async fn test_services_sharing_an_auth_profile_share_one_token() {
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://idp.shared.com/token"),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("profile_token")) }));
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::always(),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers
+                    .iter()
+                    .any(|(n, v)| n == "Authorization" && v == "Bearer profile_token")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(2)
+        .returning(|_, _, _, _, _| {
+            Box::pin(async {
+                Ok(QResponse {
+                    status: 200,
+                    status_text: "OK".to_string(),
+                    headers: vec![],
+                    body: "ok".to_string(),
+                    error: None,
+                    time_elapsed: 1,
+                    size: 2,
+                    scripts: None,
+                    assertions: vec![],
+                })
+            })
+        });
+
+    let service = RequestService::new(&mock_http, None);
+    for (service_id, url) in [
+        ("service-orders", "https://orders.example.com"),
+        ("service-billing", "https://billing.example.com"),
+    ] {
+        let mut tab = create_mock_tab("GET", url, None);
+        tab.service_id = Some(service_id.to_string());
+        tab.auth_profile_id = Some("gateway-idp".to_string());
+        tab.preflight.enabled = true;
+        tab.preflight.method = "POST".to_string();
+        tab.preflight.url = "https://idp.shared.com/token".to_string();
+        tab.preflight.token_header = Some("Authorization".to_string());
+        service.send_request(tab).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_api_key_in_cookie_is_appended_to_cookie_header() {
+    use crate::domains::auth::{ApiKeyAuth, ApiKeyLocation, AuthMethod};
+
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::always(),
+            predicate::always(),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers
+                    .iter()
+                    .any(|(n, v)| n == "Cookie" && v == "theme=dark; session=abc123")
+            }),
+            predicate::always(),
+            predicate::eq(vec![]),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("ok")) }));
+
+    let mut tab = create_mock_tab("GET", "https://admin.example.com", None);
+    tab.headers.push(crate::types::Header {
+        name: "Cookie".to_string(),
+        value: "theme=dark".to_string(),
+        enabled: true,
+        secret_key: None,
+    });
+    tab.auth.method = AuthMethod::ApiKey(ApiKeyAuth {
+        api_key_name: "session".to_string(),
+        api_key_value: "abc123".to_string(),
+        api_key_location: ApiKeyLocation::Cookie,
+    });
+
+    RequestService::new(&mock_http, None)
+        .send_request(tab)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_cookie_session_preflight_sends_login_cookies() {
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request_without_redirects()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://app.example.com/login"),
+            predicate::always(),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| {
+            Box::pin(async {
+                let set_cookie = |value: &str| crate::types::Header {
+                    name: "Set-Cookie".to_string(),
+                    value: value.to_string(),
+                    enabled: true,
+                    secret_key: None,
+                };
+                Ok(QResponse {
+                    status: 302,
+                    status_text: "Found".to_string(),
+                    headers: vec![
+                        set_cookie("sid=abc123; Path=/; HttpOnly; Max-Age=600"),
+                        set_cookie("csrf=xyz; Path=/"),
+                        set_cookie("legacy=; Max-Age=0"),
+                    ],
+                    body: "<html>Redirecting</html>".to_string(),
+                    error: None,
+                    time_elapsed: 10,
+                    size: 24,
+                    scripts: None,
+                    assertions: vec![],
+                })
+            })
+        });
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::eq("https://app.example.com/dashboard"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers
+                    .iter()
+                    .any(|(n, v)| n == "Cookie" && v == "sid=abc123; csrf=xyz")
+                    && !headers.iter().any(|(n, _)| n == "Authorization")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(2)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("ok")) }));
+
+    let mut tab = create_mock_tab("GET", "https://app.example.com/dashboard", None);
+    tab.service_id = Some("service-cookie-session".to_string());
+    tab.preflight.enabled = true;
+    tab.preflight.method = "POST".to_string();
+    tab.preflight.url = "https://app.example.com/login".to_string();
+    tab.preflight.cache_duration = "derived".to_string();
+    tab.preflight.mode = PreflightMode::Cookies;
+
+    let service = RequestService::new(&mock_http, None);
+    service.send_request(tab.clone()).await.unwrap();
+    // The second request reuses the cached session instead of logging in again
+    service.send_request(tab).await.unwrap();
+
+    let cached = crate::domains::auth::cache::list_cached_tokens()
+        .into_iter()
+        .find(|t| t.service_id.as_deref() == Some("service-cookie-session"))
+        .unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    assert!(cached.expires_at <= now + 600 && cached.expires_at > now + 590);
+}
+
+#[tokio::test]
+async fn test_cached_session_is_only_reused_in_its_own_mode() {
+    let key = crate::domains::auth::cache::generate_key(
+        "service-session-mode",
+        "",
+        "https://app.example.com/login",
+        "POST",
+        "",
+        &[],
+    );
+    crate::domains::auth::cache::set_cached_session(
+        key,
+        "sid=abc123".to_string(),
+        u64::MAX,
+        false,
+        PreflightMode::Cookies,
+    );
+
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::eq("https://app.example.com/api"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                !headers
+                    .iter()
+                    .any(|(n, _)| n == "Authorization" || n == "Cookie")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("ok")) }));
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("GET"),
+            predicate::eq("https://app.example.com/dashboard"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers
+                    .iter()
+                    .any(|(n, v)| n == "Cookie" && v == "sid=abc123")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("ok")) }));
+
+    let service = RequestService::new(&mock_http, None);
+    // A cookie session is not sent as a bearer token
+    let mut tab = create_mock_tab("GET", "https://app.example.com/api", None);
+    tab.service_id = Some("service-session-mode".to_string());
+    service.send_request(tab.clone()).await.unwrap();
+
+    tab.url = "https://app.example.com/dashboard".to_string();
+    tab.preflight.mode = PreflightMode::Cookies;
+    service.send_request(tab).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pre_request_script_modifies_request_and_sets_variables() {
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::eq("POST"),
+            predicate::eq("https://api.example.com/orders"),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers.iter().any(|(n, v)| n == "X-Trace" && v == "svc")
+                    && headers
+                        .iter()
+                        .any(|(n, v)| n == "X-Signature" && v == "sig-42")
+            }),
+            predicate::eq(Some(r#"{"items":[1,2,3],"sig":"sig-42"}"#.to_string())),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("ok")) }));
+
+    let mut tab = create_mock_tab(
+        "POST",
+        "https://api.example.com/orders",
+        Some(HashMap::from([("seed".to_string(), "42".to_string())])),
+    );
+    tab.body.content = r#"{"items":[1,2,3],"sig":"{{signature}}"}"#.to_string();
+    tab.service_scripts.pre_request = r#"
+        request.headers.push(#{ name: "X-Trace", value: "svc", enabled: true });
+    "#
+    .to_string();
+    tab.scripts.pre_request = r#"
+        let body = parse_json(request.body.content);
+        variables.signature = "sig-" + variables.seed;
+        request.headers.push(#{ name: "X-Signature", value: variables.signature, enabled: true });
+        print(`signed ${body.items.len()} items`);
+    "#
+    .to_string();
+
+    let response = RequestService::new(&mock_http, None)
+        .send_request(tab)
+        .await
+        .unwrap();
+    let report = response.scripts.unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert_eq!(report.logs, vec!["signed 3 items".to_string()]);
+    assert_eq!(
+        report.variables,
+        HashMap::from([("signature".to_string(), "sig-42".to_string())])
+    );
+}
+
+#[tokio::test]
+async fn test_post_response_script_reads_response_and_reports_errors() {
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("abc123")) }));
+
+    let mut tab = create_mock_tab("GET", "https://api.example.com/login", None);
+    tab.service_scripts.post_response = r#"
+        if response.status == 200 {
+            variables.token = response.json.access_token;
+        }
+    "#
+    .to_string();
+    tab.scripts.post_response = r#"throw "unexpected " + response.status;"#.to_string();
+
+    let response = RequestService::new(&mock_http, None)
+        .send_request(tab)
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    let report = response.scripts.unwrap();
+    assert_eq!(report.variables.get("token").unwrap(), "abc123");
+    assert_eq!(report.errors.len(), 1);
+    assert!(
+        report.errors[0].starts_with("Endpoint post-response script:")
+            && report.errors[0].contains("unexpected 200"),
+        "{}",
+        report.errors[0]
+    );
+}
+
+#[test]
+fn test_scripts_are_sandboxed_and_bounded() {
+    use crate::domains::scripting::{run_pre_request, ScriptReport};
+
+    let mut tab = create_mock_tab("GET", "https://api.example.com", None);
+    let mut report = ScriptReport::default();
+    run_pre_request(
+        "Endpoint",
+        r#"import "secrets" as s; request.url = "https://evil.example.com";"#,
+        &mut tab,
+        &mut report,
+    );
+    run_pre_request(
+        "Endpoint",
+        "loop { request.url += \"\"; }",
+        &mut tab,
+        &mut report,
+    );
+    run_pre_request("Endpoint", "request.method = 42;", &mut tab, &mut report);
+
+    assert_eq!(tab.url, "https://api.example.com");
+    assert_eq!(tab.method, "GET");
+    assert_eq!(report.errors.len(), 3, "{:?}", report.errors);
+}
+
+#[tokio::test]
+async fn test_endpoint_assertions_are_evaluated_after_send() {
+    use crate::types::Assertion;
+
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("abc")) }));
+
+    let mut tab = create_mock_tab("GET", "https://api.example.com/token", None);
+    tab.assertions = vec![
+        Assertion::Status { equals: 200 },
+        Assertion::JsonPathEquals {
+            path: "$.access_token".to_string(),
+            value: serde_json::json!("xyz"),
+        },
+    ];
+
+    let response = RequestService::new(&mock_http, None)
+        .send_request(tab)
+        .await
+        .unwrap();
+    assert_eq!(response.assertions.len(), 2);
+    assert!(response.assertions[0].passed);
+    assert!(!response.assertions[1].passed);
+    assert_eq!(response.assertions[1].message, "$.access_token is \"abc\"");
+}
+
+#[tokio::test]
+async fn test_secret_placeholders_use_the_injected_secret_store() {
+    let mut mock_secrets = crate::io::MockSecretStore::new();
+    mock_secrets
+        .expect_get_secret()
+        .with(predicate::eq("api-token"))
+        .returning(|_| Ok("s3cr3t".to_string()));
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::always(),
+            predicate::always(),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers
+                    .iter()
+                    .any(|(n, v)| n == "Authorization" && v == "Bearer s3cr3t")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| {
+            Box::pin(async {
+                Ok(QResponse {
+                    status: 200,
+                    status_text: "OK".to_string(),
+                    headers: vec![],
+                    body: "".to_string(),
+                    error: None,
+                    time_elapsed: 1,
+                    size: 0,
+                    scripts: None,
+                    assertions: vec![],
+                })
+            })
+        });
+
+    let mut tab = create_mock_tab("GET", "https://api.example.com/me", None);
+    tab.headers.push(crate::types::Header {
+        name: "Authorization".to_string(),
+        value: "Bearer {{secret.api-token}}".to_string(),
+        enabled: true,
+        secret_key: None,
+    });
+    RequestService::new(&mock_http, None)
+        .with_secrets(&mock_secrets)
+        .send_request(tab)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_hmac_built_in_placeholders_are_not_shadowed_by_variables() {
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    let mut mock_secrets = crate::io::MockSecretStore::new();
+    mock_secrets
+        .expect_get_secret()
+        .with(predicate::eq("hmac-key"))
+        .returning(|_| Ok("partner-key".to_string()));
+    let sent = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    let captured = sent.clone();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .times(1)
+        .returning(move |_, _, headers, _, _| {
+            *captured.lock().unwrap() = headers;
+            Box::pin(async {
+                Ok(QResponse {
+                    status: 200,
+                    status_text: "OK".to_string(),
+                    headers: vec![],
+                    body: "".to_string(),
+                    error: None,
+                    time_elapsed: 1,
+                    size: 0,
+                    scripts: None,
+                    assertions: vec![],
+                })
+            })
+        });
+
+    let header = |name: &str, value: &str| crate::types::Header {
+        name: name.to_string(),
+        value: value.to_string(),
+        enabled: true,
+        secret_key: None,
+    };
+    let variables = HashMap::from([
+        ("timestamp".to_string(), "0".to_string()),
+        ("signature".to_string(), "forged".to_string()),
+        ("PARTNER".to_string(), "acme".to_string()),
+    ]);
+    let mut tab = create_mock_tab("GET", "https://pay.example.com/v1/orders", Some(variables));
+    tab.auth = AuthConfig {
+        active: true,
+        method: crate::types::AuthMethod::Hmac(crate::domains::auth::HmacAuth {
+            hmac_algorithm: "sha256".to_string(),
+            hmac_encoding: "hex".to_string(),
+            hmac_key_secret: "hmac-key".to_string(),
+            hmac_template: "{{PARTNER}} {{method}} {{path}} {{timestamp}}".to_string(),
+            hmac_headers: vec![
+                header("X-Timestamp", "{{timestamp}}"),
+                header("X-Signature", "{{PARTNER}}:{{signature}}"),
+            ],
+        }),
+    };
+    RequestService::new(&mock_http, None)
+        .with_secrets(&mock_secrets)
+        .send_request(tab)
+        .await
+        .unwrap();
+
+    let sent = sent.lock().unwrap();
+    let value = |name: &str| {
+        sent.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone())
+            .unwrap_or_else(|| panic!("no {} header", name))
+    };
+    let timestamp = value("X-Timestamp");
+    assert_ne!(timestamp, "0");
+    let mut mac = Hmac::<Sha256>::new_from_slice(b"partner-key").unwrap();
+    mac.update(format!("acme GET /v1/orders {}", timestamp).as_bytes());
+    assert_eq!(
+        value("X-Signature"),
+        format!("acme:{}", hex::encode(mac.finalize().into_bytes()))
+    );
+}
+
+#[tokio::test]
+async fn test_environment_auth_override_reaches_the_wire() {
+    let service: crate::types::Service = serde_yaml::from_str(
+        r#"
+id: s-env-wire
+name: Env Wire
+isAuthenticated: true
+directory: /tmp/env-wire
+auth:
+  type: bearer
+  active: true
+  bearerToken: service-token
+endpoints:
+  - id: e-orders
+    name: Orders
+    method: GET
+    url: https://api.example.com/orders
+    authenticated: true
+environments:
+  - name: DEV
+    variables: []
+    auth:
+      type: apikey
+      active: true
+      apiKeyName: X-API-Key
+      apiKeyValue: "{{DEV_KEY}}"
+      apiKeyLocation: header
+"#,
+    )
+    .unwrap();
+    let mut mock_http = MockHttpClient::new();
+    mock_http
+        .expect_send_request()
+        .with(
+            predicate::always(),
+            predicate::always(),
+            predicate::function(|headers: &Vec<(String, String)>| {
+                headers
+                    .iter()
+                    .any(|(n, v)| n == "X-API-Key" && v == "dev-key")
+                    && !headers.iter().any(|(n, _)| n == "Authorization")
+            }),
+            predicate::always(),
+            predicate::always(),
+        )
+        .times(1)
+        .returning(|_, _, _, _, _| Box::pin(async { Ok(token_response("unused")) }));
+
+    // The app opens endpoint tabs with a copy of the service's default auth
+    let variables = HashMap::from([("DEV_KEY".to_string(), "dev-key".to_string())]);
+    let mut tab = create_mock_tab("GET", "https://api.example.com/orders", Some(variables));
+    tab.service_id = Some(service.id.clone());
+    tab.endpoint_id = Some("e-orders".to_string());
+    tab.environment = Some("DEV".to_string());
+    tab.auth = service.auth.clone();
+    service.apply_defaults(&mut tab, None);
+
+    RequestService::new(&mock_http, None)
+        .send_request(tab)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_saved_endpoint_keeps_tab_auth_and_honours_unauthenticated() {
+    let service: crate::types::Service = serde_yaml::from_str(
+        r#"
+id: s-tab-auth
+name: Tab Auth
+isAuthenticated: true
+directory: /tmp/tab-auth
+auth:
+  type: bearer
+  active: true
+  bearerToken: service-token
+endpoints:
+  - id: e-orders
+    name: Orders
+    method: GET
+    url: https://api.example.com/orders
+    authenticated: true
+  - id: e-health
+    name: Health
+    method: GET
+    url: https://api.example.com/health
+    authenticated: false
+environments: []
+"#,
+    )
+    .unwrap();
+    let sent = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut mock_http = MockHttpClient::new();
+    let sink = sent.clone();
+    mock_http
+        .expect_send_request()
+        .times(3)
+        .returning(move |_, _, headers, _, _| {
+            sink.lock().unwrap().push(headers);
+            Box::pin(async { Ok(token_response("unused")) })
+        });
+    let request_service = RequestService::new(&mock_http, None);
+    let tab_for = |endpoint_id: &str, auth: AuthConfig| {
+        let mut tab = create_mock_tab("GET", "https://api.example.com/orders", None);
+        tab.service_id = Some(service.id.clone());
+        tab.endpoint_id = Some(endpoint_id.to_string());
+        tab.auth = auth;
+        service.apply_defaults(&mut tab, None);
+        tab
+    };
+
+    // Auth the user set on the tab wins over the service's
+    let own = AuthConfig {
+        active: true,
+        method: AuthMethod::Bearer {
+            bearer_token: "tab-token".to_string(),
+        },
+    };
+    request_service
+        .send_request(tab_for("e-orders", own))
+        .await
+        .unwrap();
+    // A tab opened with only the auth type inherits the service's settings
+    let blank = AuthConfig {
+        active: true,
+        method: AuthMethod::Bearer {
+            bearer_token: String::new(),
+        },
+    };
+    request_service
+        .send_request(tab_for("e-orders", blank.clone()))
+        .await
+        .unwrap();
+    // Endpoints marked unauthenticated get no service auth
+    request_service
+        .send_request(tab_for("e-health", blank))
+        .await
+        .unwrap();
+
+    let authorization = |headers: &Vec<(String, String)>| {
+        headers
+            .iter()
+            .find(|(n, _)| n == "Authorization")
+            .map(|(_, v)| v.clone())
+    };
+    let sent = sent.lock().unwrap();
+    assert_eq!(authorization(&sent[0]).as_deref(), Some("Bearer tab-token"));
+    assert_eq!(
+        authorization(&sent[1]).as_deref(),
+        Some("Bearer service-token")
+    );
+    assert_eq!(authorization(&sent[2]), None);
+}
+
 fn create_mock_tab(
     method: &str,
     url: &str,
@@ -425,16 +1529,7 @@ fn create_mock_tab(
             r#type: "none".to_string(),
             content: "".to_string(),
         },
-        auth: AuthConfig {
-            r#type: "none".to_string(),
-            active: true,
-            bearer_token: "".to_string(),
-            basic_user: "".to_string(),
-            basic_pass: "".to_string(),
-            api_key_name: "".to_string(),
-            api_key_value: "".to_string(),
-            api_key_location: "header".to_string(),
-        },
+        auth: AuthConfig::default(),
         active_sub_tab: None,
         service_id: None,
         preflight: PreflightConfig {
@@ -451,8 +1546,17 @@ fn create_mock_tab(
             cache_duration_unit: "seconds".to_string(),
             token_key: "access_token".to_string(),
             token_header: None,
+            secret_token: false,
+            refresh_before_expiry: None,
+            steps: vec![],
+            mode: PreflightMode::Token,
         },
         variables,
         is_edited: false,
+        environment: None,
+        auth_profile_id: None,
+        scripts: Scripts::default(),
+        service_scripts: Scripts::default(),
+        assertions: vec![],
     }
 }
@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-pub use crate::domains::auth::AuthConfig;
+pub use crate::domains::auth::{AuthConfig, AuthMethod};
 pub use crate::domains::auth::AuthType;
+pub use crate::domains::assertions::{Assertion, AssertionResult};
+pub use crate::domains::scripting::{ScriptReport, Scripts};
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -24,7 +26,9 @@ pub type Header = NameValue;
 
 pub use crate::domains::service::environment::EnvironmentConfig;
 
-pub use crate::domains::service::endpoint::{Endpoint, EndpointMetadata, PreflightConfig};
+pub use crate::domains::service::endpoint::{
+    Endpoint, EndpointMetadata, ExampleResponse, PreflightConfig, PreflightMode,
+};
 pub use crate::domains::service::service::{Service, ServiceStub};
 
 pub use crate::domains::git::GitStatus;
@@ -39,6 +43,12 @@ pub struct QResponse {
     pub error: Option<String>,
     pub time_elapsed: u64,
     pub size: u64,
+    /// Logs, errors and variables of the scripts that ran around the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scripts: Option<ScriptReport>,
+    /// Outcome of the endpoint's assertions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assertions: Vec<AssertionResult>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -55,6 +65,25 @@ pub struct PreflightTestResult {
     pub response_body: String,
     pub response_headers: Vec<Header>,
     pub time_elapsed: u64,
+    /// Requests that ran before the token request, in order
+    #[serde(default)]
+    pub steps: Vec<PreflightStepResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PreflightStepResult {
+    pub name: String,
+    pub request_url: String,
+    pub request_method: String,
+    pub request_headers: Vec<Header>,
+    pub request_body: String,
+    pub response_status: u16,
+    pub response_body: String,
+    pub response_headers: Vec<Header>,
+    pub time_elapsed: u64,
+    pub extracted: std::collections::HashMap<String, String>,
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -80,13 +109,40 @@ pub struct RequestTab {
     pub active_sub_tab: Option<String>,
     #[serde(default)]
     pub service_id: Option<String>,
+    /// Active environment of the service; scopes cached preflight tokens
+    #[serde(default)]
+    pub environment: Option<String>,
+    /// Shared auth profile; its token is cached once for every service using it
+    #[serde(default)]
+    pub auth_profile_id: Option<String>,
     pub preflight: PreflightConfig,
+    /// Scripts of the endpoint
+    #[serde(default)]
+    pub scripts: Scripts,
+    /// Scripts of the service, run before those of the endpoint
+    #[serde(default)]
+    pub service_scripts: Scripts,
+    /// Assertions of the endpoint, checked against the response
+    #[serde(default)]
+    pub assertions: Vec<Assertion>,
     #[serde(default)]
     pub variables: Option<std::collections::HashMap<String, String>>,
     #[serde(default)]
     pub is_edited: bool,
 }
 
+impl RequestTab {
+    /// Uses the inherited auth and preflight unless the request sets its own
+    pub fn inherit_auth(&mut self, auth: AuthConfig, preflight: PreflightConfig) {
+        if self.auth.method == AuthMethod::None {
+            self.auth = auth;
+        }
+        if !self.preflight.enabled {
+            self.preflight = preflight;
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SettingsTab {
@@ -131,6 +187,8 @@ pub struct HistoryEntry {
     pub time_elapsed: u64,
     pub size: u64,
     pub created_at: String,
+    #[serde(default)]
+    pub assertions: Vec<AssertionResult>,
 }
 
 #[cfg(test)]
@@ -146,7 +204,7 @@ mod tests {
             method: "GET".to_string(),
             url: "/test".to_string(),
             authenticated: true,
-            auth_type: "bearer".to_string(),
+            auth_type: AuthType::Bearer,
             metadata: EndpointMetadata {
                 version: "1.0".to_string(),
                 last_updated: 123456789,
@@ -178,9 +236,17 @@ mod tests {
                 cache_duration_unit: "seconds".to_string(),
                 token_key: "access_token".to_string(),
                 token_header: Some("Authorization".to_string()),
+                secret_token: false,
+                refresh_before_expiry: None,
+                steps: vec![],
+                mode: PreflightMode::Token,
             },
             last_version: 0,
             versions: vec![],
+            auth_profile_id: None,
+            scripts: Scripts::default(),
+            assertions: vec![],
+            examples: vec![],
         };
 
         let yaml = serde_yaml::to_string(&endpoint).unwrap();
@@ -200,6 +266,8 @@ mod tests {
             environments: vec![EnvironmentConfig {
                 name: "DEV".to_string(),
                 is_unsafe: false,
+                auth: None,
+                preflight: None,
                 variables: vec![NameValue {
                     name: "BASE_URL".to_string(),
                     value: "http://localhost:3000".to_string(),
@@ -210,14 +278,10 @@ mod tests {
             is_authenticated: true,
             auth_type: Some(AuthType::Bearer),
             auth: AuthConfig {
-                r#type: "bearer".to_string(),
                 active: true,
-                basic_user: "".to_string(),
-                basic_pass: "".to_string(),
-                bearer_token: "token".to_string(),
-                api_key_name: "".to_string(),
-                api_key_value: "".to_string(),
-                api_key_location: "header".to_string(),
+                method: AuthMethod::Bearer {
+                    bearer_token: "token".to_string(),
+                },
             },
             preflight: PreflightConfig {
                 enabled: false,
@@ -233,11 +297,17 @@ mod tests {
                 cache_duration_unit: "seconds".to_string(),
                 token_key: "".to_string(),
                 token_header: None,
+                secret_token: false,
+                refresh_before_expiry: None,
+                steps: vec![],
+                mode: PreflightMode::Token,
             },
             endpoints: vec![],
             directory: "/tmp".to_string(),
             selected_environment: Some("DEV".to_string()),
             git_url: None,
+            auth_profile_id: None,
+            scripts: Scripts::default(),
         };
 
         let yaml = serde_yaml::to_string(&service).unwrap();